@@ -1,4 +1,12 @@
-from .hydro_rs import __version__, calibration, climate, metrics, pet, snow
+from .hydro_rs import (
+    __version__,
+    calibration,
+    climate,
+    metrics,
+    pet,
+    signatures,
+    snow,
+)
 
 __all__ = [
     "__version__",
@@ -6,5 +14,6 @@ __all__ = [
     "climate",
     "metrics",
     "pet",
+    "signatures",
     "snow",
 ]
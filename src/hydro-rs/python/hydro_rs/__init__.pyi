@@ -1,4 +1,4 @@
-from . import calibration, climate, metrics, pet, snow
+from . import calibration, climate, metrics, pet, signatures, snow
 from .model import Data, Metadata
 
 __version__: str
@@ -11,5 +11,6 @@ __all__ = [
     "climate",
     "metrics",
     "pet",
+    "signatures",
     "snow",
 ]
@@ -1,5 +1,41 @@
-from . import sce
+from . import (
+    amalgam,
+    archive,
+    bayes_opt,
+    dds,
+    dream,
+    glue,
+    lhs,
+    mocom,
+    multi_objective,
+    nelder_mead,
+    nsga2,
+    random_search,
+    regional,
+    regionalization,
+    sce,
+    sensitivity,
+    split_sample,
+    surrogate,
+)
 
 __all__ = [
+    "amalgam",
+    "archive",
+    "bayes_opt",
+    "dds",
+    "dream",
+    "glue",
+    "lhs",
+    "mocom",
+    "multi_objective",
+    "nelder_mead",
+    "nsga2",
+    "random_search",
+    "regional",
+    "regionalization",
     "sce",
+    "sensitivity",
+    "split_sample",
+    "surrogate",
 ]
@@ -0,0 +1,26 @@
+from typing import final
+
+import numpy as np
+import numpy.typing as npt
+
+@final
+class Archive:
+    def __new__(
+        cls,
+        epsilons: list[float],
+        maximize: list[bool] | None = None,
+    ) -> Archive: ...
+    def push(
+        self,
+        params: npt.NDArray[np.float64],
+        objectives: npt.NDArray[np.float64],
+    ) -> bool: ...
+    def push_batch(
+        self,
+        params: npt.NDArray[np.float64],
+        objectives: npt.NDArray[np.float64],
+    ) -> int: ...
+    def contents(
+        self,
+    ) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
+    def __len__(self) -> int: ...
@@ -0,0 +1,35 @@
+from typing import final
+
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+@final
+class Dds:
+    def __new__(
+        cls,
+        climate_model: str,
+        snow_model: str | None,
+        objective: str,
+        max_evaluations: int,
+        seed: int,
+        perturbation: float = 0.2,
+    ) -> Dds: ...
+    def init(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> None: ...
+    def step(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> tuple[
+        bool,
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+    ]: ...
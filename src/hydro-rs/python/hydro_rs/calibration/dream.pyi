@@ -0,0 +1,44 @@
+from typing import final
+
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+@final
+class Dream:
+    def __new__(
+        cls,
+        climate_model: str,
+        snow_model: str | None,
+        likelihood: str,
+        max_evaluations: int,
+        seed: int,
+        n_chains: int = 3,
+        n_pairs: int = 1,
+    ) -> Dream: ...
+    def init(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> None: ...
+    def step(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> tuple[
+        bool,
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+    ]: ...
+    def posterior(
+        self, burn_in: float = 0.5
+    ) -> tuple[
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+        float,
+        npt.NDArray[np.float64],
+    ]: ...
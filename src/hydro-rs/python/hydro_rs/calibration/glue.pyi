@@ -0,0 +1,32 @@
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+def run(
+    climate_model: str,
+    snow_model: str | None,
+    objective: str,
+    data: Data,
+    metadata: Metadata,
+    observations: npt.NDArray[np.float64],
+    n_samples: int,
+    threshold: float,
+    seed: int,
+    sampling: str = "lhs",
+    quantiles: list[float] = [0.05, 0.5, 0.95],
+) -> tuple[
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+]: ...
+def predict(
+    climate_model: str,
+    snow_model: str | None,
+    data: Data,
+    metadata: Metadata,
+    params: npt.NDArray[np.float64],
+    likelihoods: npt.NDArray[np.float64] | None = None,
+    quantiles: list[float] = [0.05, 0.5, 0.95],
+) -> npt.NDArray[np.float64]: ...
@@ -0,0 +1,17 @@
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+def run(
+    climate_model: str,
+    snow_model: str | None,
+    criteria: list[tuple[str, str]],
+    data: Data,
+    metadata: Metadata,
+    observations: npt.NDArray[np.float64],
+    max_evaluations: int,
+    seed: int,
+    algorithm: str = "nsga2",
+    population_size: int = 100,
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
@@ -0,0 +1,34 @@
+from typing import final
+
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+@final
+class Nsga2:
+    def __new__(
+        cls,
+        climate_model: str,
+        snow_model: str | None,
+        criteria: list[tuple[str, str]],
+        max_evaluations: int,
+        seed: int,
+        population_size: int = 100,
+    ) -> Nsga2: ...
+    def init(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> None: ...
+    def step(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> tuple[
+        bool,
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+    ]: ...
@@ -0,0 +1,16 @@
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+def run(
+    climate_model: str,
+    snow_model: str | None,
+    objective: str,
+    data: Data,
+    metadata: Metadata,
+    observations: npt.NDArray[np.float64],
+    n_samples: int,
+    seed: int,
+    mode: str = "uniform",
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
@@ -0,0 +1,17 @@
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+def run(
+    climate_model: str,
+    snow_model: str | None,
+    objective: str,
+    data: list[Data],
+    metadata: list[Metadata],
+    observations: list[npt.NDArray[np.float64]],
+    max_evaluations: int,
+    seed: int,
+    aggregation: str = "mean",
+    perturbation: float = 0.2,
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
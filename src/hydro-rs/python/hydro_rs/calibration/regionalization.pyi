@@ -0,0 +1,27 @@
+from typing import final
+
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+@final
+class Regionalization:
+    def __new__(
+        cls,
+        climate_model: str,
+        snow_model: str | None,
+        objective: str,
+        max_evaluations: int,
+        seed: int,
+        transfer: str = "linear",
+        aggregation: str = "mean",
+        perturbation: float = 0.2,
+    ) -> Regionalization: ...
+    def calibrate(
+        self,
+        data: list[Data],
+        metadata: list[Metadata],
+        observations: list[npt.NDArray[np.float64]],
+    ) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
+    def predict(self, metadata: Metadata) -> npt.NDArray[np.float64]: ...
@@ -1,4 +1,5 @@
-from typing import final
+import os
+from typing import Callable, Literal, final
 
 import numpy as np
 import numpy.typing as npt
@@ -11,13 +12,48 @@ class Sce:
         cls,
         climate_model: str,
         snow_model: str | None,
-        objective: str,
+        objective: str
+        | Callable[
+            [npt.NDArray[np.float64], npt.NDArray[np.float64]], float
+        ],
         n_complexes: int,
         k_stop: int,
         p_convergence_threshold: float,
         geometric_range_threshold: float,
         max_evaluations: int,
         seed: int,
+        n_samples: int | None = None,
+        polish_evaluations: int = 0,
+        restart_fraction: float = 0.0,
+        minimize: bool = True,
+        snow_observations: npt.NDArray[np.float64] | None = None,
+        snow_variable: Literal["swe", "snow_cover"] = "swe",
+        snow_weight: float = 0.5,
+        gauges: list[tuple[npt.NDArray[np.float64], float]] | None = None,
+        transforms: list[Literal["linear", "log", "logit"]] | None = None,
+        fixed_params: dict[int, float] | None = None,
+        initial_params: npt.NDArray[np.float64] | None = None,
+        initial_population: npt.NDArray[np.float64] | None = None,
+        trace: bool = False,
+        trace_size: int | None = None,
+        validation_range: tuple[int, int] | None = None,
+        validation_observations: npt.NDArray[np.float64] | None = None,
+        validation_patience: int = 10,
+        warmup: int = 0,
+        transformation: str = "none",
+        flow_weighting: str = "none",
+        events: float | None = None,
+        event_separation: int = 5,
+        event_padding: int = 2,
+        seasonal_weights: list[float] | None = None,
+        timestep_weights: npt.NDArray[np.float64] | None = None,
+        temporal_aggregation: str = "none",
+        prior_params: npt.NDArray[np.float64] | None = None,
+        prior_weight: float = 1.0,
+        volume_tolerance: float | None = None,
+        volume_weight: float = 1.0,
+        cache_size: int | None = None,
+        cache_resolution: float = 1e-6,
     ) -> Sce: ...
     def init(
         self,
@@ -25,14 +61,55 @@ class Sce:
         metadata: Metadata,
         observations: npt.NDArray[np.float64],
     ) -> None: ...
+    def refine(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+        shrink_factor: float = 0.2,
+    ) -> None: ...
     def step(
         self,
         data: Data,
         metadata: Metadata,
         observations: npt.NDArray[np.float64],
+        callback: Callable[[int, int, float, float], object] | None = None,
     ) -> tuple[
         bool,
         npt.NDArray[np.float64],
         npt.NDArray[np.float64],
         npt.NDArray[np.float64],
     ]: ...
+    def run(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+        callback: Callable[[int, int, float, float], object] | None = None,
+    ) -> tuple[
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+    ]: ...
+    def evaluations(self) -> dict[str, int]: ...
+    def validation_objective(self) -> float | None: ...
+    def trace(self) -> dict[str, npt.NDArray[np.generic]] | None: ...
+    def save(self, path: str | os.PathLike[str]) -> None: ...
+    @staticmethod
+    def load(path: str | os.PathLike[str]) -> Sce: ...
+
+def run_replicates(
+    climate_model: str,
+    snow_model: str | None,
+    objective: str,
+    data: Data,
+    metadata: Metadata,
+    observations: npt.NDArray[np.float64],
+    n_replicates: int,
+    n_complexes: int,
+    k_stop: int,
+    p_convergence_threshold: float,
+    geometric_range_threshold: float,
+    max_evaluations: int,
+    seed: int,
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64], int]: ...
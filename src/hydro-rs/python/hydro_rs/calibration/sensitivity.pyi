@@ -0,0 +1,40 @@
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+def fast(
+    climate_model: str,
+    snow_model: str | None,
+    data: Data,
+    metadata: Metadata,
+    observations: npt.NDArray[np.float64],
+    n_samples: int,
+    seed: int,
+    interference: int = 4,
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
+def identifiability(
+    params: npt.NDArray[np.float64],
+    objectives: npt.NDArray[np.float64],
+    objective: str,
+    top_fraction: float = 0.1,
+) -> tuple[
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+]: ...
+def dotty(
+    climate_model: str,
+    snow_model: str | None,
+    objective: str,
+    data: Data,
+    metadata: Metadata,
+    observations: npt.NDArray[np.float64],
+    n_samples: int,
+    seed: int,
+    top_fraction: float = 0.1,
+) -> tuple[
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+]: ...
@@ -0,0 +1,21 @@
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+def run(
+    climate_model: str,
+    snow_model: str | None,
+    objective: str,
+    data: Data,
+    metadata: Metadata,
+    observations: npt.NDArray[np.float64],
+    n_complexes: int,
+    k_stop: int,
+    p_convergence_threshold: float,
+    geometric_range_threshold: float,
+    max_evaluations: int,
+    seed: int,
+    split: int | None = None,
+    n_folds: int | None = None,
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
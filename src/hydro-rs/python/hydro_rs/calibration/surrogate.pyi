@@ -0,0 +1,48 @@
+from typing import Callable, final
+
+import numpy as np
+import numpy.typing as npt
+
+from ..model import Data, Metadata
+
+@final
+class Surrogate:
+    def __new__(
+        cls,
+        climate_model: str,
+        snow_model: str | None,
+        objective: str,
+        max_evaluations: int,
+        seed: int,
+    ) -> Surrogate: ...
+    def init(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> None: ...
+    def step(
+        self,
+        data: Data,
+        metadata: Metadata,
+        observations: npt.NDArray[np.float64],
+    ) -> tuple[
+        bool,
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+        npt.NDArray[np.float64],
+    ]: ...
+
+def minimize(
+    objective: Callable[[npt.NDArray[np.float64]], float],
+    lower_bounds: npt.NDArray[np.float64],
+    upper_bounds: npt.NDArray[np.float64],
+    max_evaluations: int,
+    seed: int,
+    n_initial: int | None = None,
+) -> tuple[
+    npt.NDArray[np.float64],
+    float,
+    npt.NDArray[np.float64],
+    npt.NDArray[np.float64],
+]: ...
@@ -1,5 +1,23 @@
-from . import gr4j
+from . import (
+    awbm,
+    bucket,
+    gr2m,
+    gr4h,
+    gr4j,
+    gr5j,
+    pdm,
+    thornthwaite_mather,
+    topmodel,
+)
 
 __all__ = [
+    "awbm",
+    "bucket",
+    "gr2m",
+    "gr4h",
     "gr4j",
+    "gr5j",
+    "pdm",
+    "thornthwaite_mather",
+    "topmodel",
 ]
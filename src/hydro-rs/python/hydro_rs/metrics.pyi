@@ -4,14 +4,255 @@ import numpy.typing as npt
 def calculate_rmse(
     observations: npt.NDArray[np.float64],
     simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_weighted_rmse(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    weights: npt.NDArray[np.float64],
+    skip_nan: bool = False,
 ) -> float: ...
 
 def calculate_nse(
     observations: npt.NDArray[np.float64],
     simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
 ) -> float: ...
 
 def calculate_kge(
     observations: npt.NDArray[np.float64],
     simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
 ) -> float: ...
+
+def calculate_kge_components(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> tuple[float, float, float, float]: ...
+
+def calculate_nse_components(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> tuple[float, float, float, float]: ...
+
+def calculate_kge_2012(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_log_nse(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    epsilon: float | None = None,
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_nse_sqrt(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_nse_inv(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    epsilon: float | None = None,
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_pbias(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_mae(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_mape(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    epsilon: float | None = None,
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_smape(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    epsilon: float | None = None,
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_rsr(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_volumetric_efficiency(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_index_of_agreement(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_refined_index_of_agreement(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_r2(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_pearson(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_spearman(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_bounded_nse(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_bounded_kge(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def calculate_persistence_index(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> float: ...
+
+def summarize(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    skip_nan: bool = False,
+) -> dict[str, float]: ...
+
+def calculate_split_kge(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.uintp],
+    start_day: int = 274,
+) -> tuple[float, float]: ...
+
+def calculate_seasonal_metrics(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.uintp],
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
+
+def calculate_climatology(
+    observations: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.uintp],
+) -> npt.NDArray[np.float64]: ...
+
+def calculate_benchmark_nse(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.uintp],
+) -> float: ...
+
+def calculate_benchmark_kge(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.uintp],
+) -> float: ...
+
+def find_peaks(
+    observations: npt.NDArray[np.float64],
+    quantile: float = 0.95,
+    separation: int = 7,
+) -> npt.NDArray[np.uintp]: ...
+
+def calculate_peak_errors(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    quantile: float = 0.95,
+    separation: int = 7,
+) -> tuple[float, float, float]: ...
+
+def calculate_peak_timing(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    quantile: float = 0.95,
+    separation: int = 7,
+) -> tuple[float, float]: ...
+
+def calculate_lag(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    max_lag: int = 10,
+) -> int: ...
+
+def calculate_dtw(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    window: int = 3,
+) -> tuple[float, float]: ...
+
+def event_table(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    method: str = "threshold",
+    quantile: float = 0.95,
+    separation: int = 7,
+) -> dict[str, npt.NDArray[np.uintp] | npt.NDArray[np.float64]]: ...
+
+def calculate_exceedance_probabilities(
+    ensemble: npt.NDArray[np.float64],
+    threshold: float,
+) -> npt.NDArray[np.float64]: ...
+
+def calculate_brier_score(
+    observations: npt.NDArray[np.float64],
+    probabilities: npt.NDArray[np.float64],
+    threshold: float,
+) -> float: ...
+
+def calculate_brier_skill_score(
+    observations: npt.NDArray[np.float64],
+    probabilities: npt.NDArray[np.float64],
+    threshold: float,
+) -> float: ...
+
+def calculate_rank_histogram(
+    observations: npt.NDArray[np.float64],
+    ensemble: npt.NDArray[np.float64],
+) -> npt.NDArray[np.uintp]: ...
+
+def calculate_reliability(
+    observations: npt.NDArray[np.float64],
+    probabilities: npt.NDArray[np.float64],
+    threshold: float,
+    n_bins: int = 10,
+) -> tuple[
+    npt.NDArray[np.float64], npt.NDArray[np.float64], npt.NDArray[np.uintp]
+]: ...
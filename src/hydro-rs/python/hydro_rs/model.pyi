@@ -12,3 +12,23 @@ class Data(Protocol):
 class Metadata(Protocol):
     elevation_layers: npt.NDArray[np.float64]
     median_elevation: float
+    # latitude in degrees, only required by radiation-based models
+    lat: float
+    # glacierized fraction of the catchment (0-1), defaults to 0
+    glacier_fraction: float
+    # lapse rates used to extrapolate the forcings to the elevation layers
+    # (°C/100m and 1/m), defaulting to the CemaNeige values when None
+    temperature_lapse_rate: float | None
+    precipitation_gradient: float | None
+    # snowpack loss to sublimation, as a fraction of the snowpack per day
+    # and of the pet, both defaulting to 0 (no sublimation)
+    sublimation_fraction: float
+    sublimation_pet_factor: float
+    # snow water equivalent (mm) above which a layer is fully snow covered,
+    # defaults to 10
+    snow_cover_threshold: float
+    # timestep in hours, only used by gr4h, which is hourly when None
+    timestep: float | None
+    # topographic index histogram, only required by topmodel
+    topographic_index: npt.NDArray[np.float64] | None
+    topographic_index_fractions: npt.NDArray[np.float64] | None
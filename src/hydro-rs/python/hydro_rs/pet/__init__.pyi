@@ -1,5 +1,31 @@
-from . import oudin
+import numpy as np
+import numpy.typing as npt
+
+from . import (
+    blaney_criddle,
+    hamon,
+    hargreaves,
+    mcguinness_bordne,
+    oudin,
+    priestley_taylor,
+)
 
 __all__ = [
+    "blaney_criddle",
+    "hamon",
+    "hargreaves",
+    "mcguinness_bordne",
     "oudin",
+    "priestley_taylor",
+    "simulate",
 ]
+
+def simulate(
+    model: str,
+    temperature: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.float64],
+    latitude: float,
+    temperature_min: npt.NDArray[np.float64] | None = None,
+    temperature_max: npt.NDArray[np.float64] | None = None,
+    net_radiation: npt.NDArray[np.float64] | None = None,
+) -> npt.NDArray[np.float64]: ...
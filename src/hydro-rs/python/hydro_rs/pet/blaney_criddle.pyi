@@ -0,0 +1,9 @@
+import numpy as np
+import numpy.typing as npt
+
+def simulate(
+    temperature: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.float64],
+    latitude: float,
+    coefficient: float = 1.0,
+) -> npt.NDArray[np.float64]: ...
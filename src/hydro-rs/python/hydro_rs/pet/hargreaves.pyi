@@ -0,0 +1,10 @@
+import numpy as np
+import numpy.typing as npt
+
+def simulate(
+    temperature_min: npt.NDArray[np.float64],
+    temperature_max: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.float64],
+    latitude: float,
+    temperature_mean: npt.NDArray[np.float64] | None = None,
+) -> npt.NDArray[np.float64]: ...
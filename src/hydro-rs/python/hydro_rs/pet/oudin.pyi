@@ -5,4 +5,11 @@ def simulate(
     temperature: npt.NDArray[np.float64],
     day_of_year: npt.NDArray[np.float64],
     latitude: float,
+    timestep: float = 24.0,
+) -> npt.NDArray[np.float64]: ...
+def simulate_batch(
+    temperature: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.float64],
+    latitude: npt.NDArray[np.float64],
+    timestep: float = 24.0,
 ) -> npt.NDArray[np.float64]: ...
@@ -0,0 +1,10 @@
+import numpy as np
+import numpy.typing as npt
+
+def simulate(
+    temperature: npt.NDArray[np.float64],
+    day_of_year: npt.NDArray[np.float64],
+    latitude: float,
+    net_radiation: npt.NDArray[np.float64] | None = None,
+    alpha: float = 1.26,
+) -> npt.NDArray[np.float64]: ...
@@ -0,0 +1,31 @@
+import numpy as np
+import numpy.typing as npt
+
+from .model import Data
+
+def calculate_runoff_ratio(
+    data: Data,
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+) -> tuple[float, float, float]: ...
+
+def calculate_flashiness(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+) -> tuple[float, float, float]: ...
+
+def calculate_rising_limb_density(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+) -> tuple[float, float, float]: ...
+
+def calculate_falling_limb_density(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+) -> tuple[float, float, float]: ...
+
+def calculate_autocorrelation(
+    observations: npt.NDArray[np.float64],
+    simulations: npt.NDArray[np.float64],
+    lag: int = 1,
+) -> tuple[float, float, float]: ...
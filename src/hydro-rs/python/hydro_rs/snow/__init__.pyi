@@ -1,5 +1,9 @@
-from . import cemaneige
+from . import cemaneige, cemaneige_lh, glacier, hbv, hock
 
 __all__ = [
     "cemaneige",
+    "cemaneige_lh",
+    "glacier",
+    "hbv",
+    "hock",
 ]
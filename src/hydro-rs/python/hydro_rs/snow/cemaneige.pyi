@@ -9,3 +9,13 @@ def simulate(
     data: Data,
     metadata: Metadata,
 ) -> npt.NDArray[np.float64]: ...
+def simulate_with_snow_cover(
+    params: npt.NDArray[np.float64],
+    data: Data,
+    metadata: Metadata,
+) -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]: ...
+def simulate_states(
+    params: npt.NDArray[np.float64],
+    data: Data,
+    metadata: Metadata,
+) -> dict[str, npt.NDArray[np.float64]]: ...
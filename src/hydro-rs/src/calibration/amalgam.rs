@@ -0,0 +1,531 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use ndarray_rand::rand_distr::StandardNormal;
+use ndarray_rand::RandomExt;
+use numpy::{PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::calibration::nsga2::{crossover, mutate, select_by_tournament};
+use crate::calibration::utils::{
+    build_model, compute_cholesky, evaluate_population_criteria,
+    parse_criteria, sample_latin_hypercube, select_by_front,
+    CalibrationParams, Criterion,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+#[derive(Debug, Clone, Copy)]
+enum Method {
+    Nsga2,
+    Pso,
+    De,
+    Metropolis,
+}
+
+const METHODS: [Method; 4] =
+    [Method::Nsga2, Method::Pso, Method::De, Method::Metropolis];
+
+impl Method {
+    fn name(self) -> &'static str {
+        match self {
+            Method::Nsga2 => "nsga2",
+            Method::Pso => "pso",
+            Method::De => "de",
+            Method::Metropolis => "metropolis",
+        }
+    }
+}
+
+struct AmalgamParams {
+    pub population: Array2<f64>,
+    pub values: Array2<f64>,
+    pub velocities: Array2<f64>,
+    pub ranks: Vec<usize>,
+    pub crowding_distances: Vec<f64>,
+    pub counts: [usize; 4],
+    pub criteria: Vec<Criterion>,
+    pub n_calls: usize,
+    pub max_evaluations: usize,
+}
+
+/// Multi-algorithm, genetically adaptive multi-objective search (Vrugt and
+/// Robinson, 2007).
+///
+/// Each step creates as many children as there are points in the population
+/// with NSGA-II, particle swarm, differential evolution and adaptive
+/// Metropolis operators, and keeps the best half of the parents and children
+/// ranked by Pareto front and crowding distance. The number of children of
+/// each operator is then made proportional to the fraction of its children
+/// that survived. Returns the current non-dominated front with the value of
+/// each criterion.
+#[pyclass(module = "hydro_rs.calibration.amalgam", unsendable)]
+pub struct Amalgam {
+    calibration_params: CalibrationParams,
+    amalgam_params: AmalgamParams,
+}
+
+impl Amalgam {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        criteria: Vec<Criterion>,
+        population_size: usize,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+
+        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // every operator creates at least one child
+        let population_size = population_size.max(2 * METHODS.len());
+        let population = sample_latin_hypercube(
+            population_size,
+            lower_bounds.view(),
+            upper_bounds.view(),
+            &mut rng,
+        );
+        let mut counts = [population_size / METHODS.len(); 4];
+        counts[0] += population_size % METHODS.len();
+
+        let amalgam_params = AmalgamParams {
+            population,
+            values: Array2::zeros((population_size, criteria.len())),
+            velocities: Array2::zeros((population_size, params.len())),
+            ranks: vec![0; population_size],
+            crowding_distances: vec![0.; population_size],
+            counts,
+            criteria: criteria.clone(),
+            n_calls: 0,
+            max_evaluations,
+        };
+        let calibration_params = CalibrationParams {
+            params,
+            simulate,
+            lower_bounds,
+            upper_bounds,
+            objective: criteria[0].objective,
+            rng,
+            done: false,
+        };
+
+        Ok(Amalgam {
+            calibration_params,
+            amalgam_params,
+        })
+    }
+
+    pub fn init<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        let values = evaluate_population_criteria(
+            &self.calibration_params.simulate,
+            data,
+            metadata,
+            observations,
+            &self.amalgam_params.population,
+            &self.amalgam_params.criteria,
+        )?;
+        self.amalgam_params.n_calls += values.nrows();
+        let population = std::mem::take(&mut self.amalgam_params.population);
+        let velocities = std::mem::take(&mut self.amalgam_params.velocities);
+        let origins = vec![None; population.nrows()];
+        self.select_survivors(population, values, velocities, origins);
+        Ok(())
+    }
+
+    pub fn step<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(bool, Array2<f64>, Array2<f64>), Error> {
+        if !self.calibration_params.done {
+            let (children, children_velocities, children_origins) =
+                self.generate_children();
+            let children_values = evaluate_population_criteria(
+                &self.calibration_params.simulate,
+                data,
+                metadata,
+                observations,
+                &children,
+                &self.amalgam_params.criteria,
+            )?;
+            self.amalgam_params.n_calls += children.nrows();
+
+            let population = ndarray::concatenate(
+                Axis(0),
+                &[self.amalgam_params.population.view(), children.view()],
+            )
+            .unwrap();
+            let values = ndarray::concatenate(
+                Axis(0),
+                &[self.amalgam_params.values.view(), children_values.view()],
+            )
+            .unwrap();
+            let velocities = ndarray::concatenate(
+                Axis(0),
+                &[
+                    self.amalgam_params.velocities.view(),
+                    children_velocities.view(),
+                ],
+            )
+            .unwrap();
+            let origins: Vec<Option<usize>> =
+                vec![None; self.amalgam_params.population.nrows()]
+                    .into_iter()
+                    .chain(children_origins.into_iter().map(Some))
+                    .collect();
+            self.select_survivors(population, values, velocities, origins);
+
+            self.calibration_params.done = self.amalgam_params.n_calls
+                >= self.amalgam_params.max_evaluations;
+        }
+
+        let (front_params, front_objectives) = self.front();
+        Ok((self.calibration_params.done, front_params, front_objectives))
+    }
+
+    /// Returns the parameters of the first front and the value of each
+    /// criterion.
+    pub fn front(&self) -> (Array2<f64>, Array2<f64>) {
+        let front: Vec<usize> = (0..self.amalgam_params.ranks.len())
+            .filter(|&i| self.amalgam_params.ranks[i] == 0)
+            .collect();
+        let params = self.amalgam_params.population.select(Axis(0), &front);
+        let mut objectives =
+            self.amalgam_params.values.select(Axis(0), &front);
+        for (j, criterion) in self.amalgam_params.criteria.iter().enumerate() {
+            objectives
+                .column_mut(j)
+                .mapv_inplace(|x| criterion.to_objective(x));
+        }
+        (params, objectives)
+    }
+
+    /// Returns the number of children each operator creates per step.
+    pub fn contributions(&self) -> Vec<(&'static str, usize)> {
+        METHODS
+            .iter()
+            .zip(self.amalgam_params.counts)
+            .map(|(method, count)| (method.name(), count))
+            .collect()
+    }
+
+    /// Keeps the best points by front, then by crowding distance, and
+    /// updates the number of children of each operator with its success.
+    fn select_survivors(
+        &mut self,
+        population: Array2<f64>,
+        values: Array2<f64>,
+        velocities: Array2<f64>,
+        origins: Vec<Option<usize>>,
+    ) {
+        let n_population = self.amalgam_params.ranks.len();
+        let selected = select_by_front(&values, n_population);
+        let indices: Vec<usize> = selected.iter().map(|x| x.0).collect();
+
+        let mut survivors = [0usize; 4];
+        for &i in &indices {
+            if let Some(method) = origins[i] {
+                survivors[method] += 1;
+            }
+        }
+        if origins.iter().any(|o| o.is_some()) {
+            self.update_counts(survivors);
+        }
+
+        self.amalgam_params.population = population.select(Axis(0), &indices);
+        self.amalgam_params.values = values.select(Axis(0), &indices);
+        self.amalgam_params.velocities = velocities.select(Axis(0), &indices);
+        self.amalgam_params.ranks = selected.iter().map(|x| x.1).collect();
+        self.amalgam_params.crowding_distances =
+            selected.iter().map(|x| x.2).collect();
+    }
+
+    /// Makes the number of children of each operator proportional to its
+    /// success rate, keeping at least one.
+    fn update_counts(&mut self, survivors: [usize; 4]) {
+        let n_population = self.amalgam_params.ranks.len();
+        let counts = self.amalgam_params.counts;
+        let rates: Vec<f64> = (0..METHODS.len())
+            .map(|k| survivors[k] as f64 / counts[k].max(1) as f64)
+            .collect();
+        let total: f64 = rates.iter().sum();
+        if total <= 0. {
+            return;
+        }
+
+        let n_free = n_population - METHODS.len();
+        let mut new_counts = [1usize; 4];
+        for k in 0..METHODS.len() {
+            new_counts[k] +=
+                (rates[k] / total * n_free as f64).floor() as usize;
+        }
+        // the remainder goes to the most successful operator
+        let best = (0..METHODS.len())
+            .max_by(|&a, &b| rates[a].total_cmp(&rates[b]))
+            .unwrap();
+        new_counts[best] += n_population - new_counts.iter().sum::<usize>();
+        self.amalgam_params.counts = new_counts;
+    }
+
+    fn generate_children(&mut self) -> (Array2<f64>, Array2<f64>, Vec<usize>) {
+        let n_population = self.amalgam_params.population.nrows();
+        let n_params = self.amalgam_params.population.ncols();
+        let mut children = Array2::zeros((n_population, n_params));
+        let mut velocities = Array2::zeros((n_population, n_params));
+        let mut origins = Vec::with_capacity(n_population);
+
+        let front: Vec<usize> = (0..n_population)
+            .filter(|&i| self.amalgam_params.ranks[i] == 0)
+            .collect();
+        let jump = self.compute_metropolis_jump();
+
+        let mut i = 0;
+        for (k, &method) in METHODS.iter().enumerate() {
+            for _ in 0..self.amalgam_params.counts[k] {
+                let (child, velocity) = match method {
+                    Method::Nsga2 => (self.generate_nsga2(), None),
+                    Method::Pso => {
+                        let (child, velocity) = self.generate_pso(i, &front);
+                        (child, Some(velocity))
+                    }
+                    Method::De => (self.generate_de(i), None),
+                    Method::Metropolis => {
+                        (self.generate_metropolis(i, jump.as_ref()), None)
+                    }
+                };
+                children.row_mut(i).assign(&self.clip(child));
+                if let Some(velocity) = velocity {
+                    velocities.row_mut(i).assign(&velocity);
+                }
+                origins.push(k);
+                i += 1;
+            }
+        }
+
+        (children, velocities, origins)
+    }
+
+    fn generate_nsga2(&mut self) -> Array1<f64> {
+        let rng = &mut self.calibration_params.rng;
+        let first = select_by_tournament(
+            &self.amalgam_params.ranks,
+            &self.amalgam_params.crowding_distances,
+            rng,
+        );
+        let second = select_by_tournament(
+            &self.amalgam_params.ranks,
+            &self.amalgam_params.crowding_distances,
+            rng,
+        );
+        let (mut child, _) = crossover(
+            self.amalgam_params.population.row(first),
+            self.amalgam_params.population.row(second),
+            self.calibration_params.lower_bounds.view(),
+            self.calibration_params.upper_bounds.view(),
+            0.9,
+            15.,
+            rng,
+        );
+        mutate(
+            &mut child,
+            self.calibration_params.lower_bounds.view(),
+            self.calibration_params.upper_bounds.view(),
+            20.,
+            rng,
+        );
+        child
+    }
+
+    /// Particle swarm move towards a random point of the first front, the
+    /// population being elitist so that each point is its own best position.
+    fn generate_pso(
+        &mut self,
+        i: usize,
+        front: &[usize],
+    ) -> (Array1<f64>, Array1<f64>) {
+        let rng = &mut self.calibration_params.rng;
+        let point = self.amalgam_params.population.row(i);
+        let leader = self
+            .amalgam_params
+            .population
+            .row(front[rng.random_range(0..front.len())]);
+        let inertia = 0.5 + rng.random::<f64>() / 2.;
+        let velocity = Array1::from_iter((0..point.len()).map(|j| {
+            inertia * self.amalgam_params.velocities[[i, j]]
+                + 1.5 * rng.random::<f64>() * (leader[j] - point[j])
+        }));
+        (&point + &velocity, velocity)
+    }
+
+    /// Differential evolution (Storn and Price, 1997) rand/1 with binomial
+    /// crossover.
+    fn generate_de(&mut self, i: usize) -> Array1<f64> {
+        let rng = &mut self.calibration_params.rng;
+        let population = &self.amalgam_params.population;
+        let n_population = population.nrows();
+        let mut others = vec![];
+        while others.len() < 3 {
+            let k = rng.random_range(0..n_population);
+            if k != i && !others.contains(&k) {
+                others.push(k);
+            }
+        }
+        let mutant = &population.row(others[0])
+            + 0.5 * (&population.row(others[1]) - &population.row(others[2]));
+        let forced = rng.random_range(0..mutant.len());
+        Array1::from_iter((0..mutant.len()).map(|j| {
+            if j == forced || rng.random::<f64>() < 0.9 {
+                mutant[j]
+            } else {
+                population[[i, j]]
+            }
+        }))
+    }
+
+    /// Random walk with the scaled covariance of the population (Haario et
+    /// al., 2001).
+    fn generate_metropolis(
+        &mut self,
+        i: usize,
+        jump: Option<&Array2<f64>>,
+    ) -> Array1<f64> {
+        let point = self.amalgam_params.population.row(i);
+        let z: Array1<f64> = Array1::random_using(
+            point.len(),
+            StandardNormal,
+            &mut self.calibration_params.rng,
+        );
+        match jump {
+            Some(jump) => &point + &jump.dot(&z),
+            // degenerate population: small steps relative to the ranges
+            None => {
+                &point
+                    + &(0.01
+                        * &z
+                        * (&self.calibration_params.upper_bounds
+                            - &self.calibration_params.lower_bounds))
+            }
+        }
+    }
+
+    /// Cholesky factor of the population covariance scaled by 2.38^2 / d.
+    fn compute_metropolis_jump(&self) -> Option<Array2<f64>> {
+        let population = &self.amalgam_params.population;
+        let n_params = population.ncols();
+        let mean = population.mean_axis(Axis(0)).unwrap();
+        let centered = population - &mean;
+        let covariance =
+            centered.t().dot(&centered) / (population.nrows() - 1) as f64;
+        let scale = 2.38f64.powi(2) / n_params as f64;
+        let range = &self.calibration_params.upper_bounds
+            - &self.calibration_params.lower_bounds;
+        let jitter = Array2::from_diag(&(1e-6 * &range * &range));
+        compute_cholesky(scale * covariance + jitter)
+    }
+
+    fn clip(&self, point: Array1<f64>) -> Array1<f64> {
+        Array1::from_iter(
+            point
+                .iter()
+                .zip(&self.calibration_params.lower_bounds)
+                .zip(&self.calibration_params.upper_bounds)
+                .map(|((x, l), u)| x.clamp(*l, *u)),
+        )
+    }
+}
+
+#[pymethods]
+impl Amalgam {
+    #[new]
+    #[pyo3(signature = (
+        climate_model,
+        snow_model,
+        criteria,
+        max_evaluations,
+        seed,
+        population_size=100,
+    ))]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        criteria: Vec<(String, String)>,
+        max_evaluations: usize,
+        seed: u64,
+        population_size: usize,
+    ) -> PyResult<Self> {
+        let criteria = parse_criteria(&criteria)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        if criteria.is_empty() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "At least one criterion is required.",
+            ));
+        }
+        Amalgam::new(
+            climate_model,
+            snow_model,
+            criteria,
+            population_size,
+            max_evaluations,
+            seed,
+        )
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "init")]
+    pub fn py_init(
+        &mut self,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<()> {
+        self.init(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok(())
+    }
+
+    #[pyo3(name = "step")]
+    pub fn py_step<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<(bool, Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)>
+    {
+        let (done, front_params, front_objectives) = self.step(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok((
+            done,
+            front_params.to_pyarray(py),
+            front_objectives.to_pyarray(py),
+        ))
+    }
+
+    #[pyo3(name = "contributions")]
+    pub fn py_contributions(&self) -> Vec<(&'static str, usize)> {
+        self.contributions()
+    }
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "amalgam")?;
+    m.add_class::<Amalgam>()?;
+    Ok(m)
+}
@@ -0,0 +1,210 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use numpy::{PyArray2, PyReadonlyArray1, PyReadonlyArray2, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::calibration::utils::{dominates, stack_rows};
+
+/// Epsilon-box non-dominated archive (Laumanns et al., 2002).
+///
+/// The objective space is divided into boxes of size `epsilons` and the
+/// archive keeps at most one point per non-dominated box, the one closest to
+/// the box corner. Objectives are minimized, except where `maximize` is set.
+#[pyclass(module = "hydro_rs.calibration.archive", unsendable)]
+pub struct Archive {
+    epsilons: Array1<f64>,
+    signs: Array1<f64>,
+    params: Vec<Array1<f64>>,
+    objectives: Vec<Array1<f64>>,
+    boxes: Vec<Array1<f64>>,
+}
+
+impl Archive {
+    pub fn new(epsilons: Array1<f64>, maximize: Vec<bool>) -> Self {
+        let signs = Array1::from_iter((0..epsilons.len()).map(|j| {
+            if maximize.get(j) == Some(&true) {
+                -1.
+            } else {
+                1.
+            }
+        }));
+        Archive {
+            epsilons,
+            signs,
+            params: vec![],
+            objectives: vec![],
+            boxes: vec![],
+        }
+    }
+
+    /// Adds the point if no archived point epsilon-dominates it, removing the
+    /// archived points it epsilon-dominates. Returns whether it was added.
+    pub fn push(
+        &mut self,
+        params: ArrayView1<f64>,
+        objectives: ArrayView1<f64>,
+    ) -> bool {
+        let values = &objectives * &self.signs;
+        if values.iter().any(|x| x.is_nan()) {
+            return false;
+        }
+        let point_box = (&values / &self.epsilons).mapv(f64::floor);
+
+        let mut replaced = vec![];
+        for (i, archived_box) in self.boxes.iter().enumerate() {
+            if *archived_box == point_box {
+                // same box: keep the point closest to its corner, or the
+                // dominating one
+                let archived = &self.objectives[i] * &self.signs;
+                if dominates(archived.view(), values.view()) {
+                    return false;
+                }
+                if !dominates(values.view(), archived.view())
+                    && self.distance_to_corner(archived.view(), archived_box)
+                        <= self.distance_to_corner(values.view(), &point_box)
+                {
+                    return false;
+                }
+                replaced.push(i);
+            } else if dominates(archived_box.view(), point_box.view()) {
+                return false;
+            } else if dominates(point_box.view(), archived_box.view()) {
+                replaced.push(i);
+            }
+        }
+
+        for &i in replaced.iter().rev() {
+            self.params.swap_remove(i);
+            self.objectives.swap_remove(i);
+            self.boxes.swap_remove(i);
+        }
+        self.params.push(params.to_owned());
+        self.objectives.push(objectives.to_owned());
+        self.boxes.push(point_box);
+        true
+    }
+
+    /// Pushes each row, returning the number of points added.
+    pub fn push_batch(
+        &mut self,
+        params: ArrayView2<f64>,
+        objectives: ArrayView2<f64>,
+    ) -> usize {
+        params
+            .rows()
+            .into_iter()
+            .zip(objectives.rows())
+            .filter(|(p, o)| self.push(*p, *o))
+            .count()
+    }
+
+    /// Returns the archived parameters and objectives.
+    pub fn contents(&self) -> (Array2<f64>, Array2<f64>) {
+        (
+            stack_rows(&self.params, 0),
+            stack_rows(&self.objectives, self.epsilons.len()),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    fn distance_to_corner(
+        &self,
+        values: ArrayView1<f64>,
+        point_box: &Array1<f64>,
+    ) -> f64 {
+        ((&values / &self.epsilons) - point_box)
+            .mapv(|x| x.powi(2))
+            .sum()
+    }
+
+    fn check_objectives(&self, n_objectives: usize) -> PyResult<()> {
+        if n_objectives != self.epsilons.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Expected {} objectives, got {}.",
+                self.epsilons.len(),
+                n_objectives
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl Archive {
+    #[new]
+    #[pyo3(signature = (epsilons, maximize=None))]
+    pub fn py_new(
+        epsilons: Vec<f64>,
+        maximize: Option<Vec<bool>>,
+    ) -> PyResult<Self> {
+        if epsilons.iter().any(|&e| e <= 0. || e.is_nan()) {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "The epsilons must be positive.",
+            ));
+        }
+        let maximize = maximize.unwrap_or_default();
+        if !maximize.is_empty() && maximize.len() != epsilons.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Expected {} maximize flags, got {}.",
+                epsilons.len(),
+                maximize.len()
+            )));
+        }
+        Ok(Archive::new(Array1::from_vec(epsilons), maximize))
+    }
+
+    #[pyo3(name = "push")]
+    pub fn py_push(
+        &mut self,
+        params: PyReadonlyArray1<'_, f64>,
+        objectives: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<bool> {
+        self.check_objectives(objectives.as_array().len())?;
+        Ok(self.push(params.as_array(), objectives.as_array()))
+    }
+
+    #[pyo3(name = "push_batch")]
+    pub fn py_push_batch(
+        &mut self,
+        params: PyReadonlyArray2<'_, f64>,
+        objectives: PyReadonlyArray2<'_, f64>,
+    ) -> PyResult<usize> {
+        let (params, objectives) = (params.as_array(), objectives.as_array());
+        self.check_objectives(objectives.ncols())?;
+        if params.nrows() != objectives.nrows() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Got {} parameter sets but {} objective sets.",
+                params.nrows(),
+                objectives.nrows()
+            )));
+        }
+        Ok(self.push_batch(params, objectives))
+    }
+
+    #[pyo3(name = "contents")]
+    pub fn py_contents<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> (Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>) {
+        let (params, objectives) = self.contents();
+        (params.to_pyarray(py), objectives.to_pyarray(py))
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.len()
+    }
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "archive")?;
+    m.add_class::<Archive>()?;
+    Ok(m)
+}
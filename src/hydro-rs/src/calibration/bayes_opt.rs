@@ -0,0 +1,576 @@
+#![allow(clippy::type_complexity)]
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use ndarray_rand::rand_distr::{Normal, Uniform};
+use ndarray_rand::RandomExt;
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::calibration::lhs::evaluate_population;
+use crate::calibration::utils::{
+    build_model, compute_cholesky, evaluate_metrics, sample_latin_hypercube,
+    stack_rows, CalibrationParams, Objective, Scoring, N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+// length scales, relative to the parameter ranges, among which the one
+// maximizing the marginal likelihood is selected at each fit
+const LENGTH_SCALES: [f64; 6] = [0.05, 0.1, 0.2, 0.4, 0.8, 1.6];
+const NOISE: f64 = 1e-6;
+// standard deviation of the perturbations of the best point, relative to
+// the parameter ranges
+const PERTURBATION: f64 = 0.1;
+
+/// Gaussian process with a Matérn 5/2 kernel on standardized values.
+struct GaussianProcess {
+    points: Array2<f64>,
+    cholesky: Array2<f64>,
+    alpha: Array1<f64>,
+    length_scale: f64,
+    mean: f64,
+    std: f64,
+}
+
+impl GaussianProcess {
+    fn fit(points: &Array2<f64>, values: &Array1<f64>) -> Option<Self> {
+        let mean = values.mean()?;
+        let std = values.std(0.).max(1e-12);
+        let y = (values - mean) / std;
+
+        LENGTH_SCALES
+            .iter()
+            .filter_map(|&length_scale| {
+                let n = points.nrows();
+                let covariance = Array2::from_shape_fn((n, n), |(i, j)| {
+                    kernel(points.row(i), points.row(j), length_scale)
+                        + if i == j { NOISE } else { 0. }
+                });
+                let cholesky = compute_cholesky(covariance)?;
+                let alpha = solve_cholesky(&cholesky, &y);
+                let log_likelihood =
+                    -0.5 * y.dot(&alpha) - cholesky.diag().mapv(f64::ln).sum();
+                Some((
+                    log_likelihood,
+                    GaussianProcess {
+                        points: points.clone(),
+                        cholesky,
+                        alpha,
+                        length_scale,
+                        mean,
+                        std,
+                    },
+                ))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, gp)| gp)
+    }
+
+    /// Returns the predicted mean and standard deviation.
+    fn predict(&self, point: ArrayView1<f64>) -> (f64, f64) {
+        let k = Array1::from_iter(
+            self.points
+                .rows()
+                .into_iter()
+                .map(|p| kernel(point, p, self.length_scale)),
+        );
+        let mean = k.dot(&self.alpha);
+        let v = solve_lower(&self.cholesky, &k);
+        let variance = (1. + NOISE - v.dot(&v)).max(0.);
+        (self.mean + self.std * mean, self.std * variance.sqrt())
+    }
+}
+
+/// Bayesian optimization on the unit hypercube, proposing the candidate
+/// maximizing the expected improvement (Jones et al., 1998) among
+/// perturbations of the best point and uniform points. A uniform point is
+/// proposed when the Gaussian process can't be fitted.
+struct BayesSearch {
+    lower_bounds: Array1<f64>,
+    upper_bounds: Array1<f64>,
+    points: Vec<Array1<f64>>,
+    values: Vec<f64>,
+    n_candidates: usize,
+}
+
+impl BayesSearch {
+    fn new(lower_bounds: Array1<f64>, upper_bounds: Array1<f64>) -> Self {
+        let n_candidates = (50 * lower_bounds.len()).min(1000);
+        BayesSearch {
+            lower_bounds,
+            upper_bounds,
+            points: vec![],
+            values: vec![],
+            n_candidates,
+        }
+    }
+
+    fn add(&mut self, point: ArrayView1<f64>, value: f64) {
+        let range = &self.upper_bounds - &self.lower_bounds;
+        self.points.push((&point - &self.lower_bounds) / range);
+        // failed evaluations are the worst
+        self.values
+            .push(if value.is_nan() { f64::INFINITY } else { value });
+    }
+
+    fn best(&self) -> (Array1<f64>, f64) {
+        let best = (0..self.values.len())
+            .min_by(|&a, &b| self.values[a].total_cmp(&self.values[b]))
+            .unwrap();
+        (self.to_bounds(self.points[best].view()), self.values[best])
+    }
+
+    fn propose(&mut self, rng: &mut ChaCha8Rng) -> Array1<f64> {
+        let n_params = self.lower_bounds.len();
+        let points = stack_rows(&self.points, n_params);
+
+        let Some(gp) = GaussianProcess::fit(&points, &self.finite_values())
+        else {
+            let point = Array1::random_using(
+                n_params,
+                Uniform::new(0., 1.).unwrap(),
+                rng,
+            );
+            return self.to_bounds(point.view());
+        };
+
+        let (best, best_value) = self.best();
+        let best = (&best - &self.lower_bounds)
+            / (&self.upper_bounds - &self.lower_bounds);
+        let n_perturbed = self.n_candidates / 2;
+        let perturbations: Array2<f64> = Array2::random_using(
+            (n_perturbed, n_params),
+            Normal::new(0., PERTURBATION).unwrap(),
+            rng,
+        );
+        let perturbed = (perturbations + &best).mapv(|x| x.clamp(0., 1.));
+        let uniform: Array2<f64> = Array2::random_using(
+            (self.n_candidates - n_perturbed, n_params),
+            Uniform::new(0., 1.).unwrap(),
+            rng,
+        );
+        let candidates =
+            ndarray::concatenate(Axis(0), &[perturbed.view(), uniform.view()])
+                .unwrap();
+
+        let improvements: Vec<f64> = (0..candidates.nrows())
+            .into_par_iter()
+            .map(|i| {
+                let (mean, std) = gp.predict(candidates.row(i));
+                compute_expected_improvement(mean, std, best_value)
+            })
+            .collect();
+        let chosen = (0..candidates.nrows())
+            .max_by(|&a, &b| improvements[a].total_cmp(&improvements[b]))
+            .unwrap();
+        self.to_bounds(candidates.row(chosen))
+    }
+
+    /// Failed evaluations take the worst finite value.
+    fn finite_values(&self) -> Array1<f64> {
+        let worst = self
+            .values
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .fold(f64::NEG_INFINITY, f64::max);
+        let worst = if worst.is_finite() { worst } else { 0. };
+        Array1::from_iter(self.values.iter().map(|&v| {
+            if v.is_finite() {
+                v
+            } else {
+                worst
+            }
+        }))
+    }
+
+    fn to_bounds(&self, point: ArrayView1<f64>) -> Array1<f64> {
+        &point * &(&self.upper_bounds - &self.lower_bounds)
+            + &self.lower_bounds
+    }
+}
+
+struct BayesOptParams {
+    pub search: BayesSearch,
+    pub objectives: Array1<f64>,
+    pub n_calls: usize,
+    pub n_initial: usize,
+    pub max_evaluations: usize,
+}
+
+/// Bayesian optimization with a Gaussian process surrogate and the expected
+/// improvement, for expensive simulations with a budget of a few hundred
+/// evaluations.
+///
+/// The Gaussian process is fitted to an initial Latin hypercube sample, then
+/// each iteration evaluates the model at the candidate with the highest
+/// expected improvement and refits it. Each step runs as many iterations as
+/// there are parameters.
+#[pyclass(module = "hydro_rs.calibration.bayes_opt", unsendable)]
+pub struct BayesOpt {
+    calibration_params: CalibrationParams,
+    bayes_opt_params: BayesOptParams,
+}
+
+impl BayesOpt {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: Objective,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+
+        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+
+        let n_initial = 2 * (params.len() + 1);
+        let search =
+            BayesSearch::new(lower_bounds.clone(), upper_bounds.clone());
+
+        let calibration_params = CalibrationParams {
+            params,
+            simulate,
+            lower_bounds,
+            upper_bounds,
+            objective,
+            rng,
+            done: false,
+        };
+        let bayes_opt_params = BayesOptParams {
+            search,
+            objectives: Array1::from_elem(N_METRICS, f64::NAN),
+            n_calls: 0,
+            n_initial,
+            max_evaluations,
+        };
+
+        Ok(BayesOpt {
+            calibration_params,
+            bayes_opt_params,
+        })
+    }
+
+    pub fn init<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        let population = sample_latin_hypercube(
+            self.bayes_opt_params.n_initial,
+            self.calibration_params.lower_bounds.view(),
+            self.calibration_params.upper_bounds.view(),
+            &mut self.calibration_params.rng,
+        );
+        let (population, objectives) = evaluate_population(
+            &self.calibration_params.simulate,
+            data,
+            metadata,
+            observations,
+            population,
+            self.calibration_params.objective,
+            &Scoring {
+                objective: Some(self.calibration_params.objective),
+                ..Scoring::default()
+            },
+        )?;
+
+        for (point, point_objectives) in
+            population.rows().into_iter().zip(objectives.rows())
+        {
+            let value = self.to_value(point_objectives);
+            self.bayes_opt_params.search.add(point, value);
+        }
+        self.calibration_params.params = population.row(0).to_owned();
+        self.bayes_opt_params.objectives = objectives.row(0).to_owned();
+        self.bayes_opt_params.n_calls = population.nrows();
+
+        Ok(())
+    }
+
+    pub fn step<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(bool, Array1<f64>, Array1<f64>, Array1<f64>), Error> {
+        if !self.calibration_params.done {
+            let n_params = self.calibration_params.params.len();
+
+            for _ in 0..n_params {
+                if self.bayes_opt_params.n_calls
+                    >= self.bayes_opt_params.max_evaluations
+                {
+                    break;
+                }
+                let candidate = self
+                    .bayes_opt_params
+                    .search
+                    .propose(&mut self.calibration_params.rng);
+                let simulation = (self.calibration_params.simulate)(
+                    candidate.view(),
+                    data,
+                    metadata,
+                )?;
+                let objectives = evaluate_metrics(
+                    observations,
+                    simulation.view(),
+                    Some(self.calibration_params.objective),
+                )?;
+                self.bayes_opt_params.n_calls += 1;
+
+                let value = self.to_value(objectives.view());
+                self.bayes_opt_params.search.add(candidate.view(), value);
+                let best =
+                    self.to_value(self.bayes_opt_params.objectives.view());
+                if best.is_nan() || value < best {
+                    self.calibration_params.params = candidate;
+                    self.bayes_opt_params.objectives = objectives;
+                }
+            }
+
+            self.calibration_params.done = self.bayes_opt_params.n_calls
+                >= self.bayes_opt_params.max_evaluations;
+        }
+
+        let best_simulation = (self.calibration_params.simulate)(
+            self.calibration_params.params.view(),
+            data,
+            metadata,
+        )?;
+
+        Ok((
+            self.calibration_params.done,
+            self.calibration_params.params.clone(),
+            best_simulation,
+            self.bayes_opt_params.objectives.clone(),
+        ))
+    }
+
+    /// Objective to minimize.
+    fn to_value(&self, objectives: ArrayView1<f64>) -> f64 {
+        let (objective_idx, is_minimization) =
+            self.calibration_params.objective.index();
+        if is_minimization {
+            objectives[objective_idx]
+        } else {
+            -objectives[objective_idx]
+        }
+    }
+}
+
+#[pymethods]
+impl BayesOpt {
+    #[new]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: &str,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> PyResult<Self> {
+        let objective = Objective::from_str(objective)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        BayesOpt::new(
+            climate_model,
+            snow_model,
+            objective,
+            max_evaluations,
+            seed,
+        )
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "init")]
+    pub fn py_init(
+        &mut self,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<()> {
+        self.init(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok(())
+    }
+
+    #[pyo3(name = "step")]
+    pub fn py_step<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<(
+        bool,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+    )> {
+        let (done, best_params, simulation, objectives) = self.step(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok((
+            done,
+            best_params.to_pyarray(py),
+            simulation.to_pyarray(py),
+            objectives.to_pyarray(py),
+        ))
+    }
+}
+
+/// Minimizes a Python `objective`, called with an array of parameters, with
+/// Bayesian optimization, starting from a Latin hypercube sample of
+/// `n_initial` points (2 * (n_params + 1) by default).
+///
+/// Returns the best parameters and value, and all the evaluated points and
+/// values.
+#[pyfunction]
+#[pyo3(signature = (
+    objective,
+    lower_bounds,
+    upper_bounds,
+    max_evaluations,
+    seed,
+    n_initial=None,
+))]
+pub fn minimize<'py>(
+    py: Python<'py>,
+    objective: Bound<'py, PyAny>,
+    lower_bounds: PyReadonlyArray1<'_, f64>,
+    upper_bounds: PyReadonlyArray1<'_, f64>,
+    max_evaluations: usize,
+    seed: u64,
+    n_initial: Option<usize>,
+) -> PyResult<(
+    Bound<'py, PyArray1<f64>>,
+    f64,
+    Bound<'py, PyArray2<f64>>,
+    Bound<'py, PyArray1<f64>>,
+)> {
+    let lower_bounds = lower_bounds.as_array().to_owned();
+    let upper_bounds = upper_bounds.as_array().to_owned();
+    if lower_bounds.len() != upper_bounds.len()
+        || lower_bounds.iter().zip(&upper_bounds).any(|(l, u)| l >= u)
+    {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "The lower bounds must be below the upper bounds.",
+        ));
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let n_initial = n_initial
+        .unwrap_or(2 * (lower_bounds.len() + 1))
+        .min(max_evaluations)
+        .max(1);
+    let mut search =
+        BayesSearch::new(lower_bounds.clone(), upper_bounds.clone());
+    let mut evaluated: Vec<Array1<f64>> = vec![];
+
+    let initial = sample_latin_hypercube(
+        n_initial,
+        lower_bounds.view(),
+        upper_bounds.view(),
+        &mut rng,
+    );
+    for point in initial.rows() {
+        let value: f64 =
+            objective.call1((point.to_pyarray(py),))?.extract()?;
+        search.add(point, value);
+        evaluated.push(point.to_owned());
+    }
+    for _ in n_initial..max_evaluations {
+        let point = search.propose(&mut rng);
+        let value: f64 =
+            objective.call1((point.to_pyarray(py),))?.extract()?;
+        search.add(point.view(), value);
+        evaluated.push(point);
+    }
+
+    let (best_params, best_value) = search.best();
+    let points = stack_rows(&evaluated, lower_bounds.len());
+    let values = Array1::from_vec(search.values.clone());
+    Ok((
+        best_params.to_pyarray(py),
+        best_value,
+        points.to_pyarray(py),
+        values.to_pyarray(py),
+    ))
+}
+
+fn kernel(a: ArrayView1<f64>, b: ArrayView1<f64>, length_scale: f64) -> f64 {
+    let r = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+        / length_scale;
+    let s = 5f64.sqrt() * r;
+    (1. + s + s.powi(2) / 3.) * (-s).exp()
+}
+
+/// Expected improvement below `best` of a normal prediction.
+fn compute_expected_improvement(mean: f64, std: f64, best: f64) -> f64 {
+    if std <= 0. {
+        return (best - mean).max(0.);
+    }
+    let z = (best - mean) / std;
+    let cdf = 0.5 * (1. + erf(z / 2f64.sqrt()));
+    let pdf = (-0.5 * z.powi(2)).exp() / (2. * PI).sqrt();
+    (best - mean) * cdf + std * pdf
+}
+
+/// Error function (Abramowitz and Stegun, 1964, 7.1.26).
+fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.3275911 * x.abs());
+    let y = 1.
+        - t * (0.254829592
+            + t * (-0.284496736
+                + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))))
+            * (-x.powi(2)).exp();
+    y.copysign(x)
+}
+
+fn solve_lower(l: &Array2<f64>, b: &Array1<f64>) -> Array1<f64> {
+    let n = b.len();
+    let mut x = Array1::zeros(n);
+    for i in 0..n {
+        let sum: f64 = (0..i).map(|k| l[[i, k]] * x[k]).sum();
+        x[i] = (b[i] - sum) / l[[i, i]];
+    }
+    x
+}
+
+/// Solves (L L^T) x = b.
+fn solve_cholesky(l: &Array2<f64>, b: &Array1<f64>) -> Array1<f64> {
+    let y = solve_lower(l, b);
+    let n = y.len();
+    let mut x = Array1::zeros(n);
+    for i in (0..n).rev() {
+        let sum: f64 = ((i + 1)..n).map(|k| l[[k, i]] * x[k]).sum();
+        x[i] = (y[i] - sum) / l[[i, i]];
+    }
+    x
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "bayes_opt")?;
+    m.add_class::<BayesOpt>()?;
+    m.add_function(wrap_pyfunction!(minimize, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::model::Error;
+
+const VERSION: usize = 1;
+
+/// Plain text calibration state, one `key value...` field per line.
+///
+/// Floats are written with their shortest round-trip representation and the
+/// generator with its seed, stream and word position, so a loaded state
+/// continues exactly as the saved one would have.
+pub struct Checkpoint {
+    kind: String,
+    fields: HashMap<String, String>,
+    order: Vec<String>,
+}
+
+impl Checkpoint {
+    pub fn new(kind: &str) -> Self {
+        Checkpoint {
+            kind: kind.to_string(),
+            fields: HashMap::new(),
+            order: vec![],
+        }
+    }
+
+    pub fn read(path: &Path, kind: &str) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        let header = format!("hydro-rs {} checkpoint {}", kind, VERSION);
+        if lines.next() != Some(header.as_str()) {
+            return Err(Error::InvalidCheckpoint(format!(
+                "expected the header '{}'",
+                header
+            )));
+        }
+        let mut checkpoint = Checkpoint::new(kind);
+        for line in lines.filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            checkpoint.insert(key, value.to_string());
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let mut contents =
+            format!("hydro-rs {} checkpoint {}\n", self.kind, VERSION);
+        for key in &self.order {
+            contents.push_str(&format!("{} {}\n", key, self.fields[key]));
+        }
+        // write then rename so an interrupted save keeps the last checkpoint
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn set<T: Display>(&mut self, key: &str, value: T) {
+        self.insert(key, value.to_string());
+    }
+
+    pub fn set_array1(&mut self, key: &str, values: ArrayView1<f64>) {
+        let values: Vec<String> = values.iter().map(f64::to_string).collect();
+        self.insert(key, format!("{} {}", values.len(), values.join(" ")));
+    }
+
+    pub fn set_array2(&mut self, key: &str, values: ArrayView2<f64>) {
+        let (n_rows, n_columns) = values.dim();
+        let values: Vec<String> = values.iter().map(f64::to_string).collect();
+        self.insert(
+            key,
+            format!("{} {} {}", n_rows, n_columns, values.join(" ")),
+        );
+    }
+
+    pub fn set_rng(&mut self, key: &str, rng: &ChaCha8Rng) {
+        let seed: String = rng
+            .get_seed()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.insert(
+            key,
+            format!("{} {} {}", seed, rng.get_stream(), rng.get_word_pos()),
+        );
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<T, Error> {
+        self.get_optional(key)?.ok_or_else(|| {
+            Error::InvalidCheckpoint(format!("missing field '{}'", key))
+        })
+    }
+
+    pub fn get_optional<T: FromStr>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, Error> {
+        self.fields
+            .get(key)
+            .map(|value| parse(key, value))
+            .transpose()
+    }
+
+    pub fn get_array1(&self, key: &str) -> Result<Array1<f64>, Error> {
+        let values: Vec<f64> = self.parse_values(key)?;
+        let n = values[0] as usize;
+        if values.len() != n + 1 {
+            return Err(invalid_length(key));
+        }
+        Ok(Array1::from_vec(values[1..].to_vec()))
+    }
+
+    pub fn get_array2(&self, key: &str) -> Result<Array2<f64>, Error> {
+        let values: Vec<f64> = self.parse_values(key)?;
+        if values.len() < 2 {
+            return Err(invalid_length(key));
+        }
+        let (n_rows, n_columns) = (values[0] as usize, values[1] as usize);
+        Array2::from_shape_vec((n_rows, n_columns), values[2..].to_vec())
+            .map_err(|_| invalid_length(key))
+    }
+
+    pub fn get_rng(&self, key: &str) -> Result<ChaCha8Rng, Error> {
+        let value: String = self.get(key)?;
+        let parts: Vec<&str> = value.split(' ').collect();
+        if parts.len() != 3 || parts[0].len() != 64 {
+            return Err(invalid_length(key));
+        }
+        let mut seed = [0u8; 32];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&parts[0][2 * i..2 * i + 2], 16)
+                .map_err(|_| invalid_value(key, parts[0]))?;
+        }
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(parse(key, parts[1])?);
+        rng.set_word_pos(parse(key, parts[2])?);
+        Ok(rng)
+    }
+
+    fn insert(&mut self, key: &str, value: String) {
+        if self.fields.insert(key.to_string(), value).is_none() {
+            self.order.push(key.to_string());
+        }
+    }
+
+    fn parse_values(&self, key: &str) -> Result<Vec<f64>, Error> {
+        let value: String = self.get(key)?;
+        let values = value
+            .split(' ')
+            .filter(|x| !x.is_empty())
+            .map(|x| parse(key, x))
+            .collect::<Result<Vec<f64>, Error>>()?;
+        if values.is_empty() {
+            return Err(invalid_length(key));
+        }
+        Ok(values)
+    }
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, Error> {
+    value.parse().map_err(|_| invalid_value(key, value))
+}
+
+fn invalid_value(key: &str, value: &str) -> Error {
+    Error::InvalidCheckpoint(format!(
+        "invalid value '{}' for field '{}'",
+        value, key
+    ))
+}
+
+fn invalid_length(key: &str) -> Error {
+    Error::InvalidCheckpoint(format!(
+        "wrong number of values for field '{}'",
+        key
+    ))
+}
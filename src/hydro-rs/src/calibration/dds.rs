@@ -0,0 +1,352 @@
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1};
+use ndarray_rand::rand_distr::{StandardNormal, Uniform};
+use ndarray_rand::RandomExt;
+use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::calibration::utils::{
+    build_model, evaluate_metrics, sort_population, CalibrationParams,
+    Objective, N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
+
+struct DdsParams {
+    pub objectives: Array1<f64>,
+    pub n_calls: usize,
+    pub n_initial: usize,
+    pub perturbation: f64,
+    pub max_evaluations: usize,
+}
+
+/// Dynamically Dimensioned Search (Tolson and Shoemaker, 2007).
+///
+/// Starting from the best of a few random points, each iteration perturbs a
+/// random subset of the parameters of the best point, the expected size of
+/// the subset decreasing from all parameters to a single one as the number
+/// of evaluations approaches `max_evaluations`. Each step runs as many
+/// iterations as there are parameters.
+#[pyclass(module = "hydro_rs.calibration.dds", unsendable)]
+pub struct Dds {
+    calibration_params: CalibrationParams,
+    dds_params: DdsParams,
+}
+
+impl Dds {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: Objective,
+        perturbation: f64,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+
+        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // number of random points from which the search starts
+        let n_initial = (max_evaluations / 200).max(5);
+
+        let calibration_params = CalibrationParams {
+            params,
+            simulate,
+            lower_bounds,
+            upper_bounds,
+            objective,
+            rng,
+            done: false,
+        };
+        let dds_params = DdsParams {
+            objectives: Array1::from_elem(N_METRICS, f64::NAN),
+            n_calls: 0,
+            n_initial,
+            perturbation,
+            max_evaluations,
+        };
+
+        Ok(Dds {
+            calibration_params,
+            dds_params,
+        })
+    }
+
+    /// Replaces the bounds of the search, e.g. to search something else than
+    /// the model parameters through `init_with` and `step_with`.
+    pub fn set_bounds(
+        &mut self,
+        lower_bounds: Array1<f64>,
+        upper_bounds: Array1<f64>,
+    ) {
+        self.calibration_params.params = (&lower_bounds + &upper_bounds) / 2.;
+        self.calibration_params.lower_bounds = lower_bounds;
+        self.calibration_params.upper_bounds = upper_bounds;
+    }
+
+    pub fn init<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        let objective = Some(self.calibration_params.objective);
+        self.init_with(|simulate, params| {
+            let simulation = simulate(params, data, metadata)?;
+            evaluate_metrics(observations, simulation.view(), objective)
+        })
+    }
+
+    /// Same as `init`, but with the objectives (see `evaluate_simulation`)
+    /// of a parameter set computed by `evaluate` from the model's simulation
+    /// function, e.g. to score it on several catchments.
+    pub fn init_with<F>(&mut self, evaluate: F) -> Result<(), Error>
+    where
+        F: Fn(&SimulateFn, ArrayView1<f64>) -> Result<Array1<f64>, Error>
+            + Sync,
+    {
+        let (objective_idx, is_minimization) =
+            self.calibration_params.objective.index();
+
+        let lower_bounds = &self.calibration_params.lower_bounds;
+        let upper_bounds = &self.calibration_params.upper_bounds;
+        let random_values: Array2<f64> = Array2::random_using(
+            (self.dds_params.n_initial, lower_bounds.len()),
+            Uniform::new(0., 1.).unwrap(),
+            &mut self.calibration_params.rng,
+        );
+        let mut population =
+            &random_values * &(upper_bounds - lower_bounds) + lower_bounds;
+        population.row_mut(0).assign(
+            &((&self.calibration_params.lower_bounds
+                + &self.calibration_params.upper_bounds)
+                / 2.),
+        );
+
+        let simulate = &self.calibration_params.simulate;
+        let results: Vec<Result<Array1<f64>, Error>> = (0..population.nrows())
+            .into_par_iter()
+            .map(|i| evaluate(simulate, population.row(i)))
+            .collect();
+        let mut objectives =
+            Array2::<f64>::zeros((population.nrows(), N_METRICS));
+        for (i, result) in results.into_iter().enumerate() {
+            objectives.row_mut(i).assign(&result?);
+        }
+
+        sort_population(
+            &mut population,
+            &mut objectives,
+            objective_idx,
+            is_minimization,
+        );
+
+        self.calibration_params.params = population.row(0).to_owned();
+        self.dds_params.objectives = objectives.row(0).to_owned();
+        self.dds_params.n_calls = population.nrows();
+
+        Ok(())
+    }
+
+    pub fn step<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(bool, Array1<f64>, Array1<f64>, Array1<f64>), Error> {
+        let objective = Some(self.calibration_params.objective);
+        let (done, params, objectives) =
+            self.step_with(|simulate, params| {
+                let simulation = simulate(params, data, metadata)?;
+                evaluate_metrics(observations, simulation.view(), objective)
+            })?;
+
+        let best_simulation =
+            (self.calibration_params.simulate)(params.view(), data, metadata)?;
+
+        Ok((done, params, best_simulation, objectives))
+    }
+
+    /// Same as `step`, with the objectives computed by `evaluate` as in
+    /// `init_with`. Returns whether the search is done, the best parameters
+    /// and their objectives.
+    pub fn step_with<F>(
+        &mut self,
+        evaluate: F,
+    ) -> Result<(bool, Array1<f64>, Array1<f64>), Error>
+    where
+        F: Fn(&SimulateFn, ArrayView1<f64>) -> Result<Array1<f64>, Error>,
+    {
+        if !self.calibration_params.done {
+            let (objective_idx, is_minimization) =
+                self.calibration_params.objective.index();
+            let n_params = self.calibration_params.params.len();
+
+            for _ in 0..n_params {
+                if self.dds_params.n_calls >= self.dds_params.max_evaluations {
+                    break;
+                }
+                let candidate = self.generate_candidate();
+                let objectives = evaluate(
+                    &self.calibration_params.simulate,
+                    candidate.view(),
+                )?;
+                self.dds_params.n_calls += 1;
+
+                let new = objectives[objective_idx];
+                let best = self.dds_params.objectives[objective_idx];
+                let is_better = best.is_nan()
+                    || if is_minimization {
+                        new <= best
+                    } else {
+                        new >= best
+                    };
+                if is_better {
+                    self.calibration_params.params = candidate;
+                    self.dds_params.objectives = objectives;
+                }
+            }
+
+            self.calibration_params.done =
+                self.dds_params.n_calls >= self.dds_params.max_evaluations;
+        }
+
+        Ok((
+            self.calibration_params.done,
+            self.calibration_params.params.clone(),
+            self.dds_params.objectives.clone(),
+        ))
+    }
+
+    /// Perturbs the parameters of the best point, each being selected with a
+    /// probability decreasing with the number of evaluations.
+    fn generate_candidate(&mut self) -> Array1<f64> {
+        let rng = &mut self.calibration_params.rng;
+        let lower_bounds = &self.calibration_params.lower_bounds;
+        let upper_bounds = &self.calibration_params.upper_bounds;
+        let n_params = lower_bounds.len();
+
+        let probability = 1.
+            - (self.dds_params.n_calls as f64).ln()
+                / (self.dds_params.max_evaluations as f64).ln();
+
+        let mut selected: Vec<usize> = (0..n_params)
+            .filter(|_| rng.random::<f64>() < probability)
+            .collect();
+        if selected.is_empty() {
+            selected.push(rng.random_range(0..n_params));
+        }
+
+        let mut candidate = self.calibration_params.params.clone();
+        for j in selected {
+            let (lower, upper) = (lower_bounds[j], upper_bounds[j]);
+            let step: f64 = rng.sample(StandardNormal);
+            let mut value = candidate[j]
+                + self.dds_params.perturbation * (upper - lower) * step;
+            // reflect at the bounds, falling back on the bound itself when
+            // the reflection overshoots the opposite one
+            if value < lower {
+                value = 2. * lower - value;
+                if value > upper {
+                    value = lower;
+                }
+            } else if value > upper {
+                value = 2. * upper - value;
+                if value < lower {
+                    value = upper;
+                }
+            }
+            candidate[j] = value;
+        }
+        candidate
+    }
+}
+
+#[pymethods]
+impl Dds {
+    #[new]
+    #[pyo3(signature = (
+        climate_model,
+        snow_model,
+        objective,
+        max_evaluations,
+        seed,
+        perturbation=0.2,
+    ))]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: &str,
+        max_evaluations: usize,
+        seed: u64,
+        perturbation: f64,
+    ) -> PyResult<Self> {
+        let objective = Objective::from_str(objective)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Dds::new(
+            climate_model,
+            snow_model,
+            objective,
+            perturbation,
+            max_evaluations,
+            seed,
+        )
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "init")]
+    pub fn py_init(
+        &mut self,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<()> {
+        self.init(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok(())
+    }
+
+    #[pyo3(name = "step")]
+    pub fn py_step<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<(
+        bool,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+    )> {
+        let (done, best_params, simulation, objectives) = self.step(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok((
+            done,
+            best_params.to_pyarray(py),
+            simulation.to_pyarray(py),
+            objectives.to_pyarray(py),
+        ))
+    }
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "dds")?;
+    m.add_class::<Dds>()?;
+    Ok(m)
+}
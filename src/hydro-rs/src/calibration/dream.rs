@@ -0,0 +1,601 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{s, Array1, Array2, ArrayView1, Axis};
+use ndarray_rand::rand_distr::{StandardNormal, Uniform};
+use ndarray_rand::RandomExt;
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::calibration::utils::{
+    build_model, evaluate_simulation, CalibrationParams, Objective,
+    N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Likelihood {
+    Gaussian,
+    Heteroscedastic,
+}
+
+impl FromStr for Likelihood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gaussian" => Ok(Self::Gaussian),
+            "heteroscedastic" => Ok(Self::Heteroscedastic),
+            _ => Err(format!(
+                "Unknown likelihood '{}'. Valid options: gaussian, heteroscedastic",
+                s
+            )),
+        }
+    }
+}
+
+struct DreamParams {
+    pub chains: Array2<f64>,
+    pub log_likelihoods: Array1<f64>,
+    pub chain_objectives: Array2<f64>,
+    pub archive: Array2<f64>,
+    pub samples: Vec<Array2<f64>>,
+    pub sample_log_likelihoods: Vec<Array1<f64>>,
+    pub best_log_likelihood: f64,
+    pub best_objectives: Array1<f64>,
+    pub likelihood: Likelihood,
+    pub n_calls: usize,
+    pub n_accepted: usize,
+    pub n_chains: usize,
+    pub n_pairs: usize,
+    pub n_crossover: usize,
+    pub archive_thinning: usize,
+    pub max_evaluations: usize,
+}
+
+/// DREAM(ZS) Markov chain Monte Carlo sampler (Vrugt et al., 2009; ter
+/// Braak and Vrugt, 2008) with a uniform prior within the parameter bounds.
+///
+/// A few chains evolve by differential evolution proposals drawn from an
+/// archive of past states, which is initialized from the prior and grows by
+/// one state per chain every `archive_thinning` generations, each step
+/// running that many generations. The returned parameters are those with
+/// the highest likelihood, the posterior being available through
+/// `posterior`.
+#[pyclass(module = "hydro_rs.calibration.dream", unsendable)]
+pub struct Dream {
+    calibration_params: CalibrationParams,
+    dream_params: DreamParams,
+}
+
+impl Dream {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        likelihood: Likelihood,
+        n_chains: usize,
+        n_pairs: usize,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        if n_chains == 0 {
+            return Err(Error::Dream("n_chains must be positive".to_string()));
+        }
+        if n_pairs == 0 {
+            return Err(Error::Dream("n_pairs must be positive".to_string()));
+        }
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+        let n_params = params.len();
+
+        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // large enough to start every chain from a state of the archive
+        let archive = sample_prior(
+            (10 * n_params).max(n_chains),
+            lower_bounds.view(),
+            upper_bounds.view(),
+            &mut rng,
+        );
+        let chains = archive.slice(s![..n_chains, ..]).to_owned();
+
+        let calibration_params = CalibrationParams {
+            params,
+            simulate,
+            lower_bounds,
+            upper_bounds,
+            // unused, the chains being driven by the likelihood
+            objective: Objective::Nse,
+            rng,
+            done: false,
+        };
+        let dream_params = DreamParams {
+            chains,
+            log_likelihoods: Array1::from_elem(n_chains, f64::NEG_INFINITY),
+            chain_objectives: Array2::zeros((n_chains, N_METRICS)),
+            archive,
+            samples: vec![],
+            sample_log_likelihoods: vec![],
+            best_log_likelihood: f64::NEG_INFINITY,
+            best_objectives: Array1::zeros(N_METRICS),
+            likelihood,
+            n_calls: 0,
+            n_accepted: 0,
+            n_chains,
+            n_pairs,
+            n_crossover: 3,
+            archive_thinning: 10,
+            max_evaluations,
+        };
+
+        Ok(Dream {
+            calibration_params,
+            dream_params,
+        })
+    }
+
+    pub fn init<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        let results = evaluate_points(
+            &self.calibration_params.simulate,
+            data,
+            metadata,
+            observations,
+            &self.dream_params.chains,
+            self.dream_params.likelihood,
+        )?;
+        self.dream_params.n_calls += results.len();
+
+        for (i, (log_likelihood, objectives)) in
+            results.into_iter().enumerate()
+        {
+            self.dream_params.log_likelihoods[i] = log_likelihood;
+            self.dream_params
+                .chain_objectives
+                .row_mut(i)
+                .assign(&objectives);
+            self.update_best(i);
+        }
+
+        Ok(())
+    }
+
+    pub fn step<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(bool, Array1<f64>, Array1<f64>, Array1<f64>), Error> {
+        if !self.calibration_params.done {
+            for _ in 0..self.dream_params.archive_thinning {
+                if self.dream_params.n_calls
+                    >= self.dream_params.max_evaluations
+                {
+                    break;
+                }
+                self.evolve_chains(data, metadata, observations)?;
+            }
+
+            self.dream_params
+                .archive
+                .append(Axis(0), self.dream_params.chains.view())
+                .unwrap();
+
+            self.calibration_params.done =
+                self.dream_params.n_calls >= self.dream_params.max_evaluations;
+        }
+
+        let best_simulation = (self.calibration_params.simulate)(
+            self.calibration_params.params.view(),
+            data,
+            metadata,
+        )?;
+
+        Ok((
+            self.calibration_params.done,
+            self.calibration_params.params.clone(),
+            best_simulation,
+            self.dream_params.best_objectives.clone(),
+        ))
+    }
+
+    /// Returns the states of the chains after discarding the first
+    /// `burn_in` fraction of the generations, their log-likelihoods, the
+    /// acceptance rate of the proposals and the Gelman-Rubin statistic of
+    /// each parameter (convergence being usually assumed below 1.2).
+    pub fn posterior(
+        &self,
+        burn_in: f64,
+    ) -> (Array2<f64>, Array1<f64>, f64, Array1<f64>) {
+        let n_params = self.calibration_params.params.len();
+        let n_generations = self.dream_params.samples.len();
+        let start = ((n_generations as f64 * burn_in.clamp(0., 1.)).floor()
+            as usize)
+            .min(n_generations);
+
+        let generations = &self.dream_params.samples[start..];
+        let samples = if generations.is_empty() {
+            Array2::zeros((0, n_params))
+        } else {
+            ndarray::concatenate(
+                Axis(0),
+                &generations.iter().map(|x| x.view()).collect::<Vec<_>>(),
+            )
+            .unwrap()
+        };
+        let log_likelihoods = Array1::from_iter(
+            self.dream_params.sample_log_likelihoods[start..]
+                .iter()
+                .flatten()
+                .copied(),
+        );
+
+        let n_proposals = self.dream_params.n_calls
+            - self.dream_params.n_chains.min(self.dream_params.n_calls);
+        let acceptance_rate = if n_proposals > 0 {
+            self.dream_params.n_accepted as f64 / n_proposals as f64
+        } else {
+            0.
+        };
+
+        let r_hat = compute_gelman_rubin(generations, n_params);
+
+        (samples, log_likelihoods, acceptance_rate, r_hat)
+    }
+
+    fn evolve_chains<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        let proposals = self.generate_proposals();
+        let results = evaluate_points(
+            &self.calibration_params.simulate,
+            data,
+            metadata,
+            observations,
+            &proposals,
+            self.dream_params.likelihood,
+        )?;
+        self.dream_params.n_calls += results.len();
+
+        for (i, (log_likelihood, objectives)) in
+            results.into_iter().enumerate()
+        {
+            let log_ratio =
+                log_likelihood - self.dream_params.log_likelihoods[i];
+            let u: f64 = self.calibration_params.rng.random();
+            if log_ratio >= 0. || u.ln() < log_ratio {
+                self.dream_params
+                    .chains
+                    .row_mut(i)
+                    .assign(&proposals.row(i));
+                self.dream_params.log_likelihoods[i] = log_likelihood;
+                self.dream_params
+                    .chain_objectives
+                    .row_mut(i)
+                    .assign(&objectives);
+                self.dream_params.n_accepted += 1;
+                self.update_best(i);
+            }
+        }
+
+        self.dream_params
+            .samples
+            .push(self.dream_params.chains.clone());
+        self.dream_params
+            .sample_log_likelihoods
+            .push(self.dream_params.log_likelihoods.clone());
+
+        Ok(())
+    }
+
+    /// Differential evolution proposals from pairs of archived states, with
+    /// randomized subspace sampling and occasional unit jumps allowing
+    /// moves between modes.
+    fn generate_proposals(&mut self) -> Array2<f64> {
+        let rng = &mut self.calibration_params.rng;
+        let lower_bounds = &self.calibration_params.lower_bounds;
+        let upper_bounds = &self.calibration_params.upper_bounds;
+        let archive = &self.dream_params.archive;
+        let n_params = lower_bounds.len();
+        let n_pairs = self.dream_params.n_pairs;
+        let n_crossover = self.dream_params.n_crossover;
+
+        let mut proposals = self.dream_params.chains.clone();
+
+        for i in 0..self.dream_params.n_chains {
+            let indices = rand::seq::index::sample(
+                rng,
+                archive.nrows(),
+                (2 * n_pairs).min(archive.nrows()),
+            )
+            .into_vec();
+            let (first, second) = indices.split_at(indices.len() / 2);
+
+            let crossover =
+                rng.random_range(1..=n_crossover) as f64 / n_crossover as f64;
+            let mut dimensions: Vec<usize> = (0..n_params)
+                .filter(|_| rng.random::<f64>() < crossover)
+                .collect();
+            if dimensions.is_empty() {
+                dimensions.push(rng.random_range(0..n_params));
+            }
+
+            let gamma = if rng.random::<f64>() < 0.2 {
+                1.
+            } else {
+                2.38 / (2. * n_pairs as f64 * dimensions.len() as f64).sqrt()
+            };
+
+            for &j in &dimensions {
+                let difference: f64 = first
+                    .iter()
+                    .zip(second)
+                    .map(|(&a, &b)| archive[[a, j]] - archive[[b, j]])
+                    .sum();
+                let e: f64 = rng.sample(Uniform::new(-0.05, 0.05).unwrap());
+                let epsilon: f64 = rng.sample::<f64, _>(StandardNormal) * 1e-6;
+                let value = proposals[[i, j]]
+                    + (1. + e) * gamma * difference
+                    + epsilon;
+                proposals[[i, j]] =
+                    fold_into_bounds(value, lower_bounds[j], upper_bounds[j]);
+            }
+        }
+
+        proposals
+    }
+
+    fn update_best(&mut self, chain: usize) {
+        if self.dream_params.log_likelihoods[chain]
+            > self.dream_params.best_log_likelihood
+        {
+            self.dream_params.best_log_likelihood =
+                self.dream_params.log_likelihoods[chain];
+            self.dream_params.best_objectives =
+                self.dream_params.chain_objectives.row(chain).to_owned();
+            self.calibration_params.params =
+                self.dream_params.chains.row(chain).to_owned();
+        }
+    }
+}
+
+#[pymethods]
+impl Dream {
+    #[new]
+    #[pyo3(signature = (
+        climate_model,
+        snow_model,
+        likelihood,
+        max_evaluations,
+        seed,
+        n_chains=3,
+        n_pairs=1,
+    ))]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        likelihood: &str,
+        max_evaluations: usize,
+        seed: u64,
+        n_chains: usize,
+        n_pairs: usize,
+    ) -> PyResult<Self> {
+        let likelihood = Likelihood::from_str(likelihood)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Dream::new(
+            climate_model,
+            snow_model,
+            likelihood,
+            n_chains,
+            n_pairs,
+            max_evaluations,
+            seed,
+        )
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "init")]
+    pub fn py_init(
+        &mut self,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<()> {
+        self.init(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok(())
+    }
+
+    #[pyo3(name = "step")]
+    pub fn py_step<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<(
+        bool,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+    )> {
+        let (done, best_params, simulation, objectives) = self.step(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok((
+            done,
+            best_params.to_pyarray(py),
+            simulation.to_pyarray(py),
+            objectives.to_pyarray(py),
+        ))
+    }
+
+    #[pyo3(name = "posterior")]
+    #[pyo3(signature = (burn_in=0.5))]
+    pub fn py_posterior<'py>(
+        &self,
+        py: Python<'py>,
+        burn_in: f64,
+    ) -> (
+        Bound<'py, PyArray2<f64>>,
+        Bound<'py, PyArray1<f64>>,
+        f64,
+        Bound<'py, PyArray1<f64>>,
+    ) {
+        let (samples, log_likelihoods, acceptance_rate, r_hat) =
+            self.posterior(burn_in);
+        (
+            samples.to_pyarray(py),
+            log_likelihoods.to_pyarray(py),
+            acceptance_rate,
+            r_hat.to_pyarray(py),
+        )
+    }
+}
+
+fn sample_prior(
+    n_samples: usize,
+    lower_bounds: ArrayView1<f64>,
+    upper_bounds: ArrayView1<f64>,
+    rng: &mut ChaCha8Rng,
+) -> Array2<f64> {
+    let random_values: Array2<f64> = Array2::random_using(
+        (n_samples, lower_bounds.len()),
+        Uniform::new(0., 1.).unwrap(),
+        rng,
+    );
+    &random_values * &(&upper_bounds - &lower_bounds) + lower_bounds
+}
+
+fn evaluate_points(
+    simulate: &SimulateFn,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    points: &Array2<f64>,
+    likelihood: Likelihood,
+) -> Result<Vec<(f64, Array1<f64>)>, Error> {
+    (0..points.nrows())
+        .into_par_iter()
+        .map(|i| {
+            let simulation = simulate(points.row(i), data, metadata)?;
+            let objectives =
+                evaluate_simulation(observations, simulation.view())?;
+            let log_likelihood = compute_log_likelihood(
+                observations,
+                simulation.view(),
+                likelihood,
+            );
+            Ok((log_likelihood, objectives))
+        })
+        .collect()
+}
+
+/// Log-likelihood of independent normal errors, with the error standard
+/// deviation set to its maximum likelihood estimate. For the
+/// heteroscedastic likelihood, the standard deviation is proportional to
+/// the observed discharge plus a tenth of its mean. Timesteps with missing
+/// observations are skipped.
+fn compute_log_likelihood(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    likelihood: Likelihood,
+) -> f64 {
+    let valid = || {
+        observations
+            .iter()
+            .zip(simulations)
+            .filter(|(o, _)| !o.is_nan())
+    };
+    let n = valid().count() as f64;
+    let log_likelihood = match likelihood {
+        Likelihood::Gaussian => {
+            let sse: f64 = valid().map(|(o, s)| (o - s).powi(2)).sum();
+            -n / 2. * (sse / n).ln()
+        }
+        Likelihood::Heteroscedastic => {
+            let offset = 0.1 * valid().map(|(o, _)| o).sum::<f64>() / n;
+            let (weighted_sse, log_scales) =
+                valid().fold((0., 0.), |(sse, logs), (&o, &s)| {
+                    let scale = (offset + o.abs()).max(f64::EPSILON);
+                    (sse + ((o - s) / scale).powi(2), logs + scale.ln())
+                });
+            -log_scales - n / 2. * (weighted_sse / n).ln()
+        }
+    };
+    if log_likelihood.is_nan() {
+        f64::NEG_INFINITY
+    } else {
+        log_likelihood
+    }
+}
+
+/// Folds a value back into the bounds, as if the parameter space wrapped
+/// around.
+fn fold_into_bounds(value: f64, lower: f64, upper: f64) -> f64 {
+    let range = upper - lower;
+    if range <= 0. {
+        return lower;
+    }
+    lower + (value - lower).rem_euclid(range)
+}
+
+/// Gelman-Rubin potential scale reduction factor of each parameter, from
+/// generations of n_chains x n_params states.
+fn compute_gelman_rubin(
+    generations: &[Array2<f64>],
+    n_params: usize,
+) -> Array1<f64> {
+    let n = generations.len();
+    if n < 2 || generations[0].nrows() < 2 {
+        return Array1::from_elem(n_params, f64::NAN);
+    }
+    let n_chains = generations[0].nrows();
+    let states = ndarray::stack(
+        Axis(0),
+        &generations.iter().map(|x| x.view()).collect::<Vec<_>>(),
+    )
+    .unwrap(); // generations x chains x params
+
+    let chain_means = states.mean_axis(Axis(0)).unwrap();
+    let chain_variances = states.var_axis(Axis(0), 1.);
+    let within = chain_variances.mean_axis(Axis(0)).unwrap();
+    let between = chain_means.var_axis(Axis(0), 1.) * n as f64;
+
+    let n = n as f64;
+    let pooled = (n - 1.) / n * &within
+        + (n_chains as f64 + 1.) / (n_chains as f64 * n) * &between;
+    Array1::from_iter(pooled.iter().zip(&within).map(|(&v, &w)| {
+        if w > 0. {
+            (v / w).sqrt()
+        } else {
+            f64::NAN
+        }
+    }))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "dream")?;
+    m.add_class::<Dream>()?;
+    Ok(m)
+}
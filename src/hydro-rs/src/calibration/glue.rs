@@ -0,0 +1,319 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
+use ndarray_rand::rand_distr::Uniform;
+use ndarray_rand::RandomExt;
+use numpy::{
+    PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2, ToPyArray,
+};
+use pyo3::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::calibration::utils::{
+    build_model, evaluate_simulation, sample_latin_hypercube, stack_rows,
+    Objective, N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Sampling {
+    Uniform,
+    LatinHypercube,
+}
+
+impl FromStr for Sampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uniform" => Ok(Self::Uniform),
+            "lhs" => Ok(Self::LatinHypercube),
+            _ => Err(format!(
+                "Unknown sampling '{}'. Valid options: uniform, lhs",
+                s
+            )),
+        }
+    }
+}
+
+/// Generalized Likelihood Uncertainty Estimation (Beven and Binley, 1992).
+///
+/// Evaluates `n_samples` parameter sets and keeps the behavioural ones,
+/// whose objective is better than `threshold`. Their likelihood is the
+/// distance of the objective to the threshold, normalized to sum to 1, and
+/// is used to weight their simulations when computing the prediction
+/// `quantiles`.
+///
+/// Returns the behavioural parameter sets, their likelihoods and objectives
+/// (see `evaluate_simulation`) and the prediction quantiles (quantiles x
+/// timesteps).
+pub fn run(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: Objective,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    n_samples: usize,
+    threshold: f64,
+    sampling: Sampling,
+    quantiles: ArrayView1<f64>,
+    seed: u64,
+) -> Result<(Array2<f64>, Array1<f64>, Array2<f64>, Array2<f64>), Error> {
+    let (simulate, params, bounds) = build_model(climate_model, snow_model)?;
+    let (objective_idx, is_minimization) = objective.index();
+
+    let lower_bounds = bounds.column(0);
+    let upper_bounds = bounds.column(1);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+    let samples = match sampling {
+        Sampling::Uniform => {
+            let random_values: Array2<f64> = Array2::random_using(
+                (n_samples, params.len()),
+                Uniform::new(0., 1.).unwrap(),
+                &mut rng,
+            );
+            &random_values * &(&upper_bounds - &lower_bounds) + lower_bounds
+        }
+        Sampling::LatinHypercube => sample_latin_hypercube(
+            n_samples,
+            lower_bounds,
+            upper_bounds,
+            &mut rng,
+        ),
+    };
+
+    let results: Vec<Result<Option<(f64, Array1<f64>, Array1<f64>)>, Error>> =
+        (0..n_samples)
+            .into_par_iter()
+            .map(|i| {
+                let simulation = simulate(samples.row(i), data, metadata)?;
+                let objectives =
+                    evaluate_simulation(observations, simulation.view())?;
+                let distance = if is_minimization {
+                    threshold - objectives[objective_idx]
+                } else {
+                    objectives[objective_idx] - threshold
+                };
+                Ok((distance > 0.)
+                    .then_some((distance, objectives, simulation)))
+            })
+            .collect();
+
+    let mut behavioural: Vec<usize> = vec![];
+    let mut likelihoods: Vec<f64> = vec![];
+    let mut objectives: Vec<Array1<f64>> = vec![];
+    let mut simulations: Vec<Array1<f64>> = vec![];
+    for (i, result) in results.into_iter().enumerate() {
+        if let Some((likelihood, objective, simulation)) = result? {
+            behavioural.push(i);
+            likelihoods.push(likelihood);
+            objectives.push(objective);
+            simulations.push(simulation);
+        }
+    }
+
+    let total: f64 = likelihoods.iter().sum();
+    let likelihoods: Array1<f64> =
+        Array1::from_iter(likelihoods.iter().map(|l| l / total));
+    let behavioural_params = samples.select(Axis(0), &behavioural);
+    let objectives = stack_rows(&objectives, N_METRICS);
+    let prediction_quantiles = compute_weighted_quantiles(
+        &simulations,
+        likelihoods.view(),
+        quantiles,
+        observations.len(),
+    );
+
+    Ok((
+        behavioural_params,
+        likelihoods,
+        objectives,
+        prediction_quantiles,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "run")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    objective,
+    data,
+    metadata,
+    observations,
+    n_samples,
+    threshold,
+    seed,
+    sampling="lhs",
+    quantiles=vec![0.05, 0.5, 0.95],
+))]
+pub fn py_run<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    n_samples: usize,
+    threshold: f64,
+    seed: u64,
+    sampling: &str,
+    quantiles: Vec<f64>,
+) -> PyResult<(
+    Bound<'py, PyArray2<f64>>,
+    Bound<'py, PyArray1<f64>>,
+    Bound<'py, PyArray2<f64>>,
+    Bound<'py, PyArray2<f64>>,
+)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let sampling = Sampling::from_str(sampling)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (params, likelihoods, objectives, prediction_quantiles) = run(
+        climate_model,
+        snow_model,
+        objective,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        n_samples,
+        threshold,
+        sampling,
+        ArrayView1::from(&quantiles),
+        seed,
+    )?;
+    Ok((
+        params.to_pyarray(py),
+        likelihoods.to_pyarray(py),
+        objectives.to_pyarray(py),
+        prediction_quantiles.to_pyarray(py),
+    ))
+}
+
+/// Prediction bounds of behavioural parameter sets, such as the ones kept
+/// by `run` or taken from the `Sce` trace.
+///
+/// Simulates every parameter set and weights it by its likelihood, which
+/// must be non-negative and is normalized to sum to 1, all sets having the
+/// same weight without likelihoods.
+///
+/// Returns the prediction quantiles (quantiles x timesteps).
+pub fn predict(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    data: Data,
+    metadata: &Metadata,
+    params: ArrayView2<f64>,
+    likelihoods: Option<ArrayView1<f64>>,
+    quantiles: ArrayView1<f64>,
+) -> Result<Array2<f64>, Error> {
+    let (simulate, _, _) = build_model(climate_model, snow_model)?;
+    let n_sets = params.nrows();
+
+    let likelihoods = match likelihoods {
+        Some(likelihoods) if likelihoods.len() != n_sets => {
+            return Err(Error::InputLengthMismatch(
+                "params",
+                "likelihoods",
+                n_sets,
+                likelihoods.len(),
+            ));
+        }
+        Some(likelihoods) => &likelihoods / likelihoods.sum(),
+        None => Array1::from_elem(n_sets, 1. / n_sets as f64),
+    };
+
+    let simulations = (0..n_sets)
+        .into_par_iter()
+        .map(|i| simulate(params.row(i), data, metadata))
+        .collect::<Result<Vec<Array1<f64>>, Error>>()?;
+
+    Ok(compute_weighted_quantiles(
+        &simulations,
+        likelihoods.view(),
+        quantiles,
+        data.precipitation.len(),
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "predict")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    data,
+    metadata,
+    params,
+    likelihoods=None,
+    quantiles=vec![0.05, 0.5, 0.95],
+))]
+pub fn py_predict<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    params: PyReadonlyArray2<'_, f64>,
+    likelihoods: Option<PyReadonlyArray1<'_, f64>>,
+    quantiles: Vec<f64>,
+) -> PyResult<Bound<'py, PyArray2<f64>>> {
+    let prediction_quantiles = predict(
+        climate_model,
+        snow_model,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        params.as_array(),
+        likelihoods
+            .as_ref()
+            .map(|likelihoods| likelihoods.as_array()),
+        ArrayView1::from(&quantiles),
+    )?;
+    Ok(prediction_quantiles.to_pyarray(py))
+}
+
+/// Quantiles of the simulations at each timestep, each simulation being
+/// weighted by its likelihood. They are NaN without behavioural sets.
+fn compute_weighted_quantiles(
+    simulations: &[Array1<f64>],
+    weights: ArrayView1<f64>,
+    quantiles: ArrayView1<f64>,
+    n_timesteps: usize,
+) -> Array2<f64> {
+    let mut prediction_quantiles =
+        Array2::from_elem((quantiles.len(), n_timesteps), f64::NAN);
+    if simulations.is_empty() {
+        return prediction_quantiles;
+    }
+
+    let mut order: Vec<usize> = (0..simulations.len()).collect();
+    for t in 0..n_timesteps {
+        order
+            .sort_by(|&a, &b| simulations[a][t].total_cmp(&simulations[b][t]));
+        for (q, &quantile) in quantiles.iter().enumerate() {
+            let mut cumulative = 0.;
+            for &i in &order {
+                cumulative += weights[i];
+                prediction_quantiles[[q, t]] = simulations[i][t];
+                if cumulative >= quantile {
+                    break;
+                }
+            }
+        }
+    }
+    prediction_quantiles
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "glue")?;
+    m.add_function(wrap_pyfunction!(py_run, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_predict, &m)?)?;
+    Ok(m)
+}
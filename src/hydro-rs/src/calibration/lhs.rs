@@ -0,0 +1,127 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1};
+use numpy::{PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::calibration::utils::{
+    build_model, evaluate_params, sample_latin_hypercube, sort_population,
+    Objective, Scoring,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
+
+/// Evaluates a Latin hypercube sample of `n_samples` parameter sets.
+///
+/// Returns the parameter sets and their objectives (see
+/// `evaluate_simulation`), sorted from best to worst.
+pub fn run(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: Objective,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    n_samples: usize,
+    seed: u64,
+) -> Result<(Array2<f64>, Array2<f64>), Error> {
+    let (simulate, _, bounds) = build_model(climate_model, snow_model)?;
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let population = sample_latin_hypercube(
+        n_samples,
+        bounds.column(0),
+        bounds.column(1),
+        &mut rng,
+    );
+    evaluate_population(
+        &simulate,
+        data,
+        metadata,
+        observations,
+        population,
+        objective,
+        &Scoring::default(),
+    )
+}
+
+/// Evaluates the population in parallel and sorts it from best to worst.
+pub fn evaluate_population(
+    simulate: &SimulateFn,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    mut population: Array2<f64>,
+    objective: Objective,
+    scoring: &Scoring,
+) -> Result<(Array2<f64>, Array2<f64>), Error> {
+    let n_population = population.nrows();
+    let mut objectives =
+        Array2::<f64>::zeros((n_population, scoring.n_objectives()));
+
+    let results: Vec<Result<Array1<f64>, Error>> = (0..n_population)
+        .into_par_iter()
+        .map(|i| {
+            evaluate_params(
+                simulate,
+                population.row(i),
+                data,
+                metadata,
+                observations,
+                scoring,
+            )
+        })
+        .collect();
+    for (i, result) in results.into_iter().enumerate() {
+        objectives.row_mut(i).assign(&result?);
+    }
+
+    let (objective_idx, is_minimization) = objective.index();
+
+    sort_population(
+        &mut population,
+        &mut objectives,
+        objective_idx,
+        is_minimization,
+    );
+
+    Ok((population, objectives))
+}
+
+#[pyfunction]
+#[pyo3(name = "run")]
+pub fn py_run<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    n_samples: usize,
+    seed: u64,
+) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (params, objectives) = run(
+        climate_model,
+        snow_model,
+        objective,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        n_samples,
+        seed,
+    )?;
+    Ok((params.to_pyarray(py), objectives.to_pyarray(py)))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "lhs")?;
+    m.add_function(wrap_pyfunction!(py_run, &m)?)?;
+    Ok(m)
+}
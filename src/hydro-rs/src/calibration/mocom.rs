@@ -0,0 +1,355 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use numpy::{PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::calibration::utils::{
+    build_model, dominates, evaluate_population_criteria, parse_criteria,
+    sample_latin_hypercube, sort_non_dominated, CalibrationParams, Criterion,
+    Objective, Transformation,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+struct MocomParams {
+    pub population: Array2<f64>,
+    pub values: Array2<f64>,
+    pub ranks: Vec<usize>,
+    pub criteria: Vec<Criterion>,
+    pub n_calls: usize,
+    pub max_evaluations: usize,
+}
+
+/// Multi-Objective Complex Evolution (Yapo et al., 1998).
+///
+/// Each step ranks the population by Pareto dominance and evolves every
+/// point of the worst front with a downhill simplex whose other vertices are
+/// drawn from the population, favouring the best ranks. The search stops
+/// when the whole population is non-dominated or after `max_evaluations`.
+/// Returns the current non-dominated front with the value of each criterion.
+#[pyclass(module = "hydro_rs.calibration.mocom", unsendable)]
+pub struct Mocom {
+    calibration_params: CalibrationParams,
+    mocom_params: MocomParams,
+}
+
+impl Mocom {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        criteria: Vec<Criterion>,
+        population_size: usize,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+
+        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // each simplex needs as many other points as there are parameters
+        let population_size = population_size.max(params.len() + 2);
+        let population = sample_latin_hypercube(
+            population_size,
+            lower_bounds.view(),
+            upper_bounds.view(),
+            &mut rng,
+        );
+
+        let calibration_params = CalibrationParams {
+            params,
+            simulate,
+            lower_bounds,
+            upper_bounds,
+            objective: criteria[0].objective,
+            rng,
+            done: false,
+        };
+        let mocom_params = MocomParams {
+            population,
+            values: Array2::zeros((population_size, criteria.len())),
+            ranks: vec![0; population_size],
+            criteria,
+            n_calls: 0,
+            max_evaluations,
+        };
+
+        Ok(Mocom {
+            calibration_params,
+            mocom_params,
+        })
+    }
+
+    pub fn init<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        self.mocom_params.values = evaluate_population_criteria(
+            &self.calibration_params.simulate,
+            data,
+            metadata,
+            observations,
+            &self.mocom_params.population,
+            &self.mocom_params.criteria,
+        )?;
+        self.mocom_params.n_calls += self.mocom_params.population.nrows();
+        self.rank_population();
+        Ok(())
+    }
+
+    pub fn step<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(bool, Array2<f64>, Array2<f64>), Error> {
+        if !self.calibration_params.done {
+            let max_rank = *self.mocom_params.ranks.iter().max().unwrap();
+            let worst: Vec<usize> = (0..self.mocom_params.ranks.len())
+                .filter(|&i| self.mocom_params.ranks[i] == max_rank)
+                .collect();
+            let simplexes: Vec<Vec<usize>> =
+                worst.iter().map(|&i| self.select_simplex(i)).collect();
+
+            // reflect each worst point through the centroid of its simplex
+            let centroids = Array2::from_shape_fn(
+                (worst.len(), self.mocom_params.population.ncols()),
+                |(k, j)| {
+                    simplexes[k]
+                        .iter()
+                        .map(|&i| self.mocom_params.population[[i, j]])
+                        .sum::<f64>()
+                        / simplexes[k].len() as f64
+                },
+            );
+            let worst_points =
+                self.mocom_params.population.select(Axis(0), &worst);
+            let reflected = 2. * &centroids - &worst_points;
+            let reflected_values = evaluate_population_criteria(
+                &self.calibration_params.simulate,
+                data,
+                metadata,
+                observations,
+                &self.clip(reflected.clone()),
+                &self.mocom_params.criteria,
+            )?;
+            self.mocom_params.n_calls += worst.len();
+
+            // contract when the reflection leaves the bounds or is dominated
+            // by a vertex of its simplex
+            let needs_contraction: Vec<usize> = (0..worst.len())
+                .filter(|&k| {
+                    !self.is_in_bounds(reflected.row(k))
+                        || simplexes[k].iter().any(|&i| {
+                            dominates(
+                                self.mocom_params.values.row(i),
+                                reflected_values.row(k),
+                            )
+                        })
+                })
+                .collect();
+            let contracted = 0.5
+                * (&centroids.select(Axis(0), &needs_contraction)
+                    + &worst_points.select(Axis(0), &needs_contraction));
+            let contracted_values = evaluate_population_criteria(
+                &self.calibration_params.simulate,
+                data,
+                metadata,
+                observations,
+                &contracted,
+                &self.mocom_params.criteria,
+            )?;
+            self.mocom_params.n_calls += needs_contraction.len();
+
+            for (k, &i) in worst.iter().enumerate() {
+                let (point, values) = match needs_contraction
+                    .iter()
+                    .position(|&c| c == k)
+                {
+                    Some(c) => (contracted.row(c), contracted_values.row(c)),
+                    None => (reflected.row(k), reflected_values.row(k)),
+                };
+                self.mocom_params.population.row_mut(i).assign(&point);
+                self.mocom_params.values.row_mut(i).assign(&values);
+            }
+
+            self.rank_population();
+            self.calibration_params.done = self.mocom_params.n_calls
+                >= self.mocom_params.max_evaluations
+                || self.mocom_params.ranks.iter().all(|&r| r == 0);
+        }
+
+        let (front_params, front_objectives) = self.front();
+        Ok((self.calibration_params.done, front_params, front_objectives))
+    }
+
+    /// Returns the parameters of the first front and the value of each
+    /// criterion.
+    pub fn front(&self) -> (Array2<f64>, Array2<f64>) {
+        let front: Vec<usize> = (0..self.mocom_params.ranks.len())
+            .filter(|&i| self.mocom_params.ranks[i] == 0)
+            .collect();
+        let params = self.mocom_params.population.select(Axis(0), &front);
+        let mut objectives = self.mocom_params.values.select(Axis(0), &front);
+        for (j, criterion) in self.mocom_params.criteria.iter().enumerate() {
+            objectives
+                .column_mut(j)
+                .mapv_inplace(|x| criterion.to_objective(x));
+        }
+        (params, objectives)
+    }
+
+    fn rank_population(&mut self) {
+        for (rank, front) in sort_non_dominated(&self.mocom_params.values)
+            .iter()
+            .enumerate()
+        {
+            for &i in front {
+                self.mocom_params.ranks[i] = rank;
+            }
+        }
+    }
+
+    /// Draws as many distinct points as there are parameters, excluding
+    /// `worst`, with a probability decreasing linearly with their rank.
+    fn select_simplex(&mut self, worst: usize) -> Vec<usize> {
+        let n_params = self.calibration_params.lower_bounds.len();
+        let ranks = &self.mocom_params.ranks;
+        let max_rank = *ranks.iter().max().unwrap();
+        let mut candidates: Vec<usize> =
+            (0..ranks.len()).filter(|&i| i != worst).collect();
+        let mut weights: Vec<f64> = candidates
+            .iter()
+            .map(|&i| (max_rank - ranks[i] + 1) as f64)
+            .collect();
+
+        let mut selected = vec![];
+        for _ in 0..n_params {
+            let total: f64 = weights.iter().sum();
+            let mut u = self.calibration_params.rng.random::<f64>() * total;
+            let mut k = 0;
+            while k < weights.len() - 1 && u >= weights[k] {
+                u -= weights[k];
+                k += 1;
+            }
+            selected.push(candidates.remove(k));
+            weights.remove(k);
+        }
+        selected
+    }
+
+    fn clip(&self, mut points: Array2<f64>) -> Array2<f64> {
+        let lower_bounds = &self.calibration_params.lower_bounds;
+        let upper_bounds = &self.calibration_params.upper_bounds;
+        for mut point in points.rows_mut() {
+            for j in 0..point.len() {
+                point[j] = point[j].clamp(lower_bounds[j], upper_bounds[j]);
+            }
+        }
+        points
+    }
+
+    fn is_in_bounds(&self, point: ArrayView1<f64>) -> bool {
+        point
+            .iter()
+            .zip(&self.calibration_params.lower_bounds)
+            .zip(&self.calibration_params.upper_bounds)
+            .all(|((x, l), u)| x >= l && x <= u)
+    }
+}
+
+#[pymethods]
+impl Mocom {
+    #[new]
+    #[pyo3(signature = (
+        climate_model,
+        snow_model,
+        max_evaluations,
+        seed,
+        criteria=None,
+        population_size=100,
+    ))]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        max_evaluations: usize,
+        seed: u64,
+        criteria: Option<Vec<(String, String)>>,
+        population_size: usize,
+    ) -> PyResult<Self> {
+        // the three metrics by default
+        let criteria = match criteria {
+            Some(criteria) => parse_criteria(&criteria)
+                .map_err(pyo3::exceptions::PyValueError::new_err)?,
+            None => [Objective::Rmse, Objective::Nse, Objective::Kge]
+                .into_iter()
+                .map(|objective| Criterion {
+                    objective,
+                    transformation: Transformation::None,
+                })
+                .collect(),
+        };
+        if criteria.len() < 2 {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "At least two criteria are required.",
+            ));
+        }
+        Mocom::new(
+            climate_model,
+            snow_model,
+            criteria,
+            population_size,
+            max_evaluations,
+            seed,
+        )
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "init")]
+    pub fn py_init(
+        &mut self,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<()> {
+        self.init(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok(())
+    }
+
+    #[pyo3(name = "step")]
+    pub fn py_step<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<(bool, Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)>
+    {
+        let (done, front_params, front_objectives) = self.step(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok((
+            done,
+            front_params.to_pyarray(py),
+            front_objectives.to_pyarray(py),
+        ))
+    }
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "mocom")?;
+    m.add_class::<Mocom>()?;
+    Ok(m)
+}
@@ -1,4 +1,22 @@
+mod amalgam;
+mod archive;
+mod bayes_opt;
+mod checkpoint;
+mod dds;
+mod dream;
+mod glue;
+mod lhs;
+mod mocom;
+mod multi_objective;
+mod nelder_mead;
+mod nsga2;
+mod random_search;
+mod regional;
+mod regionalization;
 mod sce;
+mod sensitivity;
+mod split_sample;
+mod surrogate;
 mod utils;
 
 use crate::utils::register_submodule;
@@ -6,6 +24,98 @@ use pyo3::prelude::*;
 
 pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
     let m = PyModule::new(py, "calibration")?;
+    register_submodule(
+        py,
+        &m,
+        &amalgam::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &archive::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &bayes_opt::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(py, &m, &dds::make_module(py)?, "hydro_rs.calibration")?;
+    register_submodule(
+        py,
+        &m,
+        &dream::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &glue::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(py, &m, &lhs::make_module(py)?, "hydro_rs.calibration")?;
+    register_submodule(
+        py,
+        &m,
+        &mocom::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &multi_objective::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &nelder_mead::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &nsga2::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &random_search::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &regional::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &regionalization::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
     register_submodule(py, &m, &sce::make_module(py)?, "hydro_rs.calibration")?;
+    register_submodule(
+        py,
+        &m,
+        &sensitivity::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &split_sample::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
+    register_submodule(
+        py,
+        &m,
+        &surrogate::make_module(py)?,
+        "hydro_rs.calibration",
+    )?;
     Ok(m)
 }
@@ -0,0 +1,168 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array2, ArrayView1};
+use numpy::{PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::calibration::amalgam::Amalgam;
+use crate::calibration::mocom::Mocom;
+use crate::calibration::nsga2::Nsga2;
+use crate::calibration::utils::{parse_criteria, Criterion};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Algorithm {
+    Nsga2,
+    Mocom,
+    Amalgam,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nsga2" => Ok(Self::Nsga2),
+            "mocom" => Ok(Self::Mocom),
+            "amalgam" => Ok(Self::Amalgam),
+            _ => Err(format!(
+                "Unknown algorithm '{}'. Valid options: nsga2, mocom, amalgam",
+                s
+            )),
+        }
+    }
+}
+
+/// Calibrates the model on several criteria at once with `algorithm`,
+/// returning the non-dominated parameters and the value of each criterion
+/// once the search is done.
+pub fn run<'a>(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    criteria: Vec<Criterion>,
+    data: Data<'a>,
+    metadata: &Metadata<'a>,
+    observations: ArrayView1<f64>,
+    algorithm: Algorithm,
+    population_size: usize,
+    max_evaluations: usize,
+    seed: u64,
+) -> Result<(Array2<f64>, Array2<f64>), Error> {
+    match algorithm {
+        Algorithm::Nsga2 => {
+            let mut optimizer = Nsga2::new(
+                climate_model,
+                snow_model,
+                criteria,
+                population_size,
+                max_evaluations,
+                seed,
+            )?;
+            optimizer.init(data, metadata, observations)?;
+            loop {
+                let (done, params, objectives) =
+                    optimizer.step(data, metadata, observations)?;
+                if done {
+                    return Ok((params, objectives));
+                }
+            }
+        }
+        Algorithm::Mocom => {
+            let mut optimizer = Mocom::new(
+                climate_model,
+                snow_model,
+                criteria,
+                population_size,
+                max_evaluations,
+                seed,
+            )?;
+            optimizer.init(data, metadata, observations)?;
+            loop {
+                let (done, params, objectives) =
+                    optimizer.step(data, metadata, observations)?;
+                if done {
+                    return Ok((params, objectives));
+                }
+            }
+        }
+        Algorithm::Amalgam => {
+            let mut optimizer = Amalgam::new(
+                climate_model,
+                snow_model,
+                criteria,
+                population_size,
+                max_evaluations,
+                seed,
+            )?;
+            optimizer.init(data, metadata, observations)?;
+            loop {
+                let (done, params, objectives) =
+                    optimizer.step(data, metadata, observations)?;
+                if done {
+                    return Ok((params, objectives));
+                }
+            }
+        }
+    }
+}
+
+#[pyfunction]
+#[pyo3(name = "run")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    criteria,
+    data,
+    metadata,
+    observations,
+    max_evaluations,
+    seed,
+    algorithm="nsga2",
+    population_size=100,
+))]
+pub fn py_run<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    criteria: Vec<(String, String)>,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    max_evaluations: usize,
+    seed: u64,
+    algorithm: &str,
+    population_size: usize,
+) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)> {
+    let criteria = parse_criteria(&criteria)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    if !(2..=3).contains(&criteria.len()) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Expected 2 or 3 criteria, got {}.",
+            criteria.len()
+        )));
+    }
+    let algorithm = Algorithm::from_str(algorithm)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (params, objectives) = run(
+        climate_model,
+        snow_model,
+        criteria,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        algorithm,
+        population_size,
+        max_evaluations,
+        seed,
+    )?;
+    Ok((params.to_pyarray(py), objectives.to_pyarray(py)))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "multi_objective")?;
+    m.add_function(wrap_pyfunction!(py_run, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,245 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::calibration::utils::{
+    build_model, evaluate_params, Objective, Scoring,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
+
+const REFLECTION: f64 = 1.;
+const EXPANSION: f64 = 2.;
+const CONTRACTION: f64 = 0.5;
+const SHRINK: f64 = 0.5;
+// size of the initial simplex relative to the parameter ranges
+const INITIAL_STEP: f64 = 0.05;
+// relative spread of the simplex values under which the search stops
+const TOLERANCE: f64 = 1e-8;
+
+/// Bounded Nelder-Mead refinement (Nelder and Mead, 1965) of the
+/// parameters `start`, the points outside the bounds being clipped to them.
+///
+/// Returns the refined parameters, their objectives (see `evaluate_params`)
+/// and the number of evaluations used, which never exceeds
+/// `max_evaluations`. The start is returned unevaluated, with missing
+/// objectives, when the budget can't cover the initial simplex.
+pub fn polish(
+    simulate: &SimulateFn,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    objective: Objective,
+    start: ArrayView1<f64>,
+    lower_bounds: ArrayView1<f64>,
+    upper_bounds: ArrayView1<f64>,
+    max_evaluations: usize,
+    scoring: &Scoring,
+) -> Result<(Array1<f64>, Array1<f64>, usize), Error> {
+    let (objective_idx, is_minimization) = objective.index();
+    let evaluate = |params: ArrayView1<f64>| -> Result<Array1<f64>, Error> {
+        evaluate_params(
+            simulate,
+            params,
+            data,
+            metadata,
+            observations,
+            scoring,
+        )
+    };
+    // the simplex always minimizes, with failed simulations being the worst
+    let to_value = |objectives: &Array1<f64>| -> f64 {
+        let value = if is_minimization {
+            objectives[objective_idx]
+        } else {
+            -objectives[objective_idx]
+        };
+        if value.is_nan() {
+            f64::INFINITY
+        } else {
+            value
+        }
+    };
+    let clip = |point: Array1<f64>| -> Array1<f64> {
+        Array1::from_iter(
+            point
+                .iter()
+                .zip(lower_bounds.iter().zip(upper_bounds))
+                .map(|(x, (l, u))| x.clamp(*l, *u)),
+        )
+    };
+
+    let n_params = start.len();
+    if max_evaluations < n_params + 1 {
+        return Ok((
+            start.to_owned(),
+            Array1::from_elem(scoring.n_objectives(), f64::NAN),
+            0,
+        ));
+    }
+    let range = &upper_bounds - &lower_bounds;
+
+    let mut simplex = Array2::zeros((n_params + 1, n_params));
+    simplex.row_mut(0).assign(&start);
+    for j in 0..n_params {
+        let mut point = start.to_owned();
+        // step away from the closest bound
+        point[j] += if start[j] + INITIAL_STEP * range[j] <= upper_bounds[j] {
+            INITIAL_STEP * range[j]
+        } else {
+            -INITIAL_STEP * range[j]
+        };
+        simplex.row_mut(j + 1).assign(&clip(point));
+    }
+
+    let mut objectives: Vec<Array1<f64>> = vec![];
+    for point in simplex.rows() {
+        objectives.push(evaluate(point)?);
+    }
+    let mut values: Vec<f64> = objectives.iter().map(to_value).collect();
+    let mut n_calls = n_params + 1;
+
+    while n_calls < max_evaluations {
+        let mut order: Vec<usize> = (0..=n_params).collect();
+        order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+        simplex = simplex.select(Axis(0), &order);
+        objectives = order.iter().map(|&i| objectives[i].clone()).collect();
+        values = order.iter().map(|&i| values[i]).collect();
+
+        let (best, worst) = (values[0], values[n_params]);
+        if (worst - best).abs() <= TOLERANCE * (best.abs() + TOLERANCE) {
+            break;
+        }
+
+        let centroid = simplex
+            .slice(ndarray::s![..n_params, ..])
+            .mean_axis(Axis(0))
+            .unwrap();
+        let worst_point = simplex.row(n_params).to_owned();
+
+        let reflected =
+            clip(&centroid + REFLECTION * (&centroid - &worst_point));
+        let reflected_objectives = evaluate(reflected.view())?;
+        let reflected_value = to_value(&reflected_objectives);
+        n_calls += 1;
+
+        let (point, point_objectives, point_value) = if reflected_value < best
+            && n_calls < max_evaluations
+        {
+            let expanded =
+                clip(&centroid + EXPANSION * (&reflected - &centroid));
+            let expanded_objectives = evaluate(expanded.view())?;
+            let expanded_value = to_value(&expanded_objectives);
+            n_calls += 1;
+            if expanded_value < reflected_value {
+                (expanded, expanded_objectives, expanded_value)
+            } else {
+                (reflected, reflected_objectives, reflected_value)
+            }
+        } else if reflected_value < values[n_params - 1]
+            // no budget left to expand
+            || reflected_value < best
+        {
+            (reflected, reflected_objectives, reflected_value)
+        } else if n_calls >= max_evaluations {
+            break;
+        } else {
+            let contracted = if reflected_value < worst {
+                &centroid + CONTRACTION * (&reflected - &centroid)
+            } else {
+                &centroid + CONTRACTION * (&worst_point - &centroid)
+            };
+            let contracted_objectives = evaluate(contracted.view())?;
+            let contracted_value = to_value(&contracted_objectives);
+            n_calls += 1;
+            if contracted_value < reflected_value.min(worst) {
+                (contracted, contracted_objectives, contracted_value)
+            } else if n_calls + n_params > max_evaluations {
+                break;
+            } else {
+                // shrink towards the best point
+                let best_point = simplex.row(0).to_owned();
+                for i in 1..=n_params {
+                    let point =
+                        &best_point + SHRINK * (&simplex.row(i) - &best_point);
+                    objectives[i] = evaluate(point.view())?;
+                    values[i] = to_value(&objectives[i]);
+                    simplex.row_mut(i).assign(&point);
+                }
+                n_calls += n_params;
+                continue;
+            }
+        };
+
+        simplex.row_mut(n_params).assign(&point);
+        objectives[n_params] = point_objectives;
+        values[n_params] = point_value;
+    }
+
+    let best = (0..=n_params)
+        .min_by(|&a, &b| values[a].total_cmp(&values[b]))
+        .unwrap();
+    Ok((
+        simplex.row(best).to_owned(),
+        objectives[best].clone(),
+        n_calls,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "run")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    objective,
+    data,
+    metadata,
+    observations,
+    params,
+    max_evaluations=500,
+))]
+pub fn py_run<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    params: PyReadonlyArray1<'_, f64>,
+    max_evaluations: usize,
+) -> PyResult<(Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (simulate, default_params, bounds) =
+        build_model(climate_model, snow_model)?;
+    let params = params.as_array();
+    if params.len() != default_params.len() {
+        return Err(
+            Error::ParamsMismatch(default_params.len(), params.len()).into()
+        );
+    }
+    let (params, objectives, _) = polish(
+        &simulate,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        objective,
+        params,
+        bounds.column(0),
+        bounds.column(1),
+        max_evaluations,
+        &Scoring::default(),
+    )?;
+    Ok((params.to_pyarray(py), objectives.to_pyarray(py)))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "nelder_mead")?;
+    m.add_function(wrap_pyfunction!(py_run, &m)?)?;
+    Ok(m)
+}
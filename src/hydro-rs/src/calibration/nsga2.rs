@@ -0,0 +1,386 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use numpy::{PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::calibration::utils::{
+    build_model, evaluate_population_criteria, parse_criteria,
+    sample_latin_hypercube, select_by_front, CalibrationParams, Criterion,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+struct Nsga2Params {
+    pub population: Array2<f64>,
+    pub values: Array2<f64>,
+    pub ranks: Vec<usize>,
+    pub crowding_distances: Vec<f64>,
+    pub criteria: Vec<Criterion>,
+    pub n_calls: usize,
+    pub crossover_probability: f64,
+    pub crossover_index: f64,
+    pub mutation_index: f64,
+    pub max_evaluations: usize,
+}
+
+/// Non-dominated Sorting Genetic Algorithm II (Deb et al., 2002).
+///
+/// Each step creates a new generation by binary tournament selection,
+/// simulated binary crossover and polynomial mutation, and keeps the best
+/// half of the parents and children ranked by Pareto front and crowding
+/// distance. Returns the current non-dominated front with the value of each
+/// criterion.
+#[pyclass(module = "hydro_rs.calibration.nsga2", unsendable)]
+pub struct Nsga2 {
+    calibration_params: CalibrationParams,
+    nsga2_params: Nsga2Params,
+}
+
+impl Nsga2 {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        criteria: Vec<Criterion>,
+        population_size: usize,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+
+        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // an even population simplifies the pairing of parents
+        let population_size = population_size.max(4).next_multiple_of(2);
+        let population = sample_latin_hypercube(
+            population_size,
+            lower_bounds.view(),
+            upper_bounds.view(),
+            &mut rng,
+        );
+
+        let calibration_params = CalibrationParams {
+            params,
+            simulate,
+            lower_bounds,
+            upper_bounds,
+            objective: criteria[0].objective,
+            rng,
+            done: false,
+        };
+        let nsga2_params = Nsga2Params {
+            population,
+            values: Array2::zeros((population_size, criteria.len())),
+            ranks: vec![0; population_size],
+            crowding_distances: vec![0.; population_size],
+            criteria,
+            n_calls: 0,
+            crossover_probability: 0.9,
+            crossover_index: 15.,
+            mutation_index: 20.,
+            max_evaluations,
+        };
+
+        Ok(Nsga2 {
+            calibration_params,
+            nsga2_params,
+        })
+    }
+
+    pub fn init<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        let values = evaluate_population_criteria(
+            &self.calibration_params.simulate,
+            data,
+            metadata,
+            observations,
+            &self.nsga2_params.population,
+            &self.nsga2_params.criteria,
+        )?;
+        self.nsga2_params.n_calls += values.nrows();
+        let population = std::mem::take(&mut self.nsga2_params.population);
+        self.select_survivors(population, values);
+        Ok(())
+    }
+
+    pub fn step<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(bool, Array2<f64>, Array2<f64>), Error> {
+        if !self.calibration_params.done {
+            let children = self.generate_children();
+            let children_values = evaluate_population_criteria(
+                &self.calibration_params.simulate,
+                data,
+                metadata,
+                observations,
+                &children,
+                &self.nsga2_params.criteria,
+            )?;
+            self.nsga2_params.n_calls += children.nrows();
+
+            let population = ndarray::concatenate(
+                Axis(0),
+                &[self.nsga2_params.population.view(), children.view()],
+            )
+            .unwrap();
+            let values = ndarray::concatenate(
+                Axis(0),
+                &[self.nsga2_params.values.view(), children_values.view()],
+            )
+            .unwrap();
+            self.select_survivors(population, values);
+
+            self.calibration_params.done =
+                self.nsga2_params.n_calls >= self.nsga2_params.max_evaluations;
+        }
+
+        let (front_params, front_objectives) = self.front();
+        Ok((self.calibration_params.done, front_params, front_objectives))
+    }
+
+    /// Returns the parameters of the first front and the value of each
+    /// criterion.
+    pub fn front(&self) -> (Array2<f64>, Array2<f64>) {
+        let front: Vec<usize> = (0..self.nsga2_params.ranks.len())
+            .filter(|&i| self.nsga2_params.ranks[i] == 0)
+            .collect();
+        let params = self.nsga2_params.population.select(Axis(0), &front);
+        let mut objectives = self.nsga2_params.values.select(Axis(0), &front);
+        for (j, criterion) in self.nsga2_params.criteria.iter().enumerate() {
+            objectives
+                .column_mut(j)
+                .mapv_inplace(|x| criterion.to_objective(x));
+        }
+        (params, objectives)
+    }
+
+    /// Keeps the best points by front, then by crowding distance.
+    fn select_survivors(
+        &mut self,
+        population: Array2<f64>,
+        values: Array2<f64>,
+    ) {
+        let selected = select_by_front(&values, self.nsga2_params.ranks.len());
+        let indices: Vec<usize> = selected.iter().map(|x| x.0).collect();
+        self.nsga2_params.population = population.select(Axis(0), &indices);
+        self.nsga2_params.values = values.select(Axis(0), &indices);
+        self.nsga2_params.ranks = selected.iter().map(|x| x.1).collect();
+        self.nsga2_params.crowding_distances =
+            selected.iter().map(|x| x.2).collect();
+    }
+
+    fn generate_children(&mut self) -> Array2<f64> {
+        let n_population = self.nsga2_params.population.nrows();
+        let n_params = self.nsga2_params.population.ncols();
+        let mut children = Array2::zeros((n_population, n_params));
+        let rng = &mut self.calibration_params.rng;
+        let lower_bounds = self.calibration_params.lower_bounds.view();
+        let upper_bounds = self.calibration_params.upper_bounds.view();
+
+        for k in (0..n_population).step_by(2) {
+            let first = select_by_tournament(
+                &self.nsga2_params.ranks,
+                &self.nsga2_params.crowding_distances,
+                rng,
+            );
+            let second = select_by_tournament(
+                &self.nsga2_params.ranks,
+                &self.nsga2_params.crowding_distances,
+                rng,
+            );
+            let (mut a, mut b) = crossover(
+                self.nsga2_params.population.row(first),
+                self.nsga2_params.population.row(second),
+                lower_bounds,
+                upper_bounds,
+                self.nsga2_params.crossover_probability,
+                self.nsga2_params.crossover_index,
+                rng,
+            );
+            for child in [&mut a, &mut b] {
+                mutate(
+                    child,
+                    lower_bounds,
+                    upper_bounds,
+                    self.nsga2_params.mutation_index,
+                    rng,
+                );
+            }
+            children.row_mut(k).assign(&a);
+            children.row_mut(k + 1).assign(&b);
+        }
+
+        children
+    }
+}
+
+#[pymethods]
+impl Nsga2 {
+    #[new]
+    #[pyo3(signature = (
+        climate_model,
+        snow_model,
+        criteria,
+        max_evaluations,
+        seed,
+        population_size=100,
+    ))]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        criteria: Vec<(String, String)>,
+        max_evaluations: usize,
+        seed: u64,
+        population_size: usize,
+    ) -> PyResult<Self> {
+        let criteria = parse_criteria(&criteria)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        if criteria.len() < 2 {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "At least two criteria are required.",
+            ));
+        }
+        Nsga2::new(
+            climate_model,
+            snow_model,
+            criteria,
+            population_size,
+            max_evaluations,
+            seed,
+        )
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "init")]
+    pub fn py_init(
+        &mut self,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<()> {
+        self.init(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok(())
+    }
+
+    #[pyo3(name = "step")]
+    pub fn py_step<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<(bool, Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)>
+    {
+        let (done, front_params, front_objectives) = self.step(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok((
+            done,
+            front_params.to_pyarray(py),
+            front_objectives.to_pyarray(py),
+        ))
+    }
+}
+
+/// Binary tournament on the rank, then the crowding distance.
+pub fn select_by_tournament(
+    ranks: &[usize],
+    crowding_distances: &[f64],
+    rng: &mut ChaCha8Rng,
+) -> usize {
+    let a = rng.random_range(0..ranks.len());
+    let b = rng.random_range(0..ranks.len());
+    if ranks[a] < ranks[b]
+        || (ranks[a] == ranks[b]
+            && crowding_distances[a] > crowding_distances[b])
+    {
+        a
+    } else {
+        b
+    }
+}
+
+/// Simulated binary crossover (Deb and Agrawal, 1995).
+pub fn crossover(
+    first: ArrayView1<f64>,
+    second: ArrayView1<f64>,
+    lower_bounds: ArrayView1<f64>,
+    upper_bounds: ArrayView1<f64>,
+    probability: f64,
+    eta: f64,
+    rng: &mut ChaCha8Rng,
+) -> (Array1<f64>, Array1<f64>) {
+    let mut a = first.to_owned();
+    let mut b = second.to_owned();
+
+    if rng.random::<f64>() > probability {
+        return (a, b);
+    }
+
+    for j in 0..a.len() {
+        if rng.random::<f64>() > 0.5 {
+            continue;
+        }
+        let u: f64 = rng.random();
+        let beta = if u <= 0.5 {
+            (2. * u).powf(1. / (eta + 1.))
+        } else {
+            (1. / (2. * (1. - u))).powf(1. / (eta + 1.))
+        };
+        let (x, y) = (a[j], b[j]);
+        let (lower, upper) = (lower_bounds[j], upper_bounds[j]);
+        a[j] = (0.5 * ((1. + beta) * x + (1. - beta) * y)).clamp(lower, upper);
+        b[j] = (0.5 * ((1. - beta) * x + (1. + beta) * y)).clamp(lower, upper);
+    }
+
+    (a, b)
+}
+
+/// Polynomial mutation (Deb and Goyal, 1996) of each parameter with a
+/// probability of 1 / n_params.
+pub fn mutate(
+    point: &mut Array1<f64>,
+    lower_bounds: ArrayView1<f64>,
+    upper_bounds: ArrayView1<f64>,
+    eta: f64,
+    rng: &mut ChaCha8Rng,
+) {
+    let probability = 1. / point.len() as f64;
+
+    for j in 0..point.len() {
+        if rng.random::<f64>() > probability {
+            continue;
+        }
+        let (lower, upper) = (lower_bounds[j], upper_bounds[j]);
+        let u: f64 = rng.random();
+        let delta = if u < 0.5 {
+            (2. * u).powf(1. / (eta + 1.)) - 1.
+        } else {
+            1. - (2. * (1. - u)).powf(1. / (eta + 1.))
+        };
+        point[j] = (point[j] + delta * (upper - lower)).clamp(lower, upper);
+    }
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "nsga2")?;
+    m.add_class::<Nsga2>()?;
+    Ok(m)
+}
@@ -0,0 +1,168 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1};
+use ndarray_rand::rand_distr::Uniform;
+use ndarray_rand::RandomExt;
+use numpy::{PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::calibration::lhs::evaluate_population;
+use crate::calibration::utils::{build_model, Objective, Scoring};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Mode {
+    Uniform,
+    Grid,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uniform" => Ok(Self::Uniform),
+            "grid" => Ok(Self::Grid),
+            _ => Err(format!(
+                "Unknown mode '{}'. Valid options: uniform, grid",
+                s
+            )),
+        }
+    }
+}
+
+/// Baseline calibration evaluating `n_samples` parameter sets drawn
+/// uniformly, or the largest regular grid, including the bounds, with at
+/// most `n_samples` points.
+///
+/// Returns the parameter sets and their objectives (see
+/// `evaluate_simulation`), sorted from best to worst.
+pub fn run(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: Objective,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    n_samples: usize,
+    mode: Mode,
+    seed: u64,
+) -> Result<(Array2<f64>, Array2<f64>), Error> {
+    let (simulate, params, bounds) = build_model(climate_model, snow_model)?;
+
+    let lower_bounds = bounds.column(0);
+    let upper_bounds = bounds.column(1);
+
+    let population = match mode {
+        Mode::Uniform => {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let random_values: Array2<f64> = Array2::random_using(
+                (n_samples, params.len()),
+                Uniform::new(0., 1.).unwrap(),
+                &mut rng,
+            );
+            &random_values * &(&upper_bounds - &lower_bounds) + lower_bounds
+        }
+        Mode::Grid => generate_grid(n_samples, lower_bounds, upper_bounds),
+    };
+
+    evaluate_population(
+        &simulate,
+        data,
+        metadata,
+        observations,
+        population,
+        objective,
+        &Scoring::default(),
+    )
+}
+
+#[pyfunction]
+#[pyo3(name = "run")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    objective,
+    data,
+    metadata,
+    observations,
+    n_samples,
+    seed,
+    mode="uniform",
+))]
+pub fn py_run<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    n_samples: usize,
+    seed: u64,
+    mode: &str,
+) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let mode = Mode::from_str(mode)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (params, objectives) = run(
+        climate_model,
+        snow_model,
+        objective,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        n_samples,
+        mode,
+        seed,
+    )?;
+    Ok((params.to_pyarray(py), objectives.to_pyarray(py)))
+}
+
+/// Regular grid with the same number of levels for each parameter, a single
+/// level being the centre of the bounds.
+fn generate_grid(
+    n_samples: usize,
+    lower_bounds: ArrayView1<f64>,
+    upper_bounds: ArrayView1<f64>,
+) -> Array2<f64> {
+    let n_params = lower_bounds.len();
+    let mut n_levels = ((n_samples as f64).powf(1. / n_params as f64).floor()
+        as usize)
+        .max(1);
+    // correct for the rounding errors of the root
+    while (n_levels + 1)
+        .checked_pow(n_params as u32)
+        .is_some_and(|n| n <= n_samples)
+    {
+        n_levels += 1;
+    }
+
+    let levels: Vec<Array1<f64>> = (0..n_params)
+        .map(|j| {
+            if n_levels == 1 {
+                Array1::from_elem(1, (lower_bounds[j] + upper_bounds[j]) / 2.)
+            } else {
+                Array1::linspace(lower_bounds[j], upper_bounds[j], n_levels)
+            }
+        })
+        .collect();
+
+    let n_points = n_levels.pow(n_params as u32);
+    Array2::from_shape_fn((n_points, n_params), |(i, j)| {
+        let level = (i / n_levels.pow(j as u32)) % n_levels;
+        levels[j][level]
+    })
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "random_search")?;
+    m.add_function(wrap_pyfunction!(py_run, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,225 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::calibration::dds::Dds;
+use crate::calibration::utils::{
+    build_model, evaluate_simulation, stack_rows, Objective, Sense, N_METRICS,
+    SENSES,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Aggregation {
+    Mean,
+    Min,
+}
+
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mean" => Ok(Self::Mean),
+            "min" => Ok(Self::Min),
+            _ => Err(format!(
+                "Unknown aggregation '{}'. Valid options: mean, min",
+                s
+            )),
+        }
+    }
+}
+
+impl Aggregation {
+    /// Aggregates the objectives of each catchment (one row per catchment),
+    /// `Min` keeping the worst catchment for each objective, i.e. the
+    /// largest errors and biases, the smallest efficiencies and the ratios
+    /// furthest from one.
+    pub fn aggregate(&self, objectives: &Array2<f64>) -> Array1<f64> {
+        Array1::from_iter(objectives.columns().into_iter().zip(SENSES).map(
+            |(column, sense)| match self {
+                Aggregation::Mean => column.sum() / column.len() as f64,
+                Aggregation::Min => {
+                    if column.iter().any(|value| value.is_nan()) {
+                        return f64::NAN;
+                    }
+                    let worst = |a: f64, b: f64| match sense {
+                        Sense::Minimized => a.max(b),
+                        Sense::Maximized => a.min(b),
+                        Sense::Zero if b.abs() > a.abs() => b,
+                        Sense::Zero => a,
+                        Sense::One if (b - 1.).abs() > (a - 1.).abs() => b,
+                        Sense::One => a,
+                    };
+                    column.iter().copied().reduce(worst).unwrap_or(f64::NAN)
+                }
+            },
+        ))
+    }
+}
+
+pub struct Catchment<'a> {
+    pub data: Data<'a>,
+    pub metadata: &'a Metadata<'a>,
+    pub observations: ArrayView1<'a, f64>,
+}
+
+/// Objectives (see `evaluate_simulation`) of the parameters `params` on each
+/// catchment, evaluated in parallel.
+fn evaluate_catchments(
+    simulate: &SimulateFn,
+    params: ArrayView1<f64>,
+    catchments: &[Catchment],
+) -> Result<Array2<f64>, Error> {
+    let objectives = catchments
+        .par_iter()
+        .map(|catchment| {
+            let simulation =
+                simulate(params, catchment.data, catchment.metadata)?;
+            evaluate_simulation(catchment.observations, simulation.view())
+        })
+        .collect::<Result<Vec<Array1<f64>>, Error>>()?;
+    Ok(stack_rows(&objectives, N_METRICS))
+}
+
+/// Regional calibration: searches with `Dds` a single parameter set shared
+/// by all `catchments`, scored by the mean, or the worst, of its objectives
+/// over the catchments.
+///
+/// Returns the shared parameters and their objectives (see
+/// `evaluate_simulation`) on each catchment.
+pub fn run(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: Objective,
+    catchments: &[Catchment],
+    aggregation: Aggregation,
+    max_evaluations: usize,
+    seed: u64,
+    perturbation: f64,
+) -> Result<(Array1<f64>, Array2<f64>), Error> {
+    if catchments.is_empty() {
+        return Err(Error::Validation(
+            "regional calibration needs at least one catchment".to_string(),
+        ));
+    }
+
+    let mut dds = Dds::new(
+        climate_model,
+        snow_model,
+        objective,
+        perturbation,
+        max_evaluations,
+        seed,
+    )?;
+    let evaluate = |simulate: &SimulateFn, params: ArrayView1<f64>| {
+        let objectives = evaluate_catchments(simulate, params, catchments)?;
+        Ok(aggregation.aggregate(&objectives))
+    };
+    dds.init_with(evaluate)?;
+    let params = loop {
+        let (done, params, _) = dds.step_with(evaluate)?;
+        if done {
+            break params;
+        }
+    };
+
+    let (simulate, _, _) = build_model(climate_model, snow_model)?;
+    let objectives =
+        evaluate_catchments(&simulate, params.view(), catchments)?;
+    Ok((params, objectives))
+}
+
+#[pyfunction]
+#[pyo3(name = "run")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    objective,
+    data,
+    metadata,
+    observations,
+    max_evaluations,
+    seed,
+    aggregation="mean",
+    perturbation=0.2,
+))]
+pub fn py_run<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: Vec<PyData<'_>>,
+    metadata: Vec<PyMetadata<'_>>,
+    observations: Vec<PyReadonlyArray1<'_, f64>>,
+    max_evaluations: usize,
+    seed: u64,
+    aggregation: &str,
+    perturbation: f64,
+) -> PyResult<(Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let aggregation = Aggregation::from_str(aggregation)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    if data.len() != metadata.len() {
+        return Err(Error::InputLengthMismatch(
+            "data",
+            "metadata",
+            data.len(),
+            metadata.len(),
+        )
+        .into());
+    }
+    if data.len() != observations.len() {
+        return Err(Error::InputLengthMismatch(
+            "data",
+            "observations",
+            data.len(),
+            observations.len(),
+        )
+        .into());
+    }
+
+    let data = data
+        .iter()
+        .map(|data| data.as_data())
+        .collect::<Result<Vec<Data>, Error>>()?;
+    let metadata: Vec<Metadata> = metadata
+        .iter()
+        .map(|metadata| metadata.as_metadata())
+        .collect();
+    let catchments: Vec<Catchment> = data
+        .iter()
+        .zip(&metadata)
+        .zip(&observations)
+        .map(|((data, metadata), observations)| Catchment {
+            data: *data,
+            metadata,
+            observations: observations.as_array(),
+        })
+        .collect();
+
+    let (params, objectives) = run(
+        climate_model,
+        snow_model,
+        objective,
+        &catchments,
+        aggregation,
+        max_evaluations,
+        seed,
+        perturbation,
+    )?;
+    Ok((params.to_pyarray(py), objectives.to_pyarray(py)))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "regional")?;
+    m.add_function(wrap_pyfunction!(py_run, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,400 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::calibration::dds::Dds;
+use crate::calibration::regional::{Aggregation, Catchment};
+use crate::calibration::utils::{
+    build_model, evaluate_simulation, stack_rows, Objective, N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
+
+// ln(area), median elevation, latitude and glacier fraction
+const N_ATTRIBUTES: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Transfer {
+    Linear,
+    LogLinear,
+}
+
+impl FromStr for Transfer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(Self::Linear),
+            "log_linear" => Ok(Self::LogLinear),
+            _ => Err(format!(
+                "Unknown transfer '{}'. Valid options: linear, log_linear",
+                s
+            )),
+        }
+    }
+}
+
+/// Catchment attributes the parameters are regressed on: the logarithm of
+/// the area, the median elevation, the latitude and the glacier fraction.
+pub fn attributes(metadata: &Metadata) -> Array1<f64> {
+    Array1::from_vec(vec![
+        metadata.area.ln(),
+        metadata.median_elevation,
+        metadata.latitude,
+        metadata.glacier_fraction,
+    ])
+}
+
+/// Parameter regionalization: calibrates, on a set of gauged catchments,
+/// transfer functions from the standardized catchment `attributes` to each
+/// model parameter, which then give the parameters of ungauged catchments.
+///
+/// With the `Linear` transfer a parameter is an affine function of the
+/// attributes, while with `LogLinear` its logarithm is, parameters whose
+/// lower bound isn't positive staying linear. The coefficients are searched
+/// with `Dds`, the parameters of each catchment being scored by the mean, or
+/// the worst, of their objectives over the catchments.
+#[pyclass(module = "hydro_rs.calibration.regionalization", unsendable)]
+pub struct Regionalization {
+    climate_model: String,
+    snow_model: Option<String>,
+    objective: Objective,
+    transfer: Transfer,
+    aggregation: Aggregation,
+    max_evaluations: usize,
+    seed: u64,
+    perturbation: f64,
+    lower_bounds: Array1<f64>,
+    upper_bounds: Array1<f64>,
+    // one row per parameter, the intercept coming first
+    coefficients: Option<Array2<f64>>,
+    attribute_means: Array1<f64>,
+    attribute_stds: Array1<f64>,
+}
+
+impl Regionalization {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: Objective,
+        transfer: Transfer,
+        aggregation: Aggregation,
+        max_evaluations: usize,
+        seed: u64,
+        perturbation: f64,
+    ) -> Result<Self, Error> {
+        let (_, _, bounds) = build_model(climate_model, snow_model)?;
+        Ok(Regionalization {
+            climate_model: climate_model.to_string(),
+            snow_model: snow_model.map(|s| s.to_string()),
+            objective,
+            transfer,
+            aggregation,
+            max_evaluations,
+            seed,
+            perturbation,
+            lower_bounds: bounds.column(0).to_owned(),
+            upper_bounds: bounds.column(1).to_owned(),
+            coefficients: None,
+            attribute_means: Array1::zeros(N_ATTRIBUTES),
+            attribute_stds: Array1::ones(N_ATTRIBUTES),
+        })
+    }
+
+    /// Calibrates the transfer functions on the gauged `catchments`.
+    ///
+    /// Returns the coefficients, one row per parameter with the intercept
+    /// first, and the objectives (see `evaluate_simulation`) of the
+    /// regionalized parameters on each catchment.
+    pub fn calibrate(
+        &mut self,
+        catchments: &[Catchment],
+    ) -> Result<(Array2<f64>, Array2<f64>), Error> {
+        if catchments.is_empty() {
+            return Err(Error::Validation(
+                "regionalization needs at least one catchment".to_string(),
+            ));
+        }
+
+        let attributes = stack_rows(
+            &catchments
+                .iter()
+                .map(|catchment| attributes(catchment.metadata))
+                .collect::<Vec<Array1<f64>>>(),
+            N_ATTRIBUTES,
+        );
+        self.attribute_means = attributes.mean_axis(Axis(0)).unwrap();
+        self.attribute_stds = attributes.std_axis(Axis(0), 0.);
+        let standardized = Array2::from_shape_fn(
+            (catchments.len(), N_ATTRIBUTES + 1),
+            |(i, k)| {
+                if k == 0 {
+                    1.
+                } else {
+                    self.standardize(attributes[[i, k - 1]], k - 1)
+                }
+            },
+        );
+
+        let mut dds = Dds::new(
+            &self.climate_model,
+            self.snow_model.as_deref(),
+            self.objective,
+            self.perturbation,
+            self.max_evaluations,
+            self.seed,
+        )?;
+        let (lower_bounds, upper_bounds) = self.coefficient_bounds();
+        dds.set_bounds(lower_bounds, upper_bounds);
+
+        let n_params = self.lower_bounds.len();
+        let evaluate = |simulate: &SimulateFn, flat: ArrayView1<f64>| {
+            let coefficients = flat
+                .to_shape((n_params, N_ATTRIBUTES + 1))
+                .unwrap()
+                .to_owned();
+            let objectives = self.evaluate_catchments(
+                simulate,
+                coefficients.view(),
+                standardized.view(),
+                catchments,
+            )?;
+            Ok(self.aggregation.aggregate(&objectives))
+        };
+        dds.init_with(evaluate)?;
+        let flat = loop {
+            let (done, flat, _) = dds.step_with(evaluate)?;
+            if done {
+                break flat;
+            }
+        };
+
+        let coefficients = flat
+            .to_shape((n_params, N_ATTRIBUTES + 1))
+            .unwrap()
+            .to_owned();
+        let (simulate, _, _) =
+            build_model(&self.climate_model, self.snow_model.as_deref())?;
+        let objectives = self.evaluate_catchments(
+            &simulate,
+            coefficients.view(),
+            standardized.view(),
+            catchments,
+        )?;
+        self.coefficients = Some(coefficients.clone());
+        Ok((coefficients, objectives))
+    }
+
+    /// Parameters of a (possibly ungauged) catchment given by the calibrated
+    /// transfer functions.
+    pub fn predict(&self, metadata: &Metadata) -> Result<Array1<f64>, Error> {
+        let coefficients = self.coefficients.as_ref().ok_or_else(|| {
+            Error::Validation(
+                "the transfer functions must be calibrated first".to_string(),
+            )
+        })?;
+        let attributes = attributes(metadata);
+        let standardized = Array1::from_shape_fn(N_ATTRIBUTES + 1, |k| {
+            if k == 0 {
+                1.
+            } else {
+                self.standardize(attributes[k - 1], k - 1)
+            }
+        });
+        Ok(self.apply(coefficients.view(), standardized.view()))
+    }
+
+    /// Standardizes attribute `k`, constant attributes being set to zero.
+    fn standardize(&self, value: f64, k: usize) -> f64 {
+        if self.attribute_stds[k] > 0. {
+            (value - self.attribute_means[k]) / self.attribute_stds[k]
+        } else {
+            0.
+        }
+    }
+
+    fn is_log(&self, j: usize) -> bool {
+        matches!(self.transfer, Transfer::LogLinear)
+            && self.lower_bounds[j] > 0.
+    }
+
+    /// Parameters given by the `coefficients` for the standardized
+    /// attributes (preceded by 1 for the intercept), clipped to the bounds.
+    fn apply(
+        &self,
+        coefficients: ArrayView2<f64>,
+        standardized: ArrayView1<f64>,
+    ) -> Array1<f64> {
+        Array1::from_shape_fn(self.lower_bounds.len(), |j| {
+            let value = coefficients.row(j).dot(&standardized);
+            let value = if self.is_log(j) { value.exp() } else { value };
+            value.clamp(self.lower_bounds[j], self.upper_bounds[j])
+        })
+    }
+
+    /// Bounds of the flattened coefficients: the intercepts span the
+    /// parameter ranges (in log space for log-linear parameters) and the
+    /// slopes half of them in each direction.
+    fn coefficient_bounds(&self) -> (Array1<f64>, Array1<f64>) {
+        let n_params = self.lower_bounds.len();
+        let mut lower_bounds = Array2::zeros((n_params, N_ATTRIBUTES + 1));
+        let mut upper_bounds = Array2::zeros((n_params, N_ATTRIBUTES + 1));
+        for j in 0..n_params {
+            let (lower, upper) = if self.is_log(j) {
+                (self.lower_bounds[j].ln(), self.upper_bounds[j].ln())
+            } else {
+                (self.lower_bounds[j], self.upper_bounds[j])
+            };
+            let slope = (upper - lower) / 2.;
+            lower_bounds.row_mut(j).fill(-slope);
+            upper_bounds.row_mut(j).fill(slope);
+            lower_bounds[[j, 0]] = lower;
+            upper_bounds[[j, 0]] = upper;
+        }
+        (
+            lower_bounds
+                .into_shape_with_order(n_params * (N_ATTRIBUTES + 1))
+                .unwrap(),
+            upper_bounds
+                .into_shape_with_order(n_params * (N_ATTRIBUTES + 1))
+                .unwrap(),
+        )
+    }
+
+    /// Objectives (see `evaluate_simulation`) on each catchment of the
+    /// parameters given by the `coefficients`, evaluated in parallel.
+    fn evaluate_catchments(
+        &self,
+        simulate: &SimulateFn,
+        coefficients: ArrayView2<f64>,
+        standardized: ArrayView2<f64>,
+        catchments: &[Catchment],
+    ) -> Result<Array2<f64>, Error> {
+        let objectives = catchments
+            .par_iter()
+            .enumerate()
+            .map(|(i, catchment)| {
+                let params = self.apply(coefficients, standardized.row(i));
+                let simulation = simulate(
+                    params.view(),
+                    catchment.data,
+                    catchment.metadata,
+                )?;
+                evaluate_simulation(catchment.observations, simulation.view())
+            })
+            .collect::<Result<Vec<Array1<f64>>, Error>>()?;
+        Ok(stack_rows(&objectives, N_METRICS))
+    }
+}
+
+#[pymethods]
+impl Regionalization {
+    #[new]
+    #[pyo3(signature = (
+        climate_model,
+        snow_model,
+        objective,
+        max_evaluations,
+        seed,
+        transfer="linear",
+        aggregation="mean",
+        perturbation=0.2,
+    ))]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: &str,
+        max_evaluations: usize,
+        seed: u64,
+        transfer: &str,
+        aggregation: &str,
+        perturbation: f64,
+    ) -> PyResult<Self> {
+        let objective = Objective::from_str(objective)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let transfer = Transfer::from_str(transfer)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let aggregation = Aggregation::from_str(aggregation)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(Regionalization::new(
+            climate_model,
+            snow_model,
+            objective,
+            transfer,
+            aggregation,
+            max_evaluations,
+            seed,
+            perturbation,
+        )?)
+    }
+
+    #[pyo3(name = "calibrate")]
+    pub fn py_calibrate<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: Vec<PyData<'_>>,
+        metadata: Vec<PyMetadata<'_>>,
+        observations: Vec<PyReadonlyArray1<'_, f64>>,
+    ) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)> {
+        if data.len() != metadata.len() {
+            return Err(Error::InputLengthMismatch(
+                "data",
+                "metadata",
+                data.len(),
+                metadata.len(),
+            )
+            .into());
+        }
+        if data.len() != observations.len() {
+            return Err(Error::InputLengthMismatch(
+                "data",
+                "observations",
+                data.len(),
+                observations.len(),
+            )
+            .into());
+        }
+
+        let data = data
+            .iter()
+            .map(|data| data.as_data())
+            .collect::<Result<Vec<Data>, Error>>()?;
+        let metadata: Vec<Metadata> = metadata
+            .iter()
+            .map(|metadata| metadata.as_metadata())
+            .collect();
+        let catchments: Vec<Catchment> = data
+            .iter()
+            .zip(&metadata)
+            .zip(&observations)
+            .map(|((data, metadata), observations)| Catchment {
+                data: *data,
+                metadata,
+                observations: observations.as_array(),
+            })
+            .collect();
+
+        let (coefficients, objectives) = self.calibrate(&catchments)?;
+        Ok((coefficients.to_pyarray(py), objectives.to_pyarray(py)))
+    }
+
+    #[pyo3(name = "predict")]
+    pub fn py_predict<'py>(
+        &self,
+        py: Python<'py>,
+        metadata: PyMetadata<'_>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        Ok(self.predict(&metadata.as_metadata())?.to_pyarray(py))
+    }
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "regionalization")?;
+    m.add_class::<Regionalization>()?;
+    Ok(m)
+}
@@ -1,31 +1,45 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::type_complexity)]
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard};
 
 use ndarray::{s, Array1, Array2, ArrayView1, ArrayView2, Axis};
 use ndarray_rand::rand_distr::Uniform;
 use ndarray_rand::RandomExt;
-use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
+use numpy::{
+    PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2, ToPyArray,
+};
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use rayon::prelude::*;
 
-use crate::calibration::utils::{CalibrationParams, Objective};
-use crate::climate;
-use crate::metrics::{calculate_kge, calculate_nse, calculate_rmse};
-use crate::model::{
-    compose_init, compose_simulate, Data, Error, Metadata, PyData, PyMetadata,
-    SimulateFn,
+use crate::calibration::checkpoint::Checkpoint;
+use crate::calibration::lhs::evaluate_population;
+use crate::calibration::nelder_mead::polish;
+use crate::calibration::utils::{
+    build_model, call_objective, evaluate_params, sample_latin_hypercube,
+    sort_population, stack_rows, CalibrationParams, CustomObjective,
+    EvaluationCache, Events, FlowWeighting, Gauge, Objective, ParamTransform,
+    Prior, Scoring, SeasonalWeights, SnowObjective, SnowVariable,
+    TemporalAggregation, Trace, Transform, Transformation, VolumePenalty,
+    N_METRICS,
 };
-use crate::snow;
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
 
 struct SceParams {
+    pub climate_model: String,
+    pub snow_model: Option<String>,
     pub population: Array2<f64>,
     pub objectives: Array2<f64>,
     pub criteria: Array1<f64>,
     pub n_calls: usize,
+    pub n_samples: usize,
+    pub initial_population: Array2<f64>,
     pub n_complexes: usize,
     pub n_per_complex: usize,
     pub n_simplex: usize,
@@ -34,6 +48,119 @@ struct SceParams {
     pub p_convergence_threshold: f64,
     pub geometric_range_threshold: f64,
     pub max_evaluations: usize,
+    pub polish_evaluations: usize,
+    pub restart_fraction: f64,
+    pub scoring: Scoring,
+    pub validation: Option<Validation>,
+}
+
+/// Objective on held-out observations, either a series of its own spanning
+/// the forcing or the validation range of the calibration observations,
+/// stopping the search once it hasn't improved for `patience` iterations.
+pub struct Validation {
+    pub observations: Option<Array1<f64>>,
+    pub patience: usize,
+    pub best: f64,
+    pub n_stale: usize,
+}
+
+impl Validation {
+    pub fn new(observations: Option<Array1<f64>>, patience: usize) -> Self {
+        Validation {
+            observations,
+            patience,
+            best: f64::NAN,
+            n_stale: 0,
+        }
+    }
+
+    /// Checks the validation series spans the `n_timesteps` of the forcing.
+    fn check_length(&self, n_timesteps: usize) -> Result<(), Error> {
+        match &self.observations {
+            Some(observations) if observations.len() != n_timesteps => {
+                Err(Error::InputLengthMismatch(
+                    "validation_observations",
+                    "data",
+                    observations.len(),
+                    n_timesteps,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn evaluate(
+        &self,
+        objective: Objective,
+        data: Data,
+        observations: ArrayView1<f64>,
+        simulations: ArrayView1<f64>,
+        scoring: &Scoring,
+    ) -> Result<f64, Error> {
+        self.check_length(simulations.len())?;
+        let (observations, range) = match (
+            &self.observations,
+            &scoring.validation_range,
+        ) {
+            (Some(observations), _) => {
+                let warmup = scoring.warmup.min(observations.len());
+                (observations.view(), warmup..observations.len())
+            }
+            (None, Some(range)) if range.end > observations.len() => {
+                return Err(Error::Validation(format!(
+                    "range {}..{} is longer than the {} observations",
+                    range.start,
+                    range.end,
+                    observations.len()
+                )))
+            }
+            (None, Some(range)) => {
+                (observations, range.start.max(scoring.warmup)..range.end)
+            }
+            (None, None) => {
+                return Err(Error::Validation(
+                    "no validation observations or range".to_string(),
+                ))
+            }
+        };
+        let observations = observations.slice_move(s![range.clone()]);
+        let simulations = simulations.slice_move(s![range.clone()]);
+        let record_weights = scoring.record_weights(data)?;
+        let record_weights =
+            record_weights.as_ref().map(|w| w.slice(s![range]));
+        let (objective_idx, _) = objective.index();
+        match &scoring.custom_objective {
+            Some(custom_objective) if objective_idx == N_METRICS => {
+                call_objective(
+                    &custom_objective.function,
+                    observations,
+                    simulations,
+                )
+            }
+            _ => Ok(scoring.evaluate_flow(
+                observations,
+                simulations,
+                record_weights,
+            )?[objective_idx]),
+        }
+    }
+
+    /// Keeps the best value, returning whether the patience ran out.
+    fn update(&mut self, value: f64, is_minimization: bool) -> bool {
+        let is_better = self.best.is_nan()
+            || if is_minimization {
+                value < self.best
+            } else {
+                value > self.best
+            };
+        if is_better {
+            self.best = value;
+            self.n_stale = 0;
+        } else {
+            self.n_stale += 1;
+        }
+        self.n_stale >= self.patience
+    }
 }
 
 #[pyclass(module = "hydro_rs.calibration.sce", unsendable)]
@@ -54,55 +181,141 @@ impl Sce {
         geometric_range_threshold: f64,
         max_evaluations: usize,
         seed: u64,
+        n_samples: Option<usize>,
+        polish_evaluations: usize,
+        restart_fraction: f64,
+        transforms: Vec<Transform>,
+        fixed_params: Vec<(usize, f64)>,
+        initial_population: Option<Array2<f64>>,
+        validation: Option<Validation>,
+        mut scoring: Scoring,
     ) -> Result<Self, Error> {
-        let (simulate, params, bounds): (SimulateFn, _, _) =
-            if let Some(snow_model) = snow_model {
-                let (snow_init, snow_simulate) = snow::get_model(snow_model)?;
-                let (climate_init, climate_simulate) =
-                    climate::get_model(climate_model)?;
-
-                let init = compose_init(snow_init, climate_init);
-                let (defaults, bounds, n_snow_params) = init();
-                let simulate = compose_simulate(
-                    snow_simulate,
-                    climate_simulate,
-                    n_snow_params,
-                );
-                (simulate, defaults, bounds)
-            } else {
-                let (init, simulate) = climate::get_model(climate_model)?;
-                let (defaults, bounds) = init();
-                (Box::new(simulate), defaults, bounds)
+        if !(0. ..1.).contains(&restart_fraction) {
+            return Err(Error::RestartFraction(restart_fraction));
+        }
+        if let Some(validation) = &validation {
+            if validation.observations.is_none()
+                && scoring.validation_range.is_none()
+            {
+                return Err(Error::Validation(
+                    "no validation observations or range".to_string(),
+                ));
+            }
+        }
+
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+        scoring.objective = Some(objective);
+
+        let mut lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let mut upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+        let n_model_params = params.len();
+
+        if let Some(prior) = &mut scoring.prior {
+            if prior.params.len() != n_model_params {
+                return Err(Error::ParamsMismatch(
+                    n_model_params,
+                    prior.params.len(),
+                ));
+            }
+            prior.ranges = &upper_bounds - &lower_bounds;
+        }
+
+        // the search happens in the transformed space of the free
+        // parameters, linear by default
+        if !transforms.is_empty() && transforms.len() != n_model_params {
+            return Err(Error::ParamsMismatch(
+                n_model_params,
+                transforms.len(),
+            ));
+        }
+        let mut fixed = vec![None; n_model_params];
+        for &(j, value) in &fixed_params {
+            if j >= n_model_params {
+                return Err(Error::FixedParams(format!(
+                    "index {} is out of range for {} params",
+                    j, n_model_params
+                )));
+            }
+            fixed[j] = Some(value);
+        }
+        if fixed.iter().all(Option::is_some) {
+            return Err(Error::FixedParams(
+                "at least one parameter must be calibrated".to_string(),
+            ));
+        }
+        if transforms.iter().any(|&t| t != Transform::Linear)
+            || !fixed_params.is_empty()
+        {
+            let transform = ParamTransform {
+                transforms: if transforms.is_empty() {
+                    vec![Transform::Linear; n_model_params]
+                } else {
+                    transforms
+                },
+                fixed,
+                lower_bounds: lower_bounds.clone(),
+                upper_bounds: upper_bounds.clone(),
             };
+            lower_bounds = transform.forward(lower_bounds.view());
+            upper_bounds = transform.forward(upper_bounds.view());
+            scoring.transform = Some(transform);
+        }
 
-        let n_params = params.len();
+        let n_params = lower_bounds.len();
         let n_per_complex = 2 * n_params + 1;
         let n_simplex = n_params + 1;
         let population_size = n_complexes * n_per_complex;
         let n_evolution_steps = 2 * n_params + 1;
 
-        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
-        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
-
-        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // the initial population is the best of the latin hypercube sample
+        // and of the points given to start from
+        let n_samples = n_samples.unwrap_or(0).max(population_size);
+        let initial_population = match initial_population {
+            Some(initial_population) => {
+                if initial_population.ncols() != n_model_params {
+                    return Err(Error::ParamsMismatch(
+                        n_model_params,
+                        initial_population.ncols(),
+                    ));
+                }
+                let rows: Vec<Array1<f64>> = initial_population
+                    .rows()
+                    .into_iter()
+                    .map(|row| {
+                        let row = match &scoring.transform {
+                            Some(transform) => transform.forward(row),
+                            None => row.to_owned(),
+                        };
+                        Array1::from_iter((0..n_params).map(|j| {
+                            row[j].clamp(lower_bounds[j], upper_bounds[j])
+                        }))
+                    })
+                    .collect();
+                stack_rows(&rows, n_params)
+            }
+            None => Array2::zeros((0, n_params)),
+        };
 
-        let population = generate_initial_population(
-            population_size,
-            &lower_bounds,
-            &upper_bounds,
-            &mut rng,
-        );
-        let objectives: Array2<f64> =
-            Array2::from_shape_fn((population_size, 3), |(_, j)| {
+        let population =
+            Array2::from_shape_fn((population_size, n_params), |(_, j)| {
+                (lower_bounds[j] + upper_bounds[j]) / 2.
+            });
+        let objectives: Array2<f64> = Array2::from_shape_fn(
+            (population_size, scoring.n_objectives()),
+            |(_, j)| {
                 if j == 0 {
                     f64::INFINITY
                 } else {
                     f64::NEG_INFINITY
                 }
-            });
+            },
+        );
 
         let criteria: Array1<f64> = Array1::from_vec(vec![]);
-        let params = population.row(0).to_owned();
+        let params = scoring.to_model(population.row(0));
 
         let calibration_params = CalibrationParams {
             params,
@@ -114,10 +327,14 @@ impl Sce {
             done: false,
         };
         let sce_params = SceParams {
+            climate_model: climate_model.to_string(),
+            snow_model: snow_model.map(str::to_string),
             population,
             objectives,
             criteria,
             n_calls: 0,
+            n_samples,
+            initial_population,
             n_complexes,
             n_per_complex,
             n_simplex,
@@ -126,6 +343,10 @@ impl Sce {
             p_convergence_threshold,
             geometric_range_threshold,
             max_evaluations,
+            polish_evaluations,
+            restart_fraction,
+            scoring,
+            validation,
         };
 
         Ok(Sce {
@@ -140,31 +361,41 @@ impl Sce {
         metadata: &Metadata<'a>,
         observations: ArrayView1<f64>,
     ) -> Result<(), Error> {
-        let objective_idx = match self.calibration_params.objective {
-            Objective::Rmse => 0,
-            Objective::Nse => 1,
-            Objective::Kge => 2,
-        };
+        if let Some(validation) = &self.sce_params.validation {
+            validation.check_length(data.precipitation.len())?;
+        }
+        let (objective_idx, _) = self.calibration_params.objective.index();
+        self.sce_params.scoring.set_iteration(0);
 
+        let population_size = self.sce_params.population.nrows();
         let population = generate_initial_population(
-            self.sce_params.population.nrows(),
+            self.sce_params.n_samples,
+            self.sce_params.initial_population.view(),
             &self.calibration_params.lower_bounds,
             &self.calibration_params.upper_bounds,
             &mut self.calibration_params.rng,
         );
 
-        let (population, objectives) = evaluate_initial_population(
+        let (population, objectives) = evaluate_population(
             &self.calibration_params.simulate,
             data,
             metadata,
             observations,
             population,
             self.calibration_params.objective,
+            &self.sce_params.scoring,
         )?;
+        // the whole sample counts against the budget, not only the kept part
+        self.sce_params.n_calls = population.nrows();
+        let population =
+            population.slice(s![..population_size, ..]).to_owned();
+        let objectives =
+            objectives.slice(s![..population_size, ..]).to_owned();
 
         self.sce_params.criteria =
             Array1::from_vec(vec![objectives[[0, objective_idx]]]);
-        self.calibration_params.params = population.row(0).to_owned();
+        self.calibration_params.params =
+            self.sce_params.scoring.to_model(population.row(0));
         self.sce_params.population = population;
         self.sce_params.objectives = objectives;
 
@@ -193,11 +424,10 @@ impl Sce {
         }
 
         let (objective_idx, is_minimization) =
-            match self.calibration_params.objective {
-                Objective::Rmse => (0, true),
-                Objective::Nse => (1, false),
-                Objective::Kge => (2, false),
-            };
+            self.calibration_params.objective.index();
+        self.sce_params
+            .scoring
+            .set_iteration(self.sce_params.criteria.len());
 
         let (mut complexes, mut complex_objectives) = partition_into_complexes(
             std::mem::take(&mut self.sce_params.population),
@@ -221,10 +451,12 @@ impl Sce {
             self.sce_params.n_per_complex,
             self.sce_params.n_simplex,
             self.sce_params.n_evolution_steps,
+            self.sce_params.max_evaluations,
+            &self.sce_params.scoring,
             &mut self.calibration_params.rng,
         )?;
 
-        let (population, objectives) = merge_complexes(
+        let (mut population, mut objectives) = merge_complexes(
             complexes,
             complex_objectives,
             objective_idx,
@@ -267,10 +499,96 @@ impl Sce {
             f64::INFINITY
         };
 
-        self.calibration_params.done = n_calls > self.sce_params.max_evaluations
-            || gnrng < self.sce_params.geometric_range_threshold
-            || criteria_change < self.sce_params.p_convergence_threshold;
-        self.calibration_params.params = population.row(0).to_owned();
+        // a collapsed population that still improves is diversified with
+        // random individuals rather than stopped
+        let mut n_calls = n_calls;
+        let mut is_collapsed =
+            gnrng < self.sce_params.geometric_range_threshold;
+        let is_stalled =
+            criteria_change < self.sce_params.p_convergence_threshold;
+        if is_collapsed
+            && !is_stalled
+            && self.sce_params.restart_fraction > 0.
+            && n_calls < self.sce_params.max_evaluations
+        {
+            let n_random = ((self.sce_params.restart_fraction
+                * population.nrows() as f64)
+                .ceil() as usize)
+                .min(population.nrows() - 1)
+                .min(self.sce_params.max_evaluations - n_calls);
+            let random_values: Array2<f64> = Array2::random_using(
+                (n_random, population.ncols()),
+                Uniform::new(0., 1.).unwrap(),
+                &mut self.calibration_params.rng,
+            );
+            let lower_bounds = &self.calibration_params.lower_bounds;
+            let upper_bounds = &self.calibration_params.upper_bounds;
+            let (random_population, random_objectives) = evaluate_population(
+                &self.calibration_params.simulate,
+                data,
+                metadata,
+                observations,
+                &random_values * &(upper_bounds - lower_bounds) + lower_bounds,
+                self.calibration_params.objective,
+                &self.sce_params.scoring,
+            )?;
+            let n_kept = population.nrows() - n_random;
+            population
+                .slice_mut(s![n_kept.., ..])
+                .assign(&random_population);
+            objectives
+                .slice_mut(s![n_kept.., ..])
+                .assign(&random_objectives);
+            sort_population(
+                &mut population,
+                &mut objectives,
+                objective_idx,
+                is_minimization,
+            );
+            n_calls += n_random;
+            is_collapsed = false;
+        }
+
+        self.calibration_params.done = n_calls
+            >= self.sce_params.max_evaluations
+            || is_collapsed
+            || is_stalled;
+
+        // refine the best point locally once the search has converged, within
+        // what is left of the evaluation budget
+        let polish_evaluations = self
+            .sce_params
+            .polish_evaluations
+            .min(self.sce_params.max_evaluations.saturating_sub(n_calls));
+        if self.calibration_params.done && polish_evaluations > 0 {
+            let (params, polished_objectives, n_polish_calls) = polish(
+                &self.calibration_params.simulate,
+                data,
+                metadata,
+                observations,
+                self.calibration_params.objective,
+                population.row(0),
+                self.calibration_params.lower_bounds.view(),
+                self.calibration_params.upper_bounds.view(),
+                polish_evaluations,
+                &self.sce_params.scoring,
+            )?;
+            n_calls += n_polish_calls;
+            let new = polished_objectives[objective_idx];
+            let best = objectives[[0, objective_idx]];
+            let is_better = if is_minimization {
+                new < best
+            } else {
+                new > best
+            };
+            if is_better {
+                population.row_mut(0).assign(&params);
+                objectives.row_mut(0).assign(&polished_objectives);
+            }
+        }
+
+        self.calibration_params.params =
+            self.sce_params.scoring.to_model(population.row(0));
         self.sce_params.n_calls = n_calls;
 
         // Compute simulation once and return directly (no clone)
@@ -281,6 +599,20 @@ impl Sce {
         )?;
         let best_objectives = objectives.row(0).to_owned();
 
+        // stop early once the validation objective stops improving
+        if let Some(validation) = &mut self.sce_params.validation {
+            let value = validation.evaluate(
+                self.calibration_params.objective,
+                data,
+                observations,
+                best_simulation.view(),
+                &self.sce_params.scoring,
+            )?;
+            if validation.update(value, is_minimization) {
+                self.calibration_params.done = true;
+            }
+        }
+
         self.sce_params.population = population;
         self.sce_params.objectives = objectives;
 
@@ -291,24 +623,766 @@ impl Sce {
             best_objectives,
         ))
     }
+
+    /// Restarts the search with a fresh population within bounds shrunk by
+    /// `shrink_factor` around the best point, which is kept. The bounds are
+    /// shifted rather than cut where they would leave the current ones, and
+    /// the restarted search has a budget of `max_evaluations` of its own.
+    pub fn refine<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+        shrink_factor: f64,
+    ) -> Result<(), Error> {
+        if !(shrink_factor > 0. && shrink_factor <= 1.) {
+            return Err(Error::ShrinkFactor(shrink_factor));
+        }
+        let best = self.sce_params.population.row(0).to_owned();
+        let lower_bounds = &mut self.calibration_params.lower_bounds;
+        let upper_bounds = &mut self.calibration_params.upper_bounds;
+        for j in 0..best.len() {
+            let width = shrink_factor * (upper_bounds[j] - lower_bounds[j]);
+            let lower = (best[j] - width / 2.)
+                .clamp(lower_bounds[j], upper_bounds[j] - width);
+            lower_bounds[j] = lower;
+            upper_bounds[j] = lower + width;
+        }
+
+        self.sce_params.initial_population = best.insert_axis(Axis(0));
+        if let Some(validation) = &mut self.sce_params.validation {
+            validation.n_stale = 0;
+        }
+        self.calibration_params.done = false;
+        self.init(data, metadata, observations)
+    }
+
+    /// Returns the number of completed iterations, the number of model
+    /// evaluations, the best objective and the normalized geometric range of
+    /// the population.
+    pub fn progress(&self) -> (usize, usize, f64, f64) {
+        let (objective_idx, _) = self.calibration_params.objective.index();
+        let gnrng = compute_normalized_geometric_range(
+            self.sce_params.population.view(),
+            self.calibration_params.lower_bounds.view(),
+            self.calibration_params.upper_bounds.view(),
+        );
+        (
+            self.sce_params.criteria.len().saturating_sub(1),
+            self.sce_params.n_calls,
+            self.sce_params.objectives[[0, objective_idx]],
+            gnrng,
+        )
+    }
+
+    /// Best validation objective so far, when the search is validated.
+    pub fn validation_objective(&self) -> Option<f64> {
+        self.sce_params.validation.as_ref().map(|v| v.best)
+    }
+
+    /// Hits and misses of the evaluation cache, when there is one. The hits
+    /// are included in the number of evaluations but weren't simulated.
+    pub fn cache_stats(&self) -> Option<(usize, usize)> {
+        let cache = self.sce_params.scoring.cache.as_ref()?.lock().unwrap();
+        Some((cache.hits, cache.misses))
+    }
+
+    /// Evaluations recorded so far, when the search is traced.
+    pub fn trace(&self) -> Option<MutexGuard<'_, Trace>> {
+        let trace = self.sce_params.scoring.trace.as_ref()?;
+        Some(trace.lock().unwrap())
+    }
+
+    /// Writes the whole state of the search, so it can be resumed with
+    /// `load` after a restart.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if self.sce_params.scoring.custom_objective.is_some() {
+            return Err(Error::InvalidCheckpoint(
+                "a custom objective function cannot be saved".to_string(),
+            ));
+        }
+        let mut checkpoint = Checkpoint::new("sce");
+        checkpoint.set("climate_model", &self.sce_params.climate_model);
+        if let Some(snow_model) = &self.sce_params.snow_model {
+            checkpoint.set("snow_model", snow_model);
+        }
+        checkpoint.set("objective", self.calibration_params.objective);
+        checkpoint.set("done", self.calibration_params.done);
+        checkpoint.set_array1("params", self.calibration_params.params.view());
+        checkpoint.set_rng("rng", &self.calibration_params.rng);
+        checkpoint.set_array2("population", self.sce_params.population.view());
+        checkpoint.set_array2("objectives", self.sce_params.objectives.view());
+        checkpoint.set_array1("criteria", self.sce_params.criteria.view());
+        checkpoint.set("n_calls", self.sce_params.n_calls);
+        checkpoint.set_array1(
+            "lower_bounds",
+            self.calibration_params.lower_bounds.view(),
+        );
+        checkpoint.set_array1(
+            "upper_bounds",
+            self.calibration_params.upper_bounds.view(),
+        );
+        checkpoint.set("n_samples", self.sce_params.n_samples);
+        checkpoint.set_array2(
+            "initial_population",
+            self.sce_params.initial_population.view(),
+        );
+        checkpoint.set("n_complexes", self.sce_params.n_complexes);
+        checkpoint.set("k_stop", self.sce_params.k_stop);
+        checkpoint.set(
+            "p_convergence_threshold",
+            self.sce_params.p_convergence_threshold,
+        );
+        checkpoint.set(
+            "geometric_range_threshold",
+            self.sce_params.geometric_range_threshold,
+        );
+        checkpoint.set("max_evaluations", self.sce_params.max_evaluations);
+        checkpoint
+            .set("polish_evaluations", self.sce_params.polish_evaluations);
+        checkpoint.set("restart_fraction", self.sce_params.restart_fraction);
+        if let Some(transform) = &self.sce_params.scoring.transform {
+            let transforms: Vec<String> =
+                transform.transforms.iter().map(|t| t.to_string()).collect();
+            checkpoint.set("transforms", transforms.join(" "));
+            let fixed: Vec<String> = transform
+                .fixed
+                .iter()
+                .enumerate()
+                .filter_map(|(j, value)| {
+                    value.map(|value| format!("{} {}", j, value))
+                })
+                .collect();
+            checkpoint.set("fixed_params", fixed.join(" "));
+        }
+        if let Some(snow_objective) = &self.sce_params.scoring.snow_objective
+        {
+            checkpoint.set("snow_variable", snow_objective.variable);
+            checkpoint.set("snow_weight", snow_objective.weight);
+            checkpoint.set_array1(
+                "snow_observations",
+                snow_objective.observations.view(),
+            );
+        }
+        if let Some(trace) = self.trace() {
+            let n_params = self.calibration_params.params.len();
+            let n_objectives = self.sce_params.objectives.ncols();
+            let iterations: Array1<f64> =
+                trace.iterations.iter().map(|&i| i as f64).collect();
+            checkpoint.set("trace", true);
+            if let Some(max_size) = trace.max_size {
+                checkpoint.set("trace_max_size", max_size);
+            }
+            checkpoint.set_array1("trace_iterations", iterations.view());
+            checkpoint.set_array2(
+                "trace_params",
+                stack_rows(&trace.params, n_params).view(),
+            );
+            checkpoint.set_array2(
+                "trace_objectives",
+                stack_rows(&trace.objectives, n_objectives).view(),
+            );
+        }
+        checkpoint.set("warmup", self.sce_params.scoring.warmup);
+        checkpoint
+            .set("transformation", self.sce_params.scoring.transformation);
+        checkpoint
+            .set("flow_weighting", self.sce_params.scoring.flow_weighting);
+        if let Some(weights) = &self.sce_params.scoring.seasonal_weights {
+            checkpoint.set_array1("seasonal_weights", weights.months.view());
+        }
+        if let Some(weights) = &self.sce_params.scoring.timestep_weights {
+            checkpoint.set_array1("timestep_weights", weights.view());
+        }
+        checkpoint.set(
+            "temporal_aggregation",
+            self.sce_params.scoring.temporal_aggregation,
+        );
+        if let Some(events) = &self.sce_params.scoring.events {
+            checkpoint.set("events_quantile", events.quantile);
+            checkpoint.set("events_separation", events.separation);
+            checkpoint.set("events_padding", events.padding);
+        }
+        if let Some(cache) = &self.sce_params.scoring.cache {
+            let cache = cache.lock().unwrap();
+            checkpoint.set("cache_size", cache.capacity);
+            checkpoint.set("cache_resolution", cache.resolution);
+            checkpoint.set("cache_hits", cache.hits);
+            checkpoint.set("cache_misses", cache.misses);
+            let (keys, values): (Vec<Array1<f64>>, Vec<Array1<f64>>) = cache
+                .entries()
+                .map(|(key, values)| {
+                    (key.iter().map(|&k| k as f64).collect(), values.clone())
+                })
+                .unzip();
+            checkpoint.set_array2(
+                "cache_keys",
+                stack_rows(&keys, self.sce_params.population.ncols()).view(),
+            );
+            checkpoint.set_array2(
+                "cache_values",
+                stack_rows(&values, self.sce_params.objectives.ncols()).view(),
+            );
+        }
+        if let Some(volume_penalty) = &self.sce_params.scoring.volume_penalty {
+            checkpoint.set("volume_tolerance", volume_penalty.tolerance);
+            checkpoint.set("volume_weight", volume_penalty.weight);
+        }
+        if let Some(prior) = &self.sce_params.scoring.prior {
+            checkpoint.set_array1("prior_params", prior.params.view());
+            checkpoint.set("prior_weight", prior.weight);
+        }
+        if let Some(range) = &self.sce_params.scoring.validation_range {
+            checkpoint.set("validation_start", range.start);
+            checkpoint.set("validation_end", range.end);
+        }
+        if let Some(validation) = &self.sce_params.validation {
+            checkpoint.set("validation_patience", validation.patience);
+            checkpoint.set("validation_best", validation.best);
+            checkpoint.set("validation_n_stale", validation.n_stale);
+            if let Some(observations) = &validation.observations {
+                checkpoint.set_array1(
+                    "validation_observations",
+                    observations.view(),
+                );
+            }
+        }
+        let gauges = &self.sce_params.scoring.gauges;
+        checkpoint.set("n_gauges", gauges.len());
+        for (i, gauge) in gauges.iter().enumerate() {
+            checkpoint.set(&format!("gauge_{}_weight", i), gauge.weight);
+            checkpoint.set_array1(
+                &format!("gauge_{}_observations", i),
+                gauge.observations.view(),
+            );
+        }
+        checkpoint.write(path)
+    }
+
+    /// Restores a search written by `save`, continuing exactly where it
+    /// stopped.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let checkpoint = Checkpoint::read(path, "sce")?;
+        let climate_model: String = checkpoint.get("climate_model")?;
+        let snow_model: Option<String> =
+            checkpoint.get_optional("snow_model")?;
+        let objective: String = checkpoint.get("objective")?;
+        let objective = Objective::from_str(&objective)
+            .map_err(Error::InvalidCheckpoint)?;
+        let snow_variable: Option<String> =
+            checkpoint.get_optional("snow_variable")?;
+        let snow_objective = match (&snow_model, snow_variable) {
+            (Some(snow_model), Some(snow_variable)) => Some(SnowObjective::new(
+                snow_model,
+                SnowVariable::from_str(&snow_variable)
+                    .map_err(Error::InvalidCheckpoint)?,
+                checkpoint.get_array1("snow_observations")?,
+                checkpoint.get("snow_weight")?,
+            )?),
+            _ => None,
+        };
+        let n_gauges: usize =
+            checkpoint.get_optional("n_gauges")?.unwrap_or(0);
+        let gauges = (0..n_gauges)
+            .map(|i| {
+                Ok(Gauge {
+                    observations: checkpoint
+                        .get_array1(&format!("gauge_{}_observations", i))?,
+                    weight: checkpoint.get(&format!("gauge_{}_weight", i))?,
+                })
+            })
+            .collect::<Result<Vec<Gauge>, Error>>()?;
+        let transforms: Option<String> =
+            checkpoint.get_optional("transforms")?;
+        let transforms = transforms
+            .unwrap_or_default()
+            .split(' ')
+            .filter(|t| !t.is_empty())
+            .map(Transform::from_str)
+            .collect::<Result<Vec<Transform>, String>>()
+            .map_err(Error::InvalidCheckpoint)?;
+        let fixed_params: Option<String> =
+            checkpoint.get_optional("fixed_params")?;
+        let fixed_params: Vec<&str> = fixed_params
+            .as_deref()
+            .unwrap_or_default()
+            .split(' ')
+            .filter(|x| !x.is_empty())
+            .collect();
+        let fixed_params = fixed_params
+            .chunks(2)
+            .map(|pair| match pair {
+                [j, value] => Some((j.parse().ok()?, value.parse().ok()?)),
+                _ => None,
+            })
+            .collect::<Option<Vec<(usize, f64)>>>()
+            .ok_or_else(|| {
+                Error::InvalidCheckpoint(
+                    "invalid value for field 'fixed_params'".to_string(),
+                )
+            })?;
+        let trace = match checkpoint.get_optional("trace")? {
+            Some(true) => {
+                let iterations = checkpoint.get_array1("trace_iterations")?;
+                let params = checkpoint.get_array2("trace_params")?;
+                let objectives = checkpoint.get_array2("trace_objectives")?;
+                Some(Mutex::new(Trace {
+                    iteration: 0,
+                    max_size: checkpoint.get_optional("trace_max_size")?,
+                    iterations: iterations
+                        .iter()
+                        .map(|&i| i as usize)
+                        .collect(),
+                    params: params
+                        .outer_iter()
+                        .map(|r| r.to_owned())
+                        .collect(),
+                    objectives: objectives
+                        .outer_iter()
+                        .map(|r| r.to_owned())
+                        .collect(),
+                }))
+            }
+            _ => None,
+        };
+        let transformation: Option<String> =
+            checkpoint.get_optional("transformation")?;
+        let transformation = transformation
+            .map(|t| Transformation::from_str(&t))
+            .transpose()
+            .map_err(Error::InvalidCheckpoint)?
+            .unwrap_or_default();
+        let flow_weighting: Option<String> =
+            checkpoint.get_optional("flow_weighting")?;
+        let flow_weighting = flow_weighting
+            .map(|w| FlowWeighting::from_str(&w))
+            .transpose()
+            .map_err(Error::InvalidCheckpoint)?
+            .unwrap_or_default();
+        let temporal_aggregation: Option<String> =
+            checkpoint.get_optional("temporal_aggregation")?;
+        let temporal_aggregation = temporal_aggregation
+            .map(|a| TemporalAggregation::from_str(&a))
+            .transpose()
+            .map_err(Error::InvalidCheckpoint)?
+            .unwrap_or_default();
+        let events = match checkpoint.get_optional("events_quantile")? {
+            Some(quantile) => Some(Events {
+                quantile,
+                separation: checkpoint.get("events_separation")?,
+                padding: checkpoint.get("events_padding")?,
+            }),
+            None => None,
+        };
+        let seasonal_weights = match checkpoint
+            .get_optional::<String>("seasonal_weights")?
+        {
+            Some(_) => Some(SeasonalWeights {
+                months: checkpoint.get_array1("seasonal_weights")?,
+            }),
+            None => None,
+        };
+        let timestep_weights =
+            match checkpoint.get_optional::<String>("timestep_weights")? {
+                Some(_) => Some(checkpoint.get_array1("timestep_weights")?),
+                None => None,
+            };
+        let prior = match checkpoint.get_optional("prior_weight")? {
+            Some(weight) => Some(Prior::new(
+                checkpoint.get_array1("prior_params")?,
+                weight,
+            )),
+            None => None,
+        };
+        let volume_penalty =
+            match checkpoint.get_optional("volume_tolerance")? {
+                Some(tolerance) => Some(VolumePenalty {
+                    tolerance,
+                    weight: checkpoint.get("volume_weight")?,
+                }),
+                None => None,
+            };
+        // refilled in the order the entries were used, so the same ones are
+        // evicted next
+        let cache = match checkpoint.get_optional("cache_size")? {
+            Some(capacity) => {
+                let mut cache = EvaluationCache::new(
+                    capacity,
+                    checkpoint.get("cache_resolution")?,
+                );
+                cache.hits = checkpoint.get("cache_hits")?;
+                cache.misses = checkpoint.get("cache_misses")?;
+                let keys = checkpoint.get_array2("cache_keys")?;
+                let values = checkpoint.get_array2("cache_values")?;
+                if keys.nrows() != values.nrows() {
+                    return Err(Error::InvalidCheckpoint(
+                        "cache_keys and cache_values must have the same \
+                         number of rows"
+                            .to_string(),
+                    ));
+                }
+                for (key, values) in keys.rows().into_iter().zip(values.rows())
+                {
+                    cache.restore(
+                        key.iter().map(|&k| k as i64).collect(),
+                        values.to_owned(),
+                    );
+                }
+                Some(Mutex::new(cache))
+            }
+            None => None,
+        };
+        let validation_range = match (
+            checkpoint.get_optional("validation_start")?,
+            checkpoint.get_optional("validation_end")?,
+        ) {
+            (Some(start), Some(end)) => Some(start..end),
+            _ => None,
+        };
+        let patience = checkpoint.get_optional("validation_patience")?;
+        let validation = match patience {
+            Some(patience) => {
+                let observations = match checkpoint
+                    .get_optional::<String>("validation_observations")?
+                {
+                    Some(_) => {
+                        Some(checkpoint.get_array1("validation_observations")?)
+                    }
+                    None => None,
+                };
+                let mut validation = Validation::new(observations, patience);
+                validation.best = checkpoint.get("validation_best")?;
+                validation.n_stale = checkpoint.get("validation_n_stale")?;
+                Some(validation)
+            }
+            None => None,
+        };
+        let scoring = Scoring {
+            objective: None,
+            custom_objective: None,
+            snow_objective,
+            gauges,
+            transform: None,
+            trace,
+            validation_range,
+            warmup: checkpoint.get_optional("warmup")?.unwrap_or(0),
+            transformation,
+            flow_weighting,
+            events,
+            seasonal_weights,
+            timestep_weights,
+            temporal_aggregation,
+            prior,
+            volume_penalty,
+            cache,
+        };
+
+        let mut sce = Sce::new(
+            &climate_model,
+            snow_model.as_deref(),
+            objective,
+            checkpoint.get("n_complexes")?,
+            checkpoint.get("k_stop")?,
+            checkpoint.get("p_convergence_threshold")?,
+            checkpoint.get("geometric_range_threshold")?,
+            checkpoint.get("max_evaluations")?,
+            0,
+            Some(checkpoint.get("n_samples")?),
+            checkpoint.get("polish_evaluations")?,
+            checkpoint.get_optional("restart_fraction")?.unwrap_or(0.),
+            transforms,
+            fixed_params,
+            None,
+            validation,
+            scoring,
+        )?;
+
+        let population = checkpoint.get_array2("population")?;
+        let objectives = checkpoint.get_array2("objectives")?;
+        if population.dim() != sce.sce_params.population.dim()
+            || objectives.dim() != sce.sce_params.objectives.dim()
+        {
+            return Err(Error::InvalidCheckpoint(
+                "the population does not match the model and complexes"
+                    .to_string(),
+            ));
+        }
+
+        sce.calibration_params.done = checkpoint.get("done")?;
+        sce.calibration_params.params = checkpoint.get_array1("params")?;
+        sce.calibration_params.rng = checkpoint.get_rng("rng")?;
+        sce.sce_params.population = population;
+        sce.sce_params.objectives = objectives;
+        sce.sce_params.criteria = checkpoint.get_array1("criteria")?;
+        sce.sce_params.n_calls = checkpoint.get("n_calls")?;
+        // shrunk by `refine`
+        if checkpoint.get_optional::<String>("lower_bounds")?.is_some() {
+            sce.calibration_params.lower_bounds =
+                checkpoint.get_array1("lower_bounds")?;
+            sce.calibration_params.upper_bounds =
+                checkpoint.get_array1("upper_bounds")?;
+        }
+        if checkpoint.get_optional::<String>("initial_population")?.is_some() {
+            sce.sce_params.initial_population =
+                checkpoint.get_array2("initial_population")?;
+        }
+        Ok(sce)
+    }
 }
 
 #[pymethods]
 impl Sce {
     #[new]
+    #[pyo3(signature = (
+        climate_model,
+        snow_model,
+        objective,
+        n_complexes,
+        k_stop,
+        p_convergence_threshold,
+        geometric_range_threshold,
+        max_evaluations,
+        seed,
+        n_samples=None,
+        polish_evaluations=0,
+        restart_fraction=0.0,
+        minimize=true,
+        snow_observations=None,
+        snow_variable="swe",
+        snow_weight=0.5,
+        gauges=None,
+        transforms=None,
+        fixed_params=None,
+        initial_params=None,
+        initial_population=None,
+        trace=false,
+        trace_size=None,
+        validation_range=None,
+        validation_observations=None,
+        validation_patience=10,
+        warmup=0,
+        transformation="none",
+        flow_weighting="none",
+        events=None,
+        event_separation=5,
+        event_padding=2,
+        seasonal_weights=None,
+        timestep_weights=None,
+        temporal_aggregation="none",
+        prior_params=None,
+        prior_weight=1.0,
+        volume_tolerance=None,
+        volume_weight=1.0,
+        cache_size=None,
+        cache_resolution=1e-6,
+    ))]
     pub fn py_new(
         climate_model: &str,
         snow_model: Option<&str>,
-        objective: &str,
+        objective: Bound<'_, PyAny>,
         n_complexes: usize,
         k_stop: usize,
         p_convergence_threshold: f64,
         geometric_range_threshold: f64,
         max_evaluations: usize,
         seed: u64,
+        n_samples: Option<usize>,
+        polish_evaluations: usize,
+        restart_fraction: f64,
+        minimize: bool,
+        snow_observations: Option<PyReadonlyArray1<'_, f64>>,
+        snow_variable: &str,
+        snow_weight: f64,
+        gauges: Option<Vec<(PyReadonlyArray1<'_, f64>, f64)>>,
+        transforms: Option<Vec<String>>,
+        fixed_params: Option<HashMap<usize, f64>>,
+        initial_params: Option<PyReadonlyArray1<'_, f64>>,
+        initial_population: Option<PyReadonlyArray2<'_, f64>>,
+        trace: bool,
+        trace_size: Option<usize>,
+        validation_range: Option<(usize, usize)>,
+        validation_observations: Option<PyReadonlyArray1<'_, f64>>,
+        validation_patience: usize,
+        warmup: usize,
+        transformation: &str,
+        flow_weighting: &str,
+        events: Option<f64>,
+        event_separation: usize,
+        event_padding: usize,
+        seasonal_weights: Option<Vec<f64>>,
+        timestep_weights: Option<PyReadonlyArray1<'_, f64>>,
+        temporal_aggregation: &str,
+        prior_params: Option<PyReadonlyArray1<'_, f64>>,
+        prior_weight: f64,
+        volume_tolerance: Option<f64>,
+        volume_weight: f64,
+        cache_size: Option<usize>,
+        cache_resolution: f64,
     ) -> PyResult<Self> {
-        let objective = Objective::from_str(objective)
+        // a metric name, or a function of the observations and simulation
+        let (objective, custom_objective) =
+            if let Ok(objective) = objective.extract::<&str>() {
+                let objective = Objective::from_str(objective)
+                    .map_err(pyo3::exceptions::PyValueError::new_err)?;
+                (objective, None)
+            } else if objective.is_callable() {
+                (
+                    Objective::Custom { minimize },
+                    Some(CustomObjective {
+                        function: objective.unbind(),
+                        minimize,
+                    }),
+                )
+            } else {
+                return Err(pyo3::exceptions::PyTypeError::new_err(
+                    "objective must be a metric name or a callable",
+                ));
+            };
+        let snow_objective = match (snow_model, snow_observations) {
+            (_, None) => None,
+            (None, Some(_)) => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "snow_observations require a snow_model",
+                ));
+            }
+            (Some(snow_model), Some(snow_observations)) => {
+                if !(0. ..=1.).contains(&snow_weight) {
+                    return Err(pyo3::exceptions::PyValueError::new_err(
+                        "snow_weight must be between 0 and 1",
+                    ));
+                }
+                let snow_variable = SnowVariable::from_str(snow_variable)
+                    .map_err(pyo3::exceptions::PyValueError::new_err)?;
+                Some(
+                    SnowObjective::new(
+                        snow_model,
+                        snow_variable,
+                        snow_observations.as_array().to_owned(),
+                        snow_weight,
+                    )
+                    .map_err(|e| {
+                        pyo3::exceptions::PyValueError::new_err(e.to_string())
+                    })?,
+                )
+            }
+        };
+        // the snow observations are only scored with the metrics
+        if custom_objective.is_some() && snow_objective.is_some() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "a custom objective can't be combined with snow_observations",
+            ));
+        }
+        // other gauges, weighted relative to the main observations
+        let gauges = gauges
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(observations, weight)| {
+                if weight.is_nan() || weight < 0. {
+                    return Err(pyo3::exceptions::PyValueError::new_err(
+                        "gauge weights must be positive",
+                    ));
+                }
+                Ok(Gauge {
+                    observations: observations.as_array().to_owned(),
+                    weight,
+                })
+            })
+            .collect::<PyResult<Vec<Gauge>>>()?;
+        // a previous calibration or regional estimates to start from
+        let mut rows: Vec<Array1<f64>> = initial_params
+            .map(|params| params.as_array().to_owned())
+            .into_iter()
+            .collect();
+        if let Some(population) = &initial_population {
+            let population = population.as_array();
+            rows.extend(population.rows().into_iter().map(|r| r.to_owned()));
+        }
+        let n_columns = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != n_columns) {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "initial_params and initial_population must have the same \
+                 number of params",
+            ));
+        }
+        let initial_population =
+            (!rows.is_empty()).then(|| stack_rows(&rows, n_columns));
+        let transforms = transforms
+            .unwrap_or_default()
+            .iter()
+            .map(|t| Transform::from_str(t))
+            .collect::<Result<Vec<Transform>, String>>()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let transformation = Transformation::from_str(transformation)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let flow_weighting = FlowWeighting::from_str(flow_weighting)
             .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let temporal_aggregation =
+            TemporalAggregation::from_str(temporal_aggregation)
+                .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        // peaks over the given quantile of the observed discharge
+        let events = match events {
+            Some(quantile) if !(0. ..1.).contains(&quantile) => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "events must be a quantile between 0 and 1",
+                ));
+            }
+            Some(quantile) => Some(Events {
+                quantile,
+                separation: event_separation,
+                padding: event_padding,
+            }),
+            None => None,
+        };
+        // held out timesteps of the observations, or a series of their own
+        let validation_range =
+            validation_range.map(|(start, end)| start..end);
+        let validation = match (&validation_range, validation_observations) {
+            (None, None) => None,
+            (Some(_), Some(_)) => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "give either validation_range or validation_observations",
+                ));
+            }
+            (_, observations) => Some(Validation::new(
+                observations.map(|x| x.as_array().to_owned()),
+                validation_patience,
+            )),
+        };
+        // relative error on the runoff volume tolerated without penalty
+        let volume_penalty = match volume_tolerance {
+            Some(tolerance) if tolerance.is_nan() || tolerance < 0. => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "volume_tolerance must be positive",
+                ));
+            }
+            Some(tolerance) => Some(VolumePenalty {
+                tolerance,
+                weight: volume_weight,
+            }),
+            None => None,
+        };
+        if cache_size.is_some()
+            && (cache_resolution.is_nan() || cache_resolution <= 0.)
+        {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "cache_resolution must be positive",
+            ));
+        }
+        // per month or per season
+        let seasonal_weights = seasonal_weights
+            .map(|weights| SeasonalWeights::new(&weights))
+            .transpose()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        // per timestep of the record, e.g. from the observation uncertainty
+        let timestep_weights =
+            timestep_weights.map(|weights| weights.as_array().to_owned());
+        if let Some(weights) = &timestep_weights {
+            if weights.iter().any(|w| w.is_nan() || *w < 0.) {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "timestep_weights must be positive",
+                ));
+            }
+        }
         Sce::new(
             climate_model,
             snow_model,
@@ -319,6 +1393,39 @@ impl Sce {
             geometric_range_threshold,
             max_evaluations,
             seed,
+            n_samples,
+            polish_evaluations,
+            restart_fraction,
+            transforms,
+            fixed_params.unwrap_or_default().into_iter().collect(),
+            initial_population,
+            validation,
+            Scoring {
+                objective: None,
+                custom_objective,
+                snow_objective,
+                gauges,
+                transform: None,
+                trace: trace.then(|| Mutex::new(Trace::new(trace_size))),
+                validation_range,
+                warmup,
+                transformation,
+                flow_weighting,
+                events,
+                seasonal_weights,
+                timestep_weights,
+                temporal_aggregation,
+                prior: prior_params.map(|params| {
+                    Prior::new(params.as_array().to_owned(), prior_weight)
+                }),
+                volume_penalty,
+                cache: cache_size.map(|capacity| {
+                    Mutex::new(EvaluationCache::new(
+                        capacity,
+                        cache_resolution,
+                    ))
+                }),
+            },
         )
         .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
     }
@@ -326,44 +1433,80 @@ impl Sce {
     #[pyo3(name = "init")]
     pub fn py_init(
         &mut self,
+        py: Python<'_>,
         data: PyData<'_>,
         metadata: PyMetadata<'_>,
         observations: PyReadonlyArray1<'_, f64>,
     ) -> PyResult<()> {
-        self.init(
-            data.as_data().map_err(|e| {
+        let data = data.as_data().map_err(|e| {
+            pyo3::exceptions::PyValueError::new_err(e.to_string())
+        })?;
+        let metadata = metadata.as_metadata();
+        let observations = observations.as_array();
+        // the custom objective takes the GIL back in each evaluation thread
+        py.detach(|| self.init(data, &metadata, observations))
+            .map_err(|e| {
                 pyo3::exceptions::PyValueError::new_err(e.to_string())
-            })?,
-            &metadata.as_metadata(),
-            observations.as_array(),
-        )
+            })
+    }
+
+    #[pyo3(name = "refine", signature = (
+        data,
+        metadata,
+        observations,
+        shrink_factor=0.2,
+    ))]
+    pub fn py_refine(
+        &mut self,
+        py: Python<'_>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+        shrink_factor: f64,
+    ) -> PyResult<()> {
+        let data = data.as_data().map_err(|e| {
+            pyo3::exceptions::PyValueError::new_err(e.to_string())
+        })?;
+        let metadata = metadata.as_metadata();
+        let observations = observations.as_array();
+        py.detach(|| {
+            self.refine(data, &metadata, observations, shrink_factor)
+        })
         .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
     }
 
-    #[pyo3(name = "step")]
+    #[pyo3(name = "step", signature = (
+        data,
+        metadata,
+        observations,
+        callback=None,
+    ))]
     pub fn py_step<'py>(
         &mut self,
         py: Python<'py>,
         data: PyData<'_>,
         metadata: PyMetadata<'_>,
         observations: PyReadonlyArray1<'_, f64>,
+        callback: Option<Bound<'py, PyAny>>,
     ) -> PyResult<(
         bool,
         Bound<'py, PyArray1<f64>>,
         Bound<'py, PyArray1<f64>>,
         Bound<'py, PyArray1<f64>>,
     )> {
-        let (done, best_params, simulation, objectives) = self
-            .step(
-                data.as_data().map_err(|e| {
-                    pyo3::exceptions::PyValueError::new_err(e.to_string())
-                })?,
-                &metadata.as_metadata(),
-                observations.as_array(),
-            )
+        let data = data.as_data().map_err(|e| {
+            pyo3::exceptions::PyValueError::new_err(e.to_string())
+        })?;
+        let metadata = metadata.as_metadata();
+        let observations = observations.as_array();
+        let (done, best_params, simulation, objectives) = py
+            .detach(|| self.step(data, &metadata, observations))
             .map_err(|e| {
                 pyo3::exceptions::PyValueError::new_err(e.to_string())
             })?;
+        if let Some(callback) = callback {
+            callback.call1(self.progress())?;
+        }
         Ok((
             done,
             best_params.to_pyarray(py),
@@ -371,25 +1514,112 @@ impl Sce {
             objectives.to_pyarray(py),
         ))
     }
+
+    /// Initializes the search and steps until it is done, calling `callback`
+    /// with the progress after each step.
+    #[pyo3(name = "run", signature = (
+        data,
+        metadata,
+        observations,
+        callback=None,
+    ))]
+    pub fn py_run<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+        callback: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<(
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+    )> {
+        let data = data.as_data()?;
+        let metadata = metadata.as_metadata();
+        let observations = observations.as_array();
+        if self.sce_params.criteria.is_empty() {
+            py.detach(|| self.init(data, &metadata, observations))?;
+        }
+        loop {
+            let (done, best_params, simulation, objectives) =
+                py.detach(|| self.step(data, &metadata, observations))?;
+            if let Some(callback) = &callback {
+                callback.call1(self.progress())?;
+            }
+            if done {
+                return Ok((
+                    best_params.to_pyarray(py),
+                    simulation.to_pyarray(py),
+                    objectives.to_pyarray(py),
+                ));
+            }
+        }
+    }
+
+    /// Every evaluated parameter set with its objectives and iteration, or
+    /// None when the search isn't traced.
+    #[pyo3(name = "trace")]
+    pub fn py_trace<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let n_params = self.calibration_params.params.len();
+        let n_objectives = self.sce_params.objectives.ncols();
+        self.trace()
+            .map(|trace| trace.to_py_dict(py, n_params, n_objectives))
+            .transpose()
+    }
+
+    /// Number of evaluations so far, with the hits and misses of the
+    /// evaluation cache when there is one.
+    #[pyo3(name = "evaluations")]
+    pub fn py_evaluations<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("n_evaluations", self.sce_params.n_calls)?;
+        if let Some((hits, misses)) = self.cache_stats() {
+            dict.set_item("cache_hits", hits)?;
+            dict.set_item("cache_misses", misses)?;
+        }
+        Ok(dict)
+    }
+
+    #[pyo3(name = "validation_objective")]
+    pub fn py_validation_objective(&self) -> Option<f64> {
+        self.validation_objective()
+    }
+
+    #[pyo3(name = "save")]
+    pub fn py_save(&self, path: std::path::PathBuf) -> PyResult<()> {
+        Ok(self.save(&path)?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "load")]
+    pub fn py_load(path: std::path::PathBuf) -> PyResult<Self> {
+        Ok(Sce::load(&path)?)
+    }
 }
 
+/// Latin hypercube sample of the parameter space, the first point being the
+/// centre of the bounds.
 fn generate_initial_population(
-    population_size: usize,
+    n_samples: usize,
+    initial_population: ArrayView2<f64>,
     lower_bounds: &Array1<f64>,
     upper_bounds: &Array1<f64>,
     rng: &mut ChaCha8Rng,
 ) -> Array2<f64> {
-    let n_params = lower_bounds.len();
-
-    let random_values: Array2<f64> = Array2::random_using(
-        (population_size, n_params),
-        Uniform::new(0., 1.).unwrap(),
+    let mut population = sample_latin_hypercube(
+        n_samples,
+        lower_bounds.view(),
+        upper_bounds.view(),
         rng,
     );
 
-    let range = upper_bounds - lower_bounds;
-    let mut population = &random_values * &range + lower_bounds;
-
     let initial_point: Array1<f64> = Array1::from_iter(
         lower_bounds
             .iter()
@@ -399,84 +1629,8 @@ fn generate_initial_population(
 
     population.row_mut(0).assign(&initial_point);
 
-    population
-}
-
-fn evaluate_initial_population(
-    simulate: &SimulateFn,
-    data: Data,
-    metadata: &Metadata,
-    observations: ArrayView1<f64>,
-    mut population: Array2<f64>,
-    objective: Objective,
-) -> Result<(Array2<f64>, Array2<f64>), Error> {
-    let n_population = population.nrows();
-    let mut objectives = Array2::<f64>::zeros((n_population, 3));
-
-    let results: Vec<Result<Array1<f64>, Error>> = (0..n_population)
-        .into_par_iter()
-        .map(|i| {
-            let params = population.row(i);
-            let simulation = simulate(params, data, metadata)?;
-            evaluate_simulation(observations, simulation.view())
-        })
-        .collect();
-    for (i, result) in results.into_iter().enumerate() {
-        objectives.row_mut(i).assign(&result?);
-    }
-
-    let (objective_idx, is_minimization) = match objective {
-        Objective::Rmse => (0, true),
-        Objective::Nse => (1, false),
-        Objective::Kge => (2, false),
-    };
-
-    sort_population(
-        &mut population,
-        &mut objectives,
-        objective_idx,
-        is_minimization,
-    );
-
-    Ok((population, objectives))
-}
-
-fn evaluate_simulation(
-    observations: ArrayView1<f64>,
-    simulations: ArrayView1<f64>,
-) -> Result<Array1<f64>, Error> {
-    Ok(Array1::from_vec(vec![
-        calculate_rmse(observations, simulations)?,
-        calculate_nse(observations, simulations)?,
-        calculate_kge(observations, simulations)?,
-    ]))
-}
-
-fn sort_population(
-    population: &mut Array2<f64>,
-    objectives: &mut Array2<f64>,
-    objective_idx: usize,
-    is_minimization: bool,
-) {
-    let mut indices: Vec<usize> = (0..objectives.nrows()).collect();
-
-    if is_minimization {
-        indices.sort_by(|&a, &b| {
-            objectives[[a, objective_idx]]
-                .total_cmp(&objectives[[b, objective_idx]])
-        });
-    } else {
-        indices.sort_by(|&a, &b| {
-            objectives[[b, objective_idx]]
-                .total_cmp(&objectives[[a, objective_idx]])
-        });
-    }
-
-    let sorted_population = population.select(Axis(0), &indices);
-    let sorted_objectives = objectives.select(Axis(0), &indices);
-
-    *population = sorted_population;
-    *objectives = sorted_objectives;
+    ndarray::concatenate(Axis(0), &[population.view(), initial_population])
+        .unwrap()
 }
 
 fn compute_normalized_geometric_range(
@@ -534,6 +1688,8 @@ fn evolve_complexes(
     n_per_complex: usize,
     n_simplex: usize,
     n_evolution_steps: usize,
+    max_evaluations: usize,
+    scoring: &Scoring,
     rng: &mut ChaCha8Rng,
 ) -> Result<usize, Error> {
     // Sequential evolution (parallel version had convergence issues)
@@ -542,6 +1698,11 @@ fn evolve_complexes(
         let cf = &mut complex_objectives[igs];
 
         for _ in 0..n_evolution_steps {
+            // the rest of the complexes are left as they are once the
+            // evaluation budget is spent
+            if n_calls >= max_evaluations {
+                return Ok(n_calls);
+            }
             let simplex_indices =
                 select_simplex_indices(n_per_complex, n_simplex, rng);
             let mut s = cx.select(Axis(0), &simplex_indices);
@@ -558,6 +1719,8 @@ fn evolve_complexes(
                 observations,
                 objective_idx,
                 is_minimization,
+                max_evaluations - n_calls,
+                scoring,
                 rng,
             )?;
             n_calls += calls_made;
@@ -580,7 +1743,8 @@ fn evolve_complexes(
     Ok(n_calls)
 }
 
-/// Single step of complex evolution (extracted for parallel execution)
+/// Single step of complex evolution (extracted for parallel execution),
+/// making at most `max_calls` evaluations.
 fn evolve_complex_step(
     simplex: ArrayView2<f64>,
     simplex_objectives: ArrayView2<f64>,
@@ -592,6 +1756,8 @@ fn evolve_complex_step(
     observations: ArrayView1<f64>,
     objective_idx: usize,
     is_minimization: bool,
+    max_calls: usize,
+    scoring: &Scoring,
     rng: &mut ChaCha8Rng,
 ) -> Result<(Array1<f64>, Array1<f64>, usize), Error> {
     // This is the same logic as evolve_complexes_competitively but returns call count delta
@@ -637,27 +1803,45 @@ fn evolve_complex_step(
     }
 
     // evaluate reflection point
-    let simulation = simulate(snew.view(), data, metadata)?;
-    let mut fnew = evaluate_simulation(observations, simulation.view())?;
+    let mut fnew = evaluate_params(
+        simulate,
+        snew.view(),
+        data,
+        metadata,
+        observations,
+        scoring,
+    )?;
     calls += 1;
 
     // if reflection failed (worse than worst), try contraction
-    if is_worse(fnew[objective_idx], fw) {
+    if is_worse(fnew[objective_idx], fw) && calls < max_calls {
         snew = sw.to_owned() + beta * (&ce - &sw);
-        let simulation = simulate(snew.view(), data, metadata)?;
-        fnew = evaluate_simulation(observations, simulation.view())?;
+        fnew = evaluate_params(
+            simulate,
+            snew.view(),
+            data,
+            metadata,
+            observations,
+            scoring,
+        )?;
         calls += 1;
 
         // if contraction also failed, use random point
-        if is_worse(fnew[objective_idx], fw) {
+        if is_worse(fnew[objective_idx], fw) && calls < max_calls {
             let random_values: Array1<f64> = Array1::random_using(
                 snew.len(),
                 Uniform::new(0., 1.).unwrap(),
                 rng,
             );
             snew = &random_values * &range + lower_bounds;
-            let simulation = simulate(snew.view(), data, metadata)?;
-            fnew = evaluate_simulation(observations, simulation.view())?;
+            fnew = evaluate_params(
+                simulate,
+                snew.view(),
+                data,
+                metadata,
+                observations,
+                scoring,
+            )?;
             calls += 1;
         }
     }
@@ -724,8 +1908,237 @@ fn merge_complexes(
     (population, objectives)
 }
 
+/// Runs `n_replicates` independent calibrations in parallel, with the seeds
+/// `seed`, `seed + 1`, ..., to diagnose convergence to local optima.
+///
+/// Returns the best parameters and objectives (see `evaluate_simulation`) of
+/// each replicate and the index of the best replicate.
+pub fn run_replicates<'a>(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: Objective,
+    data: Data<'a>,
+    metadata: &Metadata<'a>,
+    observations: ArrayView1<f64>,
+    n_replicates: usize,
+    n_complexes: usize,
+    k_stop: usize,
+    p_convergence_threshold: f64,
+    geometric_range_threshold: f64,
+    max_evaluations: usize,
+    seed: u64,
+) -> Result<(Array2<f64>, Array2<f64>, usize), Error> {
+    let results: Vec<Result<(Array1<f64>, Array1<f64>), Error>> = (0
+        ..n_replicates)
+        .into_par_iter()
+        .map(|i| {
+            let mut sce = Sce::new(
+                climate_model,
+                snow_model,
+                objective,
+                n_complexes,
+                k_stop,
+                p_convergence_threshold,
+                geometric_range_threshold,
+                max_evaluations,
+                seed.wrapping_add(i as u64),
+                None,
+                0,
+                0.,
+                vec![],
+                vec![],
+                None,
+                None,
+                Scoring::default(),
+            )?;
+            sce.init(data, metadata, observations)?;
+            loop {
+                let (done, params, _, objectives) =
+                    sce.step(data, metadata, observations)?;
+                if done {
+                    return Ok((params, objectives));
+                }
+            }
+        })
+        .collect();
+
+    let mut params: Vec<Array1<f64>> = vec![];
+    let mut objectives: Vec<Array1<f64>> = vec![];
+    for result in results {
+        let (replicate_params, replicate_objectives) = result?;
+        params.push(replicate_params);
+        objectives.push(replicate_objectives);
+    }
+    let params = stack_rows(&params, 0);
+    let objectives = stack_rows(&objectives, N_METRICS);
+
+    let (objective_idx, is_minimization) = objective.index();
+    let best = (0..objectives.nrows())
+        .min_by(|&a, &b| {
+            let (a, b) = (
+                objectives[[a, objective_idx]],
+                objectives[[b, objective_idx]],
+            );
+            if is_minimization {
+                a.total_cmp(&b)
+            } else {
+                b.total_cmp(&a)
+            }
+        })
+        .unwrap_or(0);
+
+    Ok((params, objectives, best))
+}
+
+#[pyfunction]
+#[pyo3(name = "run_replicates")]
+pub fn py_run_replicates<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    n_replicates: usize,
+    n_complexes: usize,
+    k_stop: usize,
+    p_convergence_threshold: f64,
+    geometric_range_threshold: f64,
+    max_evaluations: usize,
+    seed: u64,
+) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>, usize)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (params, objectives, best) = run_replicates(
+        climate_model,
+        snow_model,
+        objective,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        n_replicates,
+        n_complexes,
+        k_stop,
+        p_convergence_threshold,
+        geometric_range_threshold,
+        max_evaluations,
+        seed,
+    )?;
+    Ok((params.to_pyarray(py), objectives.to_pyarray(py), best))
+}
+
 pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
     let m = PyModule::new(py, "sce")?;
     m.add_class::<Sce>()?;
+    m.add_function(wrap_pyfunction!(py_run_replicates, &m)?)?;
     Ok(m)
 }
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::calibration::utils::EvaluationCache;
+    use crate::snow::utils::Sublimation;
+
+    #[test]
+    fn checkpoint_resumes_the_same_search() {
+        let n = 730;
+        let day_of_year: Array1<usize> =
+            (0..n).map(|t| t % 365 + 1).collect();
+        let precipitation: Array1<f64> =
+            (0..n).map(|t| if t % 4 == 0 { 12. } else { 0. }).collect();
+        let temperature = day_of_year.mapv(|d| {
+            10. - 12. * (2. * std::f64::consts::PI * d as f64 / 365.).cos()
+        });
+        let pet = temperature.mapv(|t| (0.1 * t + 1.).max(0.));
+        let data = || {
+            Data::new(
+                precipitation.view(),
+                temperature.view(),
+                pet.view(),
+                day_of_year.view(),
+            )
+            .unwrap()
+        };
+        let empty = array![];
+        let metadata = Metadata {
+            area: 100.,
+            elevation_layers: empty.view(),
+            median_elevation: 500.,
+            timestep: None,
+            latitude: 45.,
+            glacier_fraction: 0.,
+            temperature_lapse_rate: None,
+            precipitation_gradient: None,
+            snow_cover_threshold: 0.,
+            sublimation: Sublimation::default(),
+            topographic_index: empty.view(),
+            topographic_index_fractions: empty.view(),
+        };
+        // observations from a known parameter set, with some missing
+        let (simulate, _, _) = build_model("gr4j", None).unwrap();
+        let mut observations =
+            simulate(array![300., 1., 80., 2.].view(), data(), &metadata)
+                .unwrap();
+        observations.slice_mut(s![..;50]).fill(f64::NAN);
+
+        let mut sce = Sce::new(
+            "gr4j",
+            None,
+            Objective::Kge,
+            2,
+            5,
+            0.001,
+            0.001,
+            10000,
+            42,
+            None,
+            0,
+            0.,
+            vec![
+                Transform::Log,
+                Transform::Linear,
+                Transform::Linear,
+                Transform::Linear,
+            ],
+            vec![],
+            None,
+            None,
+            Scoring {
+                cache: Some(Mutex::new(EvaluationCache::new(100, 1e-6))),
+                ..Scoring::default()
+            },
+        )
+        .unwrap();
+        sce.init(data(), &metadata, observations.view()).unwrap();
+        sce.step(data(), &metadata, observations.view()).unwrap();
+
+        let path = std::env::temp_dir()
+            .join(format!("hydro_sce_checkpoint_{}", std::process::id()));
+        sce.save(&path).unwrap();
+        let mut loaded = Sce::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for _ in 0..3 {
+            let (done, params, _, objectives) =
+                sce.step(data(), &metadata, observations.view()).unwrap();
+            let (loaded_done, loaded_params, _, loaded_objectives) = loaded
+                .step(data(), &metadata, observations.view())
+                .unwrap();
+            assert_eq!(loaded_done, done);
+            assert_eq!(loaded_params, params);
+            assert_eq!(
+                loaded_objectives.mapv(f64::to_bits),
+                objectives.mapv(f64::to_bits)
+            );
+        }
+        let hits = |sce: &Sce| {
+            let cache = sce.sce_params.scoring.cache.as_ref().unwrap();
+            cache.lock().unwrap().hits
+        };
+        assert_eq!(hits(&loaded), hits(&sce));
+    }
+}
@@ -0,0 +1,387 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use ndarray::{s, Array1, Array2, ArrayView1};
+use numpy::{
+    PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2, ToPyArray,
+};
+use pyo3::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::calibration::lhs;
+use crate::calibration::utils::{
+    build_model, evaluate_simulation, sort_population, Objective, N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata, SimulateFn};
+
+/// Evaluates the parameter sets in parallel, keeping them in order.
+///
+/// Returns the objectives (see `evaluate_simulation`) of each parameter set.
+pub fn evaluate_samples(
+    simulate: &SimulateFn,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    samples: &Array2<f64>,
+) -> Result<Array2<f64>, Error> {
+    let results: Vec<Result<Array1<f64>, Error>> = (0..samples.nrows())
+        .into_par_iter()
+        .map(|i| {
+            let simulation = simulate(samples.row(i), data, metadata)?;
+            evaluate_simulation(observations, simulation.view())
+        })
+        .collect();
+
+    let mut objectives = Array2::zeros((samples.nrows(), N_METRICS));
+    for (i, result) in results.into_iter().enumerate() {
+        objectives.row_mut(i).assign(&result?);
+    }
+    Ok(objectives)
+}
+
+/// Extended Fourier Amplitude Sensitivity Test (Saltelli et al., 1999).
+///
+/// Each parameter in turn oscillates at a high frequency along a search
+/// curve of `n_samples` points while the others oscillate at lower ones, so
+/// that `n_samples * n_params` evaluations are needed. The first order index
+/// of a parameter is the part of the variance at its frequency and its first
+/// `interference` harmonics, while its total index is the part not explained
+/// by the low frequencies of the other parameters.
+///
+/// Returns the first order and total indices of each parameter for each
+/// objective (see `evaluate_simulation`).
+pub fn fast(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    n_samples: usize,
+    interference: usize,
+    seed: u64,
+) -> Result<(Array2<f64>, Array2<f64>), Error> {
+    if interference == 0 || n_samples <= 4 * interference * interference {
+        return Err(Error::Sensitivity(format!(
+            "n_samples must be over 4 * interference^2 = {} (got {})",
+            4 * interference * interference,
+            n_samples
+        )));
+    }
+
+    let (simulate, params, bounds) = build_model(climate_model, snow_model)?;
+    let n_params = params.len();
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+    let (samples, max_frequency) = sample_fast(
+        n_samples,
+        interference,
+        bounds.column(0),
+        bounds.column(1),
+        &mut rng,
+    );
+    let objectives =
+        evaluate_samples(&simulate, data, metadata, observations, &samples)?;
+
+    let mut first_order = Array2::zeros((n_params, N_METRICS));
+    let mut total = Array2::zeros((n_params, N_METRICS));
+    for i in 0..n_params {
+        for k in 0..N_METRICS {
+            let values =
+                objectives.slice(s![i * n_samples..(i + 1) * n_samples, k]);
+            let variance = values.var(0.);
+            let first_order_variance = 2.
+                * (1..=interference)
+                    .map(|p| compute_power(values, p * max_frequency))
+                    .sum::<f64>();
+            let complementary_variance = 2.
+                * (1..=max_frequency / 2)
+                    .map(|f| compute_power(values, f))
+                    .sum::<f64>();
+            first_order[[i, k]] = first_order_variance / variance;
+            total[[i, k]] = 1. - complementary_variance / variance;
+        }
+    }
+
+    Ok((first_order, total))
+}
+
+#[pyfunction]
+#[pyo3(name = "fast")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    data,
+    metadata,
+    observations,
+    n_samples,
+    seed,
+    interference=4,
+))]
+pub fn py_fast<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    n_samples: usize,
+    seed: u64,
+    interference: usize,
+) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)> {
+    let (first_order, total) = fast(
+        climate_model,
+        snow_model,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        n_samples,
+        interference,
+        seed,
+    )?;
+    Ok((first_order.to_pyarray(py), total.to_pyarray(py)))
+}
+
+/// Identifiability of the parameters from evaluated parameter sets, such as
+/// a Latin hypercube sample or the `Sce` trace, for dotty plots.
+///
+/// Returns the parameter sets and their `objective` value, sorted from best
+/// to worst, and for each parameter the minimum and maximum over the best
+/// `top_fraction` of the sets along with the width of that range relative
+/// to the one over all sets, small widths denoting identifiable parameters.
+pub fn identifiability(
+    mut params: Array2<f64>,
+    mut objectives: Array2<f64>,
+    objective: Objective,
+    top_fraction: f64,
+) -> Result<(Array2<f64>, Array1<f64>, Array2<f64>), Error> {
+    if !(top_fraction > 0. && top_fraction <= 1.) {
+        return Err(Error::Sensitivity(format!(
+            "top_fraction must be in (0, 1] (got {})",
+            top_fraction
+        )));
+    }
+    if params.nrows() == 0 || params.nrows() != objectives.nrows() {
+        return Err(Error::Sensitivity(format!(
+            "expected as many parameter sets as objectives, and at least \
+             one (got {} and {})",
+            params.nrows(),
+            objectives.nrows()
+        )));
+    }
+
+    let (objective_idx, is_minimization) = objective.index();
+    sort_population(
+        &mut params,
+        &mut objectives,
+        objective_idx,
+        is_minimization,
+    );
+
+    let n_top = ((params.nrows() as f64 * top_fraction).ceil() as usize)
+        .clamp(1, params.nrows());
+    let top = params.slice(s![..n_top, ..]);
+    let mut statistics = Array2::zeros((params.ncols(), 3));
+    for j in 0..params.ncols() {
+        let (min, max) = column_range(top.column(j));
+        let (all_min, all_max) = column_range(params.column(j));
+        statistics[[j, 0]] = min;
+        statistics[[j, 1]] = max;
+        statistics[[j, 2]] = (max - min) / (all_max - all_min);
+    }
+
+    Ok((
+        params,
+        objectives.column(objective_idx).to_owned(),
+        statistics,
+    ))
+}
+
+/// Identifiability of the parameters from a Latin hypercube sample of
+/// `n_samples` parameter sets. See `identifiability`.
+pub fn dotty(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: Objective,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    n_samples: usize,
+    top_fraction: f64,
+    seed: u64,
+) -> Result<(Array2<f64>, Array1<f64>, Array2<f64>), Error> {
+    let (params, objectives) = lhs::run(
+        climate_model,
+        snow_model,
+        objective,
+        data,
+        metadata,
+        observations,
+        n_samples,
+        seed,
+    )?;
+    identifiability(params, objectives, objective, top_fraction)
+}
+
+#[pyfunction]
+#[pyo3(name = "identifiability")]
+#[pyo3(signature = (params, objectives, objective, top_fraction=0.1))]
+pub fn py_identifiability<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray2<'_, f64>,
+    objectives: PyReadonlyArray2<'_, f64>,
+    objective: &str,
+    top_fraction: f64,
+) -> PyResult<(
+    Bound<'py, PyArray2<f64>>,
+    Bound<'py, PyArray1<f64>>,
+    Bound<'py, PyArray2<f64>>,
+)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (params, values, statistics) = identifiability(
+        params.as_array().to_owned(),
+        objectives.as_array().to_owned(),
+        objective,
+        top_fraction,
+    )?;
+    Ok((
+        params.to_pyarray(py),
+        values.to_pyarray(py),
+        statistics.to_pyarray(py),
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "dotty")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    objective,
+    data,
+    metadata,
+    observations,
+    n_samples,
+    seed,
+    top_fraction=0.1,
+))]
+pub fn py_dotty<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    n_samples: usize,
+    seed: u64,
+    top_fraction: f64,
+) -> PyResult<(
+    Bound<'py, PyArray2<f64>>,
+    Bound<'py, PyArray1<f64>>,
+    Bound<'py, PyArray2<f64>>,
+)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let (params, values, statistics) = dotty(
+        climate_model,
+        snow_model,
+        objective,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations.as_array(),
+        n_samples,
+        top_fraction,
+        seed,
+    )?;
+    Ok((
+        params.to_pyarray(py),
+        values.to_pyarray(py),
+        statistics.to_pyarray(py),
+    ))
+}
+
+/// Samples the search curves of the extended FAST, one block of `n_samples`
+/// rows per parameter in which it has the highest frequency, with a random
+/// phase shift for each parameter.
+///
+/// Returns the samples and the highest frequency.
+fn sample_fast(
+    n_samples: usize,
+    interference: usize,
+    lower_bounds: ArrayView1<f64>,
+    upper_bounds: ArrayView1<f64>,
+    rng: &mut ChaCha8Rng,
+) -> (Array2<f64>, usize) {
+    let n_params = lower_bounds.len();
+    let max_frequency = (n_samples - 1) / (2 * interference);
+    let max_other_frequency = (max_frequency / (2 * interference)).max(1);
+
+    // the other frequencies are spread as much as possible below the
+    // highest one's harmonics
+    let other_frequencies: Vec<usize> = if n_params <= 2 {
+        vec![1; n_params.saturating_sub(1)]
+    } else if max_other_frequency >= n_params - 1 {
+        (0..n_params - 1)
+            .map(|j| 1 + (max_other_frequency - 1) * j / (n_params - 2))
+            .collect()
+    } else {
+        (0..n_params - 1)
+            .map(|j| j % max_other_frequency + 1)
+            .collect()
+    };
+
+    let mut samples = Array2::zeros((n_samples * n_params, n_params));
+    for i in 0..n_params {
+        let mut others = other_frequencies.iter();
+        for j in 0..n_params {
+            let frequency = if i == j {
+                max_frequency
+            } else {
+                *others.next().unwrap()
+            };
+            let phase = rng.random::<f64>() * 2. * PI;
+            let range = upper_bounds[j] - lower_bounds[j];
+            for t in 0..n_samples {
+                let s = 2. * PI * t as f64 / n_samples as f64;
+                let u = 0.5 + (frequency as f64 * s + phase).sin().asin() / PI;
+                samples[[i * n_samples + t, j]] = lower_bounds[j] + u * range;
+            }
+        }
+    }
+    (samples, max_frequency)
+}
+
+/// Power of the discrete Fourier transform of `values` at `frequency`,
+/// normalized so that summing it over all non-zero frequencies gives the
+/// variance.
+fn compute_power(values: ArrayView1<f64>, frequency: usize) -> f64 {
+    let n = values.len() as f64;
+    let (re, im) =
+        values
+            .iter()
+            .enumerate()
+            .fold((0., 0.), |(re, im), (t, value)| {
+                let angle = 2. * PI * (frequency * t) as f64 / n;
+                (re + value * angle.cos(), im - value * angle.sin())
+            });
+    (re * re + im * im) / (n * n)
+}
+
+fn column_range(values: ArrayView1<f64>) -> (f64, f64) {
+    values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+        (min.min(value), max.max(value))
+    })
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "sensitivity")?;
+    m.add_function(wrap_pyfunction!(py_fast, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_identifiability, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_dotty, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,203 @@
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use ndarray::{s, Array1, Array2, Array3, ArrayView1};
+use numpy::{PyArray2, PyArray3, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::calibration::sce::Sce;
+use crate::calibration::utils::{
+    evaluate_simulation, stack_rows, Objective, Scoring, N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+/// Splits `n_timesteps` into `n_folds` contiguous periods of equal length,
+/// the last one taking the remainder.
+pub fn split_folds(n_timesteps: usize, n_folds: usize) -> Vec<Range<usize>> {
+    let length = n_timesteps / n_folds.max(1);
+    (0..n_folds)
+        .map(|i| {
+            let end = if i + 1 == n_folds {
+                n_timesteps
+            } else {
+                (i + 1) * length
+            };
+            i * length..end
+        })
+        .collect()
+}
+
+/// Split-sample test (Klemeš, 1986): calibrates the model with `Sce` on each
+/// period in parallel, the observations of the other periods being masked,
+/// then evaluates each calibration on every period.
+///
+/// Returns the parameters calibrated on each period and the objectives (see
+/// `evaluate_simulation`) of the calibration on period `i` evaluated on
+/// period `j` at `[i, j, ..]`.
+pub fn run<'a>(
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: Objective,
+    data: Data<'a>,
+    metadata: &Metadata<'a>,
+    observations: ArrayView1<f64>,
+    periods: &[Range<usize>],
+    n_complexes: usize,
+    k_stop: usize,
+    p_convergence_threshold: f64,
+    geometric_range_threshold: f64,
+    max_evaluations: usize,
+    seed: u64,
+) -> Result<(Array2<f64>, Array3<f64>), Error> {
+    let n_timesteps = observations.len();
+    if let Some(period) = periods
+        .iter()
+        .find(|period| period.is_empty() || period.end > n_timesteps)
+    {
+        return Err(Error::Validation(format!(
+            "period {}..{} is empty or longer than the {} observations",
+            period.start, period.end, n_timesteps
+        )));
+    }
+
+    let results: Vec<Result<(Array1<f64>, Array2<f64>), Error>> = periods
+        .par_iter()
+        .map(|period| {
+            // the simulation covers the whole record so the stores carry over
+            let mut masked = Array1::from_elem(n_timesteps, f64::NAN);
+            masked
+                .slice_mut(s![period.clone()])
+                .assign(&observations.slice(s![period.clone()]));
+
+            let mut sce = Sce::new(
+                climate_model,
+                snow_model,
+                objective,
+                n_complexes,
+                k_stop,
+                p_convergence_threshold,
+                geometric_range_threshold,
+                max_evaluations,
+                seed,
+                None,
+                0,
+                0.,
+                vec![],
+                vec![],
+                None,
+                None,
+                Scoring::default(),
+            )?;
+            sce.init(data, metadata, masked.view())?;
+            let (params, simulation) = loop {
+                let (done, params, simulation, _) =
+                    sce.step(data, metadata, masked.view())?;
+                if done {
+                    break (params, simulation);
+                }
+            };
+
+            let objectives = periods
+                .iter()
+                .map(|other| {
+                    evaluate_simulation(
+                        observations.slice(s![other.clone()]),
+                        simulation.slice(s![other.clone()]),
+                    )
+                })
+                .collect::<Result<Vec<Array1<f64>>, Error>>()?;
+            Ok((params, stack_rows(&objectives, N_METRICS)))
+        })
+        .collect();
+
+    let n_periods = periods.len();
+    let mut params: Vec<Array1<f64>> = vec![];
+    let mut objectives = Array3::zeros((n_periods, n_periods, N_METRICS));
+    for (i, result) in results.into_iter().enumerate() {
+        let (period_params, period_objectives) = result?;
+        params.push(period_params);
+        objectives
+            .slice_mut(s![i, .., ..])
+            .assign(&period_objectives);
+    }
+
+    Ok((stack_rows(&params, 0), objectives))
+}
+
+#[pyfunction]
+#[pyo3(name = "run")]
+#[pyo3(signature = (
+    climate_model,
+    snow_model,
+    objective,
+    data,
+    metadata,
+    observations,
+    n_complexes,
+    k_stop,
+    p_convergence_threshold,
+    geometric_range_threshold,
+    max_evaluations,
+    seed,
+    split=None,
+    n_folds=None,
+))]
+pub fn py_run<'py>(
+    py: Python<'py>,
+    climate_model: &str,
+    snow_model: Option<&str>,
+    objective: &str,
+    data: PyData<'_>,
+    metadata: PyMetadata<'_>,
+    observations: PyReadonlyArray1<'_, f64>,
+    n_complexes: usize,
+    k_stop: usize,
+    p_convergence_threshold: f64,
+    geometric_range_threshold: f64,
+    max_evaluations: usize,
+    seed: u64,
+    split: Option<usize>,
+    n_folds: Option<usize>,
+) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray3<f64>>)> {
+    let objective = Objective::from_str(objective)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let observations = observations.as_array();
+    let n_timesteps = observations.len();
+    let periods = match (split, n_folds) {
+        (Some(split), None) => vec![0..split, split..n_timesteps],
+        (None, Some(n_folds)) if n_folds >= 2 => {
+            split_folds(n_timesteps, n_folds)
+        }
+        _ => {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "give either split or n_folds, with at least 2 folds",
+            ));
+        }
+    };
+    let (params, objectives) = run(
+        climate_model,
+        snow_model,
+        objective,
+        data.as_data()?,
+        &metadata.as_metadata(),
+        observations,
+        &periods,
+        n_complexes,
+        k_stop,
+        p_convergence_threshold,
+        geometric_range_threshold,
+        max_evaluations,
+        seed,
+    )?;
+    Ok((params.to_pyarray(py), objectives.to_pyarray(py)))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "split_sample")?;
+    m.add_function(wrap_pyfunction!(py_run, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,597 @@
+#![allow(clippy::type_complexity)]
+
+use std::str::FromStr;
+
+use ndarray::{s, Array1, Array2, ArrayView1, Axis};
+use ndarray_rand::rand_distr::{Normal, Uniform};
+use ndarray_rand::RandomExt;
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::calibration::lhs::evaluate_population;
+use crate::calibration::utils::{
+    build_model, evaluate_metrics, sample_latin_hypercube, stack_rows,
+    CalibrationParams, Objective, Scoring, N_METRICS,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+// weights of the surrogate value against the distance to the evaluated
+// points, cycled through to alternate between exploitation and exploration
+const WEIGHTS: [f64; 4] = [0.3, 0.5, 0.8, 0.95];
+// standard deviation of the perturbations of the best point, relative to
+// the parameter ranges
+const PERTURBATION: f64 = 0.1;
+// minimal distance to the evaluated points, relative to the parameter
+// ranges, under which a candidate is considered a duplicate
+const MIN_DISTANCE: f64 = 1e-6;
+
+/// Cubic radial basis function interpolant with a linear tail.
+struct Rbf {
+    centers: Array2<f64>,
+    weights: Array1<f64>,
+    tail: Array1<f64>,
+}
+
+impl Rbf {
+    /// Returns None when the interpolation system is singular.
+    fn fit(points: &Array2<f64>, values: &Array1<f64>) -> Option<Self> {
+        let (n, d) = points.dim();
+        let mut a = Array2::zeros((n + d + 1, n + d + 1));
+        let mut b = Array1::zeros(n + d + 1);
+        for i in 0..n {
+            for j in 0..n {
+                a[[i, j]] = distance(points.row(i), points.row(j)).powi(3);
+            }
+            // small regularization for nearly duplicated points
+            a[[i, i]] += 1e-8;
+            a[[i, n]] = 1.;
+            a[[n, i]] = 1.;
+            for k in 0..d {
+                a[[i, n + 1 + k]] = points[[i, k]];
+                a[[n + 1 + k, i]] = points[[i, k]];
+            }
+            b[i] = values[i];
+        }
+        let solution = solve(a, b)?;
+        Some(Rbf {
+            centers: points.clone(),
+            weights: solution.slice(s![..n]).to_owned(),
+            tail: solution.slice(s![n..]).to_owned(),
+        })
+    }
+
+    fn predict(&self, point: ArrayView1<f64>) -> f64 {
+        let radial: f64 = self
+            .centers
+            .rows()
+            .into_iter()
+            .zip(&self.weights)
+            .map(|(center, w)| w * distance(point, center).powi(3))
+            .sum();
+        radial + self.tail[0] + point.dot(&self.tail.slice(s![1..]))
+    }
+}
+
+/// Stochastic RBF search (Regis and Shoemaker, 2007) on the unit hypercube.
+///
+/// Each proposal fits the surrogate to the evaluated points and picks, among
+/// perturbations of the best point and uniform points, the candidate with
+/// the best weighted combination of surrogate value and distance to the
+/// evaluated points. A uniform point is proposed when the surrogate can't be
+/// fitted or all candidates duplicate evaluated points.
+struct SurrogateSearch {
+    lower_bounds: Array1<f64>,
+    upper_bounds: Array1<f64>,
+    points: Vec<Array1<f64>>,
+    values: Vec<f64>,
+    n_candidates: usize,
+    n_proposals: usize,
+}
+
+impl SurrogateSearch {
+    fn new(lower_bounds: Array1<f64>, upper_bounds: Array1<f64>) -> Self {
+        let n_candidates = (100 * lower_bounds.len()).min(5000);
+        SurrogateSearch {
+            lower_bounds,
+            upper_bounds,
+            points: vec![],
+            values: vec![],
+            n_candidates,
+            n_proposals: 0,
+        }
+    }
+
+    fn add(&mut self, point: ArrayView1<f64>, value: f64) {
+        let range = &self.upper_bounds - &self.lower_bounds;
+        self.points.push((&point - &self.lower_bounds) / range);
+        // failed evaluations are the worst
+        self.values
+            .push(if value.is_nan() { f64::INFINITY } else { value });
+    }
+
+    fn best(&self) -> (Array1<f64>, f64) {
+        let best = (0..self.values.len())
+            .min_by(|&a, &b| self.values[a].total_cmp(&self.values[b]))
+            .unwrap();
+        (self.to_bounds(self.points[best].view()), self.values[best])
+    }
+
+    fn propose(&mut self, rng: &mut ChaCha8Rng) -> Array1<f64> {
+        let n_params = self.lower_bounds.len();
+        let weight = WEIGHTS[self.n_proposals % WEIGHTS.len()];
+        self.n_proposals += 1;
+
+        let points = stack_rows(&self.points, n_params);
+        let surrogate = Rbf::fit(&points, &self.clip_values());
+
+        let (best, _) = self.best();
+        let best = (&best - &self.lower_bounds)
+            / (&self.upper_bounds - &self.lower_bounds);
+        let n_perturbed = self.n_candidates / 2;
+        let perturbations: Array2<f64> = Array2::random_using(
+            (n_perturbed, n_params),
+            Normal::new(0., PERTURBATION).unwrap(),
+            rng,
+        );
+        let perturbed = (perturbations + &best).mapv(|x| x.clamp(0., 1.));
+        let uniform: Array2<f64> = Array2::random_using(
+            (self.n_candidates - n_perturbed, n_params),
+            Uniform::new(0., 1.).unwrap(),
+            rng,
+        );
+        let candidates =
+            ndarray::concatenate(Axis(0), &[perturbed.view(), uniform.view()])
+                .unwrap();
+
+        let distances: Vec<f64> = candidates
+            .rows()
+            .into_iter()
+            .map(|c| {
+                points
+                    .rows()
+                    .into_iter()
+                    .map(|p| distance(c, p))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+        let valid: Vec<usize> = (0..candidates.nrows())
+            .filter(|&i| distances[i] > MIN_DISTANCE)
+            .collect();
+
+        let Some(surrogate) = surrogate.filter(|_| !valid.is_empty()) else {
+            let point = Array1::random_using(
+                n_params,
+                Uniform::new(0., 1.).unwrap(),
+                rng,
+            );
+            return self.to_bounds(point.view());
+        };
+
+        let predictions: Vec<f64> = valid
+            .iter()
+            .map(|&i| surrogate.predict(candidates.row(i)))
+            .collect();
+        let valid_distances: Vec<f64> =
+            valid.iter().map(|&i| distances[i]).collect();
+        let prediction_scores = normalize(&predictions);
+        // far candidates score best
+        let distance_scores: Vec<f64> =
+            normalize(&valid_distances).iter().map(|x| 1. - x).collect();
+        let chosen = (0..valid.len())
+            .min_by(|&a, &b| {
+                let score_a = weight * prediction_scores[a]
+                    + (1. - weight) * distance_scores[a];
+                let score_b = weight * prediction_scores[b]
+                    + (1. - weight) * distance_scores[b];
+                score_a.total_cmp(&score_b)
+            })
+            .unwrap();
+        self.to_bounds(candidates.row(valid[chosen]))
+    }
+
+    /// Values above the median are replaced by it so that a few very bad
+    /// points don't flatten the surrogate elsewhere, failed evaluations
+    /// taking the worst finite value.
+    fn clip_values(&self) -> Array1<f64> {
+        let mut sorted = self.values.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = sorted[sorted.len() / 2];
+        let worst = sorted
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .fold(f64::NEG_INFINITY, f64::max)
+            .min(median);
+        let worst = if worst.is_finite() { worst } else { 0. };
+        Array1::from_iter(self.values.iter().map(|&v| {
+            if v.is_finite() {
+                v.min(median)
+            } else {
+                worst
+            }
+        }))
+    }
+
+    fn to_bounds(&self, point: ArrayView1<f64>) -> Array1<f64> {
+        &point * &(&self.upper_bounds - &self.lower_bounds)
+            + &self.lower_bounds
+    }
+}
+
+struct SurrogateParams {
+    pub search: SurrogateSearch,
+    pub objectives: Array1<f64>,
+    pub n_calls: usize,
+    pub n_initial: usize,
+    pub max_evaluations: usize,
+}
+
+/// Surrogate-assisted calibration with a radial basis function metamodel.
+///
+/// The surrogate is fitted to an initial Latin hypercube sample, then each
+/// iteration evaluates the model at the candidate proposed by the surrogate
+/// and refits it. Each step runs as many iterations as there are parameters.
+#[pyclass(module = "hydro_rs.calibration.surrogate", unsendable)]
+pub struct Surrogate {
+    calibration_params: CalibrationParams,
+    surrogate_params: SurrogateParams,
+}
+
+impl Surrogate {
+    pub fn new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: Objective,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        let (simulate, params, bounds) =
+            build_model(climate_model, snow_model)?;
+
+        let lower_bounds: Array1<f64> = bounds.column(0).to_owned();
+        let upper_bounds: Array1<f64> = bounds.column(1).to_owned();
+
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+
+        let n_initial = 2 * (params.len() + 1);
+        let search =
+            SurrogateSearch::new(lower_bounds.clone(), upper_bounds.clone());
+
+        let calibration_params = CalibrationParams {
+            params,
+            simulate,
+            lower_bounds,
+            upper_bounds,
+            objective,
+            rng,
+            done: false,
+        };
+        let surrogate_params = SurrogateParams {
+            search,
+            objectives: Array1::from_elem(N_METRICS, f64::NAN),
+            n_calls: 0,
+            n_initial,
+            max_evaluations,
+        };
+
+        Ok(Surrogate {
+            calibration_params,
+            surrogate_params,
+        })
+    }
+
+    pub fn init<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(), Error> {
+        let population = sample_latin_hypercube(
+            self.surrogate_params.n_initial,
+            self.calibration_params.lower_bounds.view(),
+            self.calibration_params.upper_bounds.view(),
+            &mut self.calibration_params.rng,
+        );
+        let (population, objectives) = evaluate_population(
+            &self.calibration_params.simulate,
+            data,
+            metadata,
+            observations,
+            population,
+            self.calibration_params.objective,
+            &Scoring {
+                objective: Some(self.calibration_params.objective),
+                ..Scoring::default()
+            },
+        )?;
+
+        for (point, point_objectives) in
+            population.rows().into_iter().zip(objectives.rows())
+        {
+            let value = self.to_value(point_objectives);
+            self.surrogate_params.search.add(point, value);
+        }
+        self.calibration_params.params = population.row(0).to_owned();
+        self.surrogate_params.objectives = objectives.row(0).to_owned();
+        self.surrogate_params.n_calls = population.nrows();
+
+        Ok(())
+    }
+
+    pub fn step<'a>(
+        &mut self,
+        data: Data<'a>,
+        metadata: &Metadata<'a>,
+        observations: ArrayView1<f64>,
+    ) -> Result<(bool, Array1<f64>, Array1<f64>, Array1<f64>), Error> {
+        if !self.calibration_params.done {
+            let n_params = self.calibration_params.params.len();
+
+            for _ in 0..n_params {
+                if self.surrogate_params.n_calls
+                    >= self.surrogate_params.max_evaluations
+                {
+                    break;
+                }
+                let candidate = self
+                    .surrogate_params
+                    .search
+                    .propose(&mut self.calibration_params.rng);
+                let simulation = (self.calibration_params.simulate)(
+                    candidate.view(),
+                    data,
+                    metadata,
+                )?;
+                let objectives = evaluate_metrics(
+                    observations,
+                    simulation.view(),
+                    Some(self.calibration_params.objective),
+                )?;
+                self.surrogate_params.n_calls += 1;
+
+                let value = self.to_value(objectives.view());
+                self.surrogate_params.search.add(candidate.view(), value);
+                let best =
+                    self.to_value(self.surrogate_params.objectives.view());
+                if best.is_nan() || value < best {
+                    self.calibration_params.params = candidate;
+                    self.surrogate_params.objectives = objectives;
+                }
+            }
+
+            self.calibration_params.done = self.surrogate_params.n_calls
+                >= self.surrogate_params.max_evaluations;
+        }
+
+        let best_simulation = (self.calibration_params.simulate)(
+            self.calibration_params.params.view(),
+            data,
+            metadata,
+        )?;
+
+        Ok((
+            self.calibration_params.done,
+            self.calibration_params.params.clone(),
+            best_simulation,
+            self.surrogate_params.objectives.clone(),
+        ))
+    }
+
+    /// Objective to minimize.
+    fn to_value(&self, objectives: ArrayView1<f64>) -> f64 {
+        let (objective_idx, is_minimization) =
+            self.calibration_params.objective.index();
+        if is_minimization {
+            objectives[objective_idx]
+        } else {
+            -objectives[objective_idx]
+        }
+    }
+}
+
+#[pymethods]
+impl Surrogate {
+    #[new]
+    pub fn py_new(
+        climate_model: &str,
+        snow_model: Option<&str>,
+        objective: &str,
+        max_evaluations: usize,
+        seed: u64,
+    ) -> PyResult<Self> {
+        let objective = Objective::from_str(objective)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Surrogate::new(
+            climate_model,
+            snow_model,
+            objective,
+            max_evaluations,
+            seed,
+        )
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "init")]
+    pub fn py_init(
+        &mut self,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<()> {
+        self.init(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok(())
+    }
+
+    #[pyo3(name = "step")]
+    pub fn py_step<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: PyData<'_>,
+        metadata: PyMetadata<'_>,
+        observations: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<(
+        bool,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+        Bound<'py, PyArray1<f64>>,
+    )> {
+        let (done, best_params, simulation, objectives) = self.step(
+            data.as_data()?,
+            &metadata.as_metadata(),
+            observations.as_array(),
+        )?;
+        Ok((
+            done,
+            best_params.to_pyarray(py),
+            simulation.to_pyarray(py),
+            objectives.to_pyarray(py),
+        ))
+    }
+}
+
+/// Minimizes a Python `objective`, called with an array of parameters, with
+/// the surrogate search, starting from a Latin hypercube sample of
+/// `n_initial` points (2 * (n_params + 1) by default).
+///
+/// Returns the best parameters and value, and all the evaluated points and
+/// values.
+#[pyfunction]
+#[pyo3(signature = (
+    objective,
+    lower_bounds,
+    upper_bounds,
+    max_evaluations,
+    seed,
+    n_initial=None,
+))]
+pub fn minimize<'py>(
+    py: Python<'py>,
+    objective: Bound<'py, PyAny>,
+    lower_bounds: PyReadonlyArray1<'_, f64>,
+    upper_bounds: PyReadonlyArray1<'_, f64>,
+    max_evaluations: usize,
+    seed: u64,
+    n_initial: Option<usize>,
+) -> PyResult<(
+    Bound<'py, PyArray1<f64>>,
+    f64,
+    Bound<'py, PyArray2<f64>>,
+    Bound<'py, PyArray1<f64>>,
+)> {
+    let lower_bounds = lower_bounds.as_array().to_owned();
+    let upper_bounds = upper_bounds.as_array().to_owned();
+    if lower_bounds.len() != upper_bounds.len()
+        || lower_bounds.iter().zip(&upper_bounds).any(|(l, u)| l >= u)
+    {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "The lower bounds must be below the upper bounds.",
+        ));
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let n_initial = n_initial
+        .unwrap_or(2 * (lower_bounds.len() + 1))
+        .min(max_evaluations)
+        .max(1);
+    let mut search =
+        SurrogateSearch::new(lower_bounds.clone(), upper_bounds.clone());
+    let mut evaluated: Vec<Array1<f64>> = vec![];
+
+    let initial = sample_latin_hypercube(
+        n_initial,
+        lower_bounds.view(),
+        upper_bounds.view(),
+        &mut rng,
+    );
+    for point in initial.rows() {
+        let value: f64 =
+            objective.call1((point.to_pyarray(py),))?.extract()?;
+        search.add(point, value);
+        evaluated.push(point.to_owned());
+    }
+    for _ in n_initial..max_evaluations {
+        let point = search.propose(&mut rng);
+        let value: f64 =
+            objective.call1((point.to_pyarray(py),))?.extract()?;
+        search.add(point.view(), value);
+        evaluated.push(point);
+    }
+
+    let (best_params, best_value) = search.best();
+    let points = stack_rows(&evaluated, lower_bounds.len());
+    let values = Array1::from_vec(search.values.clone());
+    Ok((
+        best_params.to_pyarray(py),
+        best_value,
+        points.to_pyarray(py),
+        values.to_pyarray(py),
+    ))
+}
+
+fn distance(a: ArrayView1<f64>, b: ArrayView1<f64>) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Scales the values to [0, 1], all being 1 when they are equal.
+fn normalize(values: &[f64]) -> Vec<f64> {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if max - min > 0. {
+        values.iter().map(|v| (v - min) / (max - min)).collect()
+    } else {
+        vec![1.; values.len()]
+    }
+}
+
+/// Solves the linear system with Gaussian elimination and partial pivoting,
+/// returning None when it is singular.
+fn solve(mut a: Array2<f64>, mut b: Array1<f64>) -> Option<Array1<f64>> {
+    let n = b.len();
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&i, &j| a[[i, k]].abs().total_cmp(&a[[j, k]].abs()))
+            .unwrap();
+        if a[[pivot, k]].abs() < 1e-12 || !a[[pivot, k]].is_finite() {
+            return None;
+        }
+        if pivot != k {
+            for j in 0..n {
+                a.swap([k, j], [pivot, j]);
+            }
+            b.swap(k, pivot);
+        }
+        for i in (k + 1)..n {
+            let factor = a[[i, k]] / a[[k, k]];
+            if factor == 0. {
+                continue;
+            }
+            for j in k..n {
+                a[[i, j]] -= factor * a[[k, j]];
+            }
+            b[i] -= factor * b[k];
+        }
+    }
+    let mut x = Array1::zeros(n);
+    for k in (0..n).rev() {
+        let sum: f64 = ((k + 1)..n).map(|j| a[[k, j]] * x[j]).sum();
+        x[k] = (b[k] - sum) / a[[k, k]];
+    }
+    Some(x)
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "surrogate")?;
+    m.add_class::<Surrogate>()?;
+    m.add_function(wrap_pyfunction!(minimize, &m)?)?;
+    Ok(m)
+}
@@ -1,8 +1,35 @@
-use ndarray::Array1;
+use ndarray::{s, Array1, Array2, ArrayView1, Axis, CowArray, Ix1};
+use numpy::ToPyArray;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
+use std::sync::Mutex;
 
-use crate::model::SimulateFn;
+use crate::climate;
+use crate::metrics::{
+    calculate_dtw, calculate_kge, calculate_kge_2012,
+    calculate_kge_components, calculate_log_nse, calculate_mae, calculate_nse,
+    calculate_nse_inv, calculate_nse_sqrt, calculate_pbias, calculate_rmse,
+    calculate_volumetric_efficiency, calculate_weighted_dtw,
+    calculate_weighted_kge, calculate_weighted_kge_2012,
+    calculate_weighted_kge_components, calculate_weighted_log_nse,
+    calculate_weighted_mae, calculate_weighted_nse,
+    calculate_weighted_nse_sqrt, calculate_weighted_pbias,
+    calculate_weighted_rmse, calculate_weighted_volumetric_efficiency,
+    default_epsilon, find_events, month, offset_pair, DTW_WINDOW,
+};
+use crate::model::{
+    compose_init, compose_simulate, Data, Error, Metadata, SimulateFn,
+};
+use crate::snow;
+use crate::snow::utils::SnowStatesFn;
 
 pub struct CalibrationParams {
     pub params: Array1<f64>,
@@ -14,11 +41,98 @@ pub struct CalibrationParams {
     pub done: bool,
 }
 
+/// Number of metrics returned by `evaluate_simulation`.
+pub const N_METRICS: usize = 15;
+
+/// How each metric of `evaluate_simulation` improves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sense {
+    Minimized,
+    Maximized,
+    // best at zero, e.g. a bias
+    Zero,
+    // best at one, e.g. a ratio
+    One,
+}
+
+pub const SENSES: [Sense; N_METRICS] = [
+    Sense::Minimized,
+    Sense::Maximized,
+    Sense::Maximized,
+    Sense::Maximized,
+    Sense::Maximized,
+    Sense::Maximized,
+    Sense::Maximized,
+    Sense::Zero,
+    Sense::Minimized,
+    Sense::Maximized,
+    Sense::Maximized,
+    Sense::Maximized,
+    Sense::One,
+    Sense::One,
+    Sense::Minimized,
+];
+
+/// Worsens the metrics of `evaluate_simulation` by `penalty`, increasing the
+/// errors and decreasing the efficiencies, the biases and ratios being left
+/// as is.
+pub fn penalize(values: &mut Array1<f64>, penalty: f64) {
+    for (value, sense) in values.iter_mut().zip(SENSES) {
+        match sense {
+            Sense::Minimized => *value += penalty,
+            Sense::Maximized => *value -= penalty,
+            Sense::Zero | Sense::One => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Objective {
     Rmse,
     Nse,
     Kge,
+    /// NSE and KGE on the inverse discharge, focusing on the low flows.
+    NseInv,
+    KgeInv,
+    /// Modified KGE (Kling et al., 2012).
+    Kge2012,
+    /// NSE on the log of the discharge, focusing on the low flows.
+    LogNse,
+    Mae,
+    /// Volumetric efficiency, focusing on the volumes.
+    Ve,
+    /// NSE on the square root of the discharge, balancing high and low
+    /// flows.
+    NseSqrt,
+    /// Mean absolute error after a dynamic time warping of at most
+    /// `DTW_WINDOW` timesteps, forgiving small timing errors.
+    Dtw,
+    /// Python function `(observations, simulation) -> float`, evaluated
+    /// after the metrics by `evaluate_params`.
+    Custom {
+        minimize: bool,
+    },
+}
+
+impl Objective {
+    /// Returns the index of the objective in the output of
+    /// `evaluate_simulation` and whether it must be minimized.
+    pub fn index(&self) -> (usize, bool) {
+        match self {
+            Objective::Rmse => (0, true),
+            Objective::Nse => (1, false),
+            Objective::Kge => (2, false),
+            Objective::NseInv => (3, false),
+            Objective::KgeInv => (4, false),
+            Objective::Kge2012 => (5, false),
+            Objective::LogNse => (6, false),
+            Objective::Mae => (8, true),
+            Objective::Ve => (9, false),
+            Objective::NseSqrt => (10, false),
+            Objective::Dtw => (14, true),
+            Objective::Custom { minimize } => (N_METRICS, *minimize),
+        }
+    }
 }
 
 impl FromStr for Objective {
@@ -29,10 +143,1664 @@ impl FromStr for Objective {
             "rmse" => Ok(Self::Rmse),
             "nse" => Ok(Self::Nse),
             "kge" => Ok(Self::Kge),
+            "nse_inv" => Ok(Self::NseInv),
+            "kge_inv" => Ok(Self::KgeInv),
+            "kge_2012" => Ok(Self::Kge2012),
+            "log_nse" => Ok(Self::LogNse),
+            "mae" => Ok(Self::Mae),
+            "ve" => Ok(Self::Ve),
+            "nse_sqrt" => Ok(Self::NseSqrt),
+            "dtw" => Ok(Self::Dtw),
             _ => Err(format!(
-                "Unknown objective function '{}'. Valid options: nse, kge, rmse",
+                "Unknown objective function '{}'. Valid options: nse, kge, \
+                 rmse, nse_inv, kge_inv, kge_2012, log_nse, mae, ve, \
+                 nse_sqrt, dtw",
                 s
             )),
         }
     }
 }
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Objective::Rmse => write!(f, "rmse"),
+            Objective::Nse => write!(f, "nse"),
+            Objective::Kge => write!(f, "kge"),
+            Objective::NseInv => write!(f, "nse_inv"),
+            Objective::KgeInv => write!(f, "kge_inv"),
+            Objective::Kge2012 => write!(f, "kge_2012"),
+            Objective::LogNse => write!(f, "log_nse"),
+            Objective::Mae => write!(f, "mae"),
+            Objective::Ve => write!(f, "ve"),
+            Objective::NseSqrt => write!(f, "nse_sqrt"),
+            Objective::Dtw => write!(f, "dtw"),
+            Objective::Custom { .. } => write!(f, "custom"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Transformation {
+    #[default]
+    None,
+    Log,
+    Sqrt,
+    BoxCox(f64),
+}
+
+impl FromStr for Transformation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        if let Some(lambda) = s
+            .strip_prefix("boxcox(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            return lambda
+                .trim()
+                .parse()
+                .map(Self::BoxCox)
+                .map_err(|_| format!("Invalid Box-Cox lambda '{}'", lambda));
+        }
+        match s.as_str() {
+            "none" => Ok(Self::None),
+            "log" => Ok(Self::Log),
+            "sqrt" => Ok(Self::Sqrt),
+            _ => Err(format!(
+                "Unknown transformation '{}'. Valid options: none, log, sqrt, \
+                 boxcox(lambda)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Transformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transformation::None => write!(f, "none"),
+            Transformation::Log => write!(f, "log"),
+            Transformation::Sqrt => write!(f, "sqrt"),
+            Transformation::BoxCox(lambda) => write!(f, "boxcox({})", lambda),
+        }
+    }
+}
+
+impl Transformation {
+    /// Transforms the discharge, the log and Box-Cox being offset by a
+    /// hundredth of the mean observed discharge to handle zero flows.
+    pub fn apply(&self, values: ArrayView1<f64>, offset: f64) -> Array1<f64> {
+        match self {
+            Transformation::None => values.to_owned(),
+            Transformation::Log => {
+                values.mapv(|x| (non_negative(x) + offset).ln())
+            }
+            Transformation::Sqrt => values.mapv(|x| non_negative(x).sqrt()),
+            Transformation::BoxCox(lambda) if *lambda == 0. => {
+                values.mapv(|x| (non_negative(x) + offset).ln())
+            }
+            Transformation::BoxCox(lambda) => values.mapv(|x| {
+                ((non_negative(x) + offset).powf(*lambda) - 1.) / lambda
+            }),
+        }
+    }
+
+    /// Transforms the observations and simulations alike, the offset coming
+    /// from the observations.
+    pub fn apply_pair(
+        &self,
+        observations: ArrayView1<f64>,
+        simulations: ArrayView1<f64>,
+    ) -> (Array1<f64>, Array1<f64>) {
+        let offset = default_epsilon(observations);
+        (
+            self.apply(observations, offset),
+            self.apply(simulations, offset),
+        )
+    }
+}
+
+/// Weight of each timestep's error as a function of the observed discharge,
+/// `Inverse` favouring low flows and `Power` with a positive exponent high
+/// flows.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum FlowWeighting {
+    #[default]
+    None,
+    Inverse(f64),
+    Power(f64),
+}
+
+impl FromStr for FlowWeighting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let parse = |prefix: &str, name: &str| {
+            s.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(')'))
+                .map(|value| {
+                    value.trim().parse::<f64>().map_err(|_| {
+                        format!("Invalid flow weighting {} '{}'", name, value)
+                    })
+                })
+        };
+        if let Some(eps) = parse("inverse(", "epsilon") {
+            return eps.map(Self::Inverse);
+        }
+        if let Some(exponent) = parse("power(", "exponent") {
+            return exponent.map(Self::Power);
+        }
+        match s.as_str() {
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "Unknown flow weighting '{}'. Valid options: none, \
+                 inverse(epsilon), power(exponent)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FlowWeighting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlowWeighting::None => write!(f, "none"),
+            FlowWeighting::Inverse(eps) => write!(f, "inverse({})", eps),
+            FlowWeighting::Power(exponent) => {
+                write!(f, "power({})", exponent)
+            }
+        }
+    }
+}
+
+impl FlowWeighting {
+    /// Weights of the timesteps, none meaning they all count the same.
+    pub fn weights(
+        &self,
+        observations: ArrayView1<f64>,
+    ) -> Option<Array1<f64>> {
+        match self {
+            FlowWeighting::None => None,
+            FlowWeighting::Inverse(eps) => {
+                Some(observations.mapv(|q| 1. / (non_negative(q) + eps)))
+            }
+            FlowWeighting::Power(exponent) => {
+                Some(observations.mapv(|q| non_negative(q).powf(*exponent)))
+            }
+        }
+    }
+}
+
+// unlike f64::max, keeps the missing values (NaN)
+fn non_negative(x: f64) -> f64 {
+    if x < 0. {
+        0.
+    } else {
+        x
+    }
+}
+
+/// Objective computed on transformed discharge, used by the multi-objective
+/// optimizers.
+#[derive(Debug, Clone, Copy)]
+pub struct Criterion {
+    pub objective: Objective,
+    pub transformation: Transformation,
+}
+
+impl Criterion {
+    pub fn from_strs(
+        objective: &str,
+        transformation: &str,
+    ) -> Result<Self, String> {
+        Ok(Criterion {
+            objective: Objective::from_str(objective)?,
+            transformation: Transformation::from_str(transformation)?,
+        })
+    }
+
+    /// Returns the value of the criterion, negated when it must be
+    /// maximized so all criteria are minimized.
+    pub fn evaluate(
+        &self,
+        observations: ArrayView1<f64>,
+        simulations: ArrayView1<f64>,
+    ) -> Result<f64, Error> {
+        let (observations, simulations) =
+            self.transformation.apply_pair(observations, simulations);
+        let value = match self.objective {
+            Objective::Rmse => {
+                calculate_rmse(observations.view(), simulations.view())?
+            }
+            Objective::Nse => {
+                -calculate_nse(observations.view(), simulations.view())?
+            }
+            Objective::Kge => {
+                -calculate_kge(observations.view(), simulations.view())?
+            }
+            Objective::NseInv => -calculate_nse_inv(
+                observations.view(),
+                simulations.view(),
+                None,
+            )?,
+            Objective::KgeInv => {
+                let (observations, simulations) = offset_pair(
+                    observations.view(),
+                    simulations.view(),
+                    None,
+                    f64::recip,
+                )?;
+                -calculate_kge(observations.view(), simulations.view())?
+            }
+            Objective::Kge2012 => {
+                -calculate_kge_2012(observations.view(), simulations.view())?
+            }
+            Objective::LogNse => -calculate_log_nse(
+                observations.view(),
+                simulations.view(),
+                None,
+            )?,
+            Objective::Mae => {
+                calculate_mae(observations.view(), simulations.view())?
+            }
+            Objective::Ve => -calculate_volumetric_efficiency(
+                observations.view(),
+                simulations.view(),
+            )?,
+            Objective::NseSqrt => {
+                -calculate_nse_sqrt(observations.view(), simulations.view())?
+            }
+            Objective::Dtw => {
+                calculate_dtw(
+                    observations.view(),
+                    simulations.view(),
+                    DTW_WINDOW,
+                )?
+                .0
+            }
+            Objective::Custom { .. } => {
+                unreachable!("criteria are built from metric names")
+            }
+        };
+        Ok(if value.is_nan() { f64::INFINITY } else { value })
+    }
+
+    /// Converts a minimized value back to the value of the objective.
+    pub fn to_objective(self, value: f64) -> f64 {
+        match self.objective {
+            Objective::Rmse | Objective::Mae | Objective::Dtw => value,
+            Objective::Nse
+            | Objective::Kge
+            | Objective::NseInv
+            | Objective::KgeInv
+            | Objective::Kge2012
+            | Objective::LogNse
+            | Objective::Ve
+            | Objective::NseSqrt => -value,
+            Objective::Custom { minimize } => {
+                if minimize {
+                    value
+                } else {
+                    -value
+                }
+            }
+        }
+    }
+}
+
+pub fn parse_criteria(
+    criteria: &[(String, String)],
+) -> Result<Vec<Criterion>, String> {
+    criteria
+        .iter()
+        .map(|(objective, transformation)| {
+            Criterion::from_strs(objective, transformation)
+        })
+        .collect()
+}
+
+pub fn evaluate_criteria(
+    criteria: &[Criterion],
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<Array1<f64>, Error> {
+    criteria
+        .iter()
+        .map(|c| c.evaluate(observations, simulations))
+        .collect()
+}
+
+/// Values of the criteria for each point of the population, in parallel.
+pub fn evaluate_population_criteria(
+    simulate: &SimulateFn,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    population: &Array2<f64>,
+    criteria: &[Criterion],
+) -> Result<Array2<f64>, Error> {
+    let results: Vec<Result<Array1<f64>, Error>> = (0..population.nrows())
+        .into_par_iter()
+        .map(|i| {
+            let simulation = simulate(population.row(i), data, metadata)?;
+            evaluate_criteria(criteria, observations, simulation.view())
+        })
+        .collect();
+    let mut values = Array2::zeros((population.nrows(), criteria.len()));
+    for (i, result) in results.into_iter().enumerate() {
+        values.row_mut(i).assign(&result?);
+    }
+    Ok(values)
+}
+
+/// Whether `a` Pareto-dominates `b`, all objectives being minimized.
+pub fn dominates(a: ArrayView1<f64>, b: ArrayView1<f64>) -> bool {
+    a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y)
+}
+
+/// Fast non-dominated sorting (Deb et al., 2002), returning the indices of
+/// the points in each successive front.
+pub fn sort_non_dominated(objectives: &Array2<f64>) -> Vec<Vec<usize>> {
+    let n = objectives.nrows();
+    let mut dominated_by: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut n_dominating: Vec<usize> = vec![0; n];
+    let mut fronts: Vec<Vec<usize>> = vec![vec![]];
+
+    for i in 0..n {
+        for j in 0..n {
+            if dominates(objectives.row(i), objectives.row(j)) {
+                dominated_by[i].push(j);
+            } else if dominates(objectives.row(j), objectives.row(i)) {
+                n_dominating[i] += 1;
+            }
+        }
+        if n_dominating[i] == 0 {
+            fronts[0].push(i);
+        }
+    }
+
+    let mut k = 0;
+    while !fronts[k].is_empty() {
+        let mut next: Vec<usize> = vec![];
+        for &i in &fronts[k] {
+            for &j in &dominated_by[i] {
+                n_dominating[j] -= 1;
+                if n_dominating[j] == 0 {
+                    next.push(j);
+                }
+            }
+        }
+        fronts.push(next);
+        k += 1;
+    }
+    fronts.pop();
+    fronts
+}
+
+/// Crowding distance of each point of a front, the extremes of each
+/// objective getting an infinite distance.
+pub fn compute_crowding_distances(
+    objectives: &Array2<f64>,
+    front: &[usize],
+) -> Vec<f64> {
+    let n = front.len();
+    let mut distances = vec![0.; n];
+    if n <= 2 {
+        return vec![f64::INFINITY; n];
+    }
+    for m in 0..objectives.ncols() {
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| {
+            objectives[[front[a], m]].total_cmp(&objectives[[front[b], m]])
+        });
+        let min = objectives[[front[order[0]], m]];
+        let max = objectives[[front[order[n - 1]], m]];
+        distances[order[0]] = f64::INFINITY;
+        distances[order[n - 1]] = f64::INFINITY;
+        if max - min <= 0. || !(max - min).is_finite() {
+            continue;
+        }
+        for k in 1..n - 1 {
+            distances[order[k]] += (objectives[[front[order[k + 1]], m]]
+                - objectives[[front[order[k - 1]], m]])
+                / (max - min);
+        }
+    }
+    distances
+}
+
+/// Indices of the best `n` points by front, then by crowding distance, with
+/// their rank and crowding distance.
+pub fn select_by_front(
+    values: &Array2<f64>,
+    n: usize,
+) -> Vec<(usize, usize, f64)> {
+    let mut selected: Vec<(usize, usize, f64)> = vec![];
+
+    for (rank, front) in sort_non_dominated(values).iter().enumerate() {
+        let distances = compute_crowding_distances(values, front);
+        let mut front: Vec<(usize, usize, f64)> = front
+            .iter()
+            .zip(distances)
+            .map(|(&i, d)| (i, rank, d))
+            .collect();
+        if selected.len() + front.len() > n {
+            front.sort_by(|a, b| b.2.total_cmp(&a.2));
+            front.truncate(n - selected.len());
+        }
+        selected.extend(front);
+        if selected.len() >= n {
+            break;
+        }
+    }
+
+    selected
+}
+
+/// Returns the simulation function of the climate model, preceded by the
+/// snow model if any, with its default parameters and bounds.
+pub fn build_model(
+    climate_model: &str,
+    snow_model: Option<&str>,
+) -> Result<(SimulateFn, Array1<f64>, Array2<f64>), Error> {
+    if let Some(snow_model) = snow_model {
+        let (snow_init, snow_simulate) = snow::get_model(snow_model)?;
+        let (climate_init, climate_simulate) =
+            climate::get_model(climate_model)?;
+
+        let init = compose_init(snow_init, climate_init);
+        let (defaults, bounds, n_snow_params) = init();
+        let simulate =
+            compose_simulate(snow_simulate, climate_simulate, n_snow_params);
+        Ok((simulate, defaults, bounds))
+    } else {
+        let (init, simulate) = climate::get_model(climate_model)?;
+        let (defaults, bounds) = init();
+        Ok((Box::new(simulate), defaults, bounds))
+    }
+}
+
+/// Latin hypercube sample of the parameter space, each parameter range
+/// being split into `n_samples` strata sampled exactly once.
+pub fn sample_latin_hypercube(
+    n_samples: usize,
+    lower_bounds: ArrayView1<f64>,
+    upper_bounds: ArrayView1<f64>,
+    rng: &mut ChaCha8Rng,
+) -> Array2<f64> {
+    let n_params = lower_bounds.len();
+    let mut samples = Array2::zeros((n_samples, n_params));
+    for j in 0..n_params {
+        let mut strata: Vec<usize> = (0..n_samples).collect();
+        strata.shuffle(rng);
+        let range = upper_bounds[j] - lower_bounds[j];
+        for (i, stratum) in strata.into_iter().enumerate() {
+            let u = (stratum as f64 + rng.random::<f64>()) / n_samples as f64;
+            samples[[i, j]] = lower_bounds[j] + u * range;
+        }
+    }
+    samples
+}
+
+/// Returns the RMSE, NSE and KGE, followed by the NSE and KGE on the
+/// inverse discharge, the modified KGE, the NSE on the log discharge, the
+/// percent bias, the MAE, the volumetric efficiency, the NSE on the square
+/// root discharge, the components of the KGE (r, alpha and beta) and the
+/// DTW distance.
+pub fn evaluate_simulation(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<Array1<f64>, Error> {
+    evaluate_metrics(observations, simulations, None)
+}
+
+/// Same as `evaluate_simulation`, but the metrics on the inverse discharge
+/// and the DTW distance, the most expensive ones, are NaN unless they are
+/// the `objective`, so the searches only ranking on it don't pay for them.
+pub fn evaluate_metrics(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    objective: Option<Objective>,
+) -> Result<Array1<f64>, Error> {
+    let (nse_inv, kge_inv) = if is_needed(Objective::NseInv, objective)
+        || is_needed(Objective::KgeInv, objective)
+    {
+        let (inverse_observations, inverse_simulations) =
+            offset_pair(observations, simulations, None, f64::recip)?;
+        let (inverse_observations, inverse_simulations) =
+            (inverse_observations.view(), inverse_simulations.view());
+        (
+            calculate_nse(inverse_observations, inverse_simulations)?,
+            calculate_kge(inverse_observations, inverse_simulations)?,
+        )
+    } else {
+        (f64::NAN, f64::NAN)
+    };
+    let dtw = if is_needed(Objective::Dtw, objective) {
+        calculate_dtw(observations, simulations, DTW_WINDOW)?.0
+    } else {
+        f64::NAN
+    };
+    let (kge, r, alpha, beta) =
+        calculate_kge_components(observations, simulations)?;
+    Ok(Array1::from_vec(vec![
+        calculate_rmse(observations, simulations)?,
+        calculate_nse(observations, simulations)?,
+        kge,
+        nse_inv,
+        kge_inv,
+        calculate_kge_2012(observations, simulations)?,
+        calculate_log_nse(observations, simulations, None)?,
+        calculate_pbias(observations, simulations)?,
+        calculate_mae(observations, simulations)?,
+        calculate_volumetric_efficiency(observations, simulations)?,
+        calculate_nse_sqrt(observations, simulations)?,
+        r,
+        alpha,
+        beta,
+        dtw,
+    ]))
+}
+
+/// Whether the metric of `evaluate_metrics` is computed for the objective,
+/// all of them being when there is none.
+fn is_needed(metric: Objective, objective: Option<Objective>) -> bool {
+    objective.is_none_or(|objective| objective.index().0 == metric.index().0)
+}
+
+/// `evaluate_metrics` with each timestep weighted.
+pub fn evaluate_weighted_metrics(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+    objective: Option<Objective>,
+) -> Result<Array1<f64>, Error> {
+    let (nse_inv, kge_inv) = if is_needed(Objective::NseInv, objective)
+        || is_needed(Objective::KgeInv, objective)
+    {
+        let (inverse_observations, inverse_simulations) =
+            offset_pair(observations, simulations, None, f64::recip)?;
+        let (inverse_observations, inverse_simulations) =
+            (inverse_observations.view(), inverse_simulations.view());
+        (
+            calculate_weighted_nse(
+                inverse_observations,
+                inverse_simulations,
+                weights,
+            )?,
+            calculate_weighted_kge(
+                inverse_observations,
+                inverse_simulations,
+                weights,
+            )?,
+        )
+    } else {
+        (f64::NAN, f64::NAN)
+    };
+    let dtw = if is_needed(Objective::Dtw, objective) {
+        calculate_weighted_dtw(observations, simulations, weights, DTW_WINDOW)?
+            .0
+    } else {
+        f64::NAN
+    };
+    let (kge, r, alpha, beta) =
+        calculate_weighted_kge_components(observations, simulations, weights)?;
+    Ok(Array1::from_vec(vec![
+        calculate_weighted_rmse(observations, simulations, weights)?,
+        calculate_weighted_nse(observations, simulations, weights)?,
+        kge,
+        nse_inv,
+        kge_inv,
+        calculate_weighted_kge_2012(observations, simulations, weights)?,
+        calculate_weighted_log_nse(observations, simulations, weights, None)?,
+        calculate_weighted_pbias(observations, simulations, weights)?,
+        calculate_weighted_mae(observations, simulations, weights)?,
+        calculate_weighted_volumetric_efficiency(
+            observations,
+            simulations,
+            weights,
+        )?,
+        calculate_weighted_nse_sqrt(observations, simulations, weights)?,
+        r,
+        alpha,
+        beta,
+        dtw,
+    ]))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SnowVariable {
+    Swe,
+    SnowCover,
+}
+
+impl FromStr for SnowVariable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "swe" => Ok(Self::Swe),
+            "snow_cover" => Ok(Self::SnowCover),
+            _ => Err(format!(
+                "Unknown snow variable '{}'. Valid options: swe, snow_cover",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SnowVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnowVariable::Swe => write!(f, "swe"),
+            SnowVariable::SnowCover => write!(f, "snow_cover"),
+        }
+    }
+}
+
+/// Observed catchment snow water equivalent (mm) or snow cover (0-1),
+/// constraining the snow model parameters. Missing values are ignored.
+pub struct SnowObjective {
+    pub simulate: SnowStatesFn,
+    pub n_params: usize,
+    pub variable: SnowVariable,
+    pub observations: Array1<f64>,
+    pub weight: f64,
+    indices: Vec<usize>,
+}
+
+impl SnowObjective {
+    pub fn new(
+        snow_model: &str,
+        variable: SnowVariable,
+        observations: Array1<f64>,
+        weight: f64,
+    ) -> Result<Self, Error> {
+        let (init, _) = snow::get_model(snow_model)?;
+        let indices = (0..observations.len())
+            .filter(|&t| observations[t].is_finite())
+            .collect();
+        Ok(SnowObjective {
+            simulate: snow::get_states_model(snow_model)?,
+            n_params: init().0.len(),
+            variable,
+            observations,
+            weight,
+            indices,
+        })
+    }
+
+    /// Weights the streamflow metrics with the same metrics computed on the
+    /// snow variable, the snow parameters coming first in `params`.
+    pub fn combine(
+        &self,
+        flow_values: Array1<f64>,
+        params: ArrayView1<f64>,
+        data: Data,
+        metadata: &Metadata,
+        objective: Option<Objective>,
+    ) -> Result<Array1<f64>, Error> {
+        let states = (self.simulate)(
+            params.slice(s![..self.n_params]),
+            data,
+            metadata,
+        )?;
+        let simulated = match self.variable {
+            SnowVariable::Swe => states.mean_swe(),
+            SnowVariable::SnowCover => states.snow_cover,
+        };
+        let snow_values = evaluate_metrics(
+            self.observations.select(Axis(0), &self.indices).view(),
+            simulated.select(Axis(0), &self.indices).view(),
+            objective,
+        )?;
+        Ok((1. - self.weight) * flow_values + self.weight * snow_values)
+    }
+}
+
+/// Streamflow observed at another gauge (mm/day), compared to the same
+/// simulation as the main observations.
+pub struct Gauge {
+    pub observations: Array1<f64>,
+    pub weight: f64,
+}
+
+/// Weighted mean of the values of `Scoring::evaluate` on the main
+/// observations, which have a unit weight, and on each gauge.
+pub fn combine_gauges(
+    flow_values: Array1<f64>,
+    simulations: ArrayView1<f64>,
+    gauges: &[Gauge],
+    scoring: &Scoring,
+    record_weights: Option<ArrayView1<f64>>,
+) -> Result<Array1<f64>, Error> {
+    let mut values = flow_values;
+    let mut total_weight = 1.;
+    for gauge in gauges {
+        let gauge_values = scoring.evaluate(
+            gauge.observations.view(),
+            simulations,
+            record_weights,
+        )?;
+        values = values + gauge.weight * gauge_values;
+        total_weight += gauge.weight;
+    }
+    Ok(values / total_weight)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    Linear,
+    Log,
+    Logit,
+}
+
+// offset of the log transform from the lower bound, relative to the range
+const LOG_OFFSET: f64 = 1e-3;
+// closest distance of the logit transform to the bounds, relative to the range
+const LOGIT_MARGIN: f64 = 1e-3;
+
+impl Transform {
+    pub fn forward(&self, x: f64, lower: f64, upper: f64) -> f64 {
+        let range = upper - lower;
+        match self {
+            Transform::Linear => x,
+            Transform::Log => (x - lower + LOG_OFFSET * range).ln(),
+            Transform::Logit => {
+                let u = ((x - lower) / range)
+                    .clamp(LOGIT_MARGIN, 1. - LOGIT_MARGIN);
+                (u / (1. - u)).ln()
+            }
+        }
+    }
+
+    pub fn inverse(&self, t: f64, lower: f64, upper: f64) -> f64 {
+        let range = upper - lower;
+        match self {
+            Transform::Linear => t,
+            Transform::Log => t.exp() + lower - LOG_OFFSET * range,
+            Transform::Logit => lower + range / (1. + (-t).exp()),
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(Self::Linear),
+            "log" => Ok(Self::Log),
+            "logit" => Ok(Self::Logit),
+            _ => Err(format!(
+                "Unknown transform '{}'. Valid options: linear, log, logit",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Linear => write!(f, "linear"),
+            Transform::Log => write!(f, "log"),
+            Transform::Logit => write!(f, "logit"),
+        }
+    }
+}
+
+/// Per-parameter transforms between the model parameters and the space
+/// searched by the calibration, so that parameters spanning orders of
+/// magnitude are sampled and reflected evenly. The fixed parameters are left
+/// out of the searched space.
+pub struct ParamTransform {
+    pub transforms: Vec<Transform>,
+    pub fixed: Vec<Option<f64>>,
+    pub lower_bounds: Array1<f64>,
+    pub upper_bounds: Array1<f64>,
+}
+
+impl ParamTransform {
+    /// Number of dimensions of the searched space.
+    pub fn n_free(&self) -> usize {
+        self.fixed.iter().filter(|value| value.is_none()).count()
+    }
+
+    pub fn forward(&self, params: ArrayView1<f64>) -> Array1<f64> {
+        (0..self.transforms.len())
+            .filter(|&j| self.fixed[j].is_none())
+            .map(|j| {
+                self.transforms[j].forward(
+                    params[j],
+                    self.lower_bounds[j],
+                    self.upper_bounds[j],
+                )
+            })
+            .collect()
+    }
+
+    pub fn inverse(&self, params: ArrayView1<f64>) -> Array1<f64> {
+        let mut free = params.iter();
+        (0..self.transforms.len())
+            .map(|j| match self.fixed[j] {
+                Some(value) => value,
+                None => self.transforms[j].inverse(
+                    *free.next().unwrap(),
+                    self.lower_bounds[j],
+                    self.upper_bounds[j],
+                ),
+            })
+            .collect()
+    }
+}
+
+/// Prior estimate of the parameters, e.g. from a regional model, softly
+/// constraining the calibration with a penalty of `weight` times the squared
+/// distance to it, each parameter being scaled by its range.
+pub struct Prior {
+    pub params: Array1<f64>,
+    pub weight: f64,
+    pub ranges: Array1<f64>,
+}
+
+impl Prior {
+    pub fn new(params: Array1<f64>, weight: f64) -> Self {
+        let ranges = Array1::ones(params.len());
+        Prior {
+            params,
+            weight,
+            ranges,
+        }
+    }
+
+    pub fn penalty(&self, params: ArrayView1<f64>) -> f64 {
+        let distance: f64 = params
+            .iter()
+            .zip(&self.params)
+            .zip(&self.ranges)
+            .map(|((x, prior), range)| ((x - prior) / range).powi(2))
+            .sum();
+        self.weight * distance
+    }
+}
+
+/// Water balance constraint, penalizing the relative error on the total
+/// runoff volume beyond `tolerance` by `weight` times the excess, so a good
+/// efficiency can't come from compensating volume errors.
+#[derive(Debug, Clone, Copy)]
+pub struct VolumePenalty {
+    pub tolerance: f64,
+    pub weight: f64,
+}
+
+impl VolumePenalty {
+    /// Penalty on the volumes over the timesteps with observations, none
+    /// when there are no observed flows.
+    pub fn penalty(
+        &self,
+        observations: ArrayView1<f64>,
+        simulations: ArrayView1<f64>,
+    ) -> f64 {
+        let (observed, simulated) = observations
+            .iter()
+            .zip(simulations)
+            .filter(|(o, _)| !o.is_nan())
+            .fold((0., 0.), |(observed, simulated), (o, s)| {
+                (observed + o, simulated + s)
+            });
+        if observed <= 0. {
+            return 0.;
+        }
+        let error = ((simulated - observed) / observed).abs();
+        self.weight * (error - self.tolerance).max(0.)
+    }
+}
+
+/// High-flow events, for the objectives to only cover them.
+///
+/// The events are the peaks over the `quantile` of the observed discharge,
+/// the exceedances less than `separation` timesteps apart belonging to the
+/// same event so that the events are independent (see `find_events`). Each
+/// event window is extended by `padding` timesteps on both sides to cover
+/// the rising limb and the start of the recession.
+#[derive(Debug, Clone, Copy)]
+pub struct Events {
+    pub quantile: f64,
+    pub separation: usize,
+    pub padding: usize,
+}
+
+impl Events {
+    /// Unit weight for the timesteps in an event window, zero otherwise.
+    pub fn mask(&self, observations: ArrayView1<f64>) -> Array1<f64> {
+        let n_timesteps = observations.len();
+        let mut mask = Array1::zeros(n_timesteps);
+        for event in find_events(observations, self.quantile, self.separation)
+        {
+            let start = event.start.saturating_sub(self.padding);
+            let end = (event.end + self.padding).min(n_timesteps);
+            mask.slice_mut(s![start..end]).fill(1.);
+        }
+        mask
+    }
+}
+
+/// Weight of each month's errors, e.g. to favour the snowmelt freshet over
+/// the summer baseflow.
+#[derive(Debug, Clone)]
+pub struct SeasonalWeights {
+    pub months: Array1<f64>,
+}
+
+impl SeasonalWeights {
+    /// Weights given per month, from january, or per season, from winter
+    /// (december to february).
+    pub fn new(weights: &[f64]) -> Result<Self, String> {
+        if weights.iter().any(|w| w.is_nan() || *w < 0.) {
+            return Err("seasonal weights must be positive".to_string());
+        }
+        let months = match weights.len() {
+            12 => Array1::from_vec(weights.to_vec()),
+            4 => Array1::from_shape_fn(12, |m| weights[(m + 1) % 12 / 3]),
+            n => {
+                return Err(format!(
+                    "expected 12 monthly or 4 seasonal weights, got {}",
+                    n
+                ))
+            }
+        };
+        Ok(SeasonalWeights { months })
+    }
+
+    pub fn weights(&self, day_of_year: ArrayView1<usize>) -> Array1<f64> {
+        day_of_year.mapv(|day| self.months[month(day)])
+    }
+}
+
+/// Periods the discharge is summed over before computing the objectives,
+/// the model still running at its own timestep, e.g. when the daily
+/// observations are noisy.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum TemporalAggregation {
+    #[default]
+    None,
+    Monthly,
+    Days(usize),
+}
+
+impl FromStr for TemporalAggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        if let Some(days) = s
+            .strip_prefix("days(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            return match days.trim().parse::<usize>() {
+                Ok(days) if days > 0 => Ok(Self::Days(days)),
+                _ => Err(format!("Invalid number of days '{}'", days)),
+            };
+        }
+        match s.as_str() {
+            "none" => Ok(Self::None),
+            "monthly" => Ok(Self::Monthly),
+            _ => Err(format!(
+                "Unknown temporal aggregation '{}'. Valid options: none, \
+                 monthly, days(n)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TemporalAggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemporalAggregation::None => write!(f, "none"),
+            TemporalAggregation::Monthly => write!(f, "monthly"),
+            TemporalAggregation::Days(days) => write!(f, "days({})", days),
+        }
+    }
+}
+
+impl TemporalAggregation {
+    /// Consecutive timesteps summed together, none meaning the discharge
+    /// isn't aggregated. A month starts whenever the month of the day of
+    /// year changes, so partial months at the ends are kept.
+    pub fn blocks(
+        &self,
+        day_of_year: ArrayView1<f64>,
+    ) -> Option<Vec<Range<usize>>> {
+        let n_timesteps = day_of_year.len();
+        match self {
+            TemporalAggregation::None => None,
+            TemporalAggregation::Days(days) => Some(
+                (0..n_timesteps)
+                    .step_by(*days)
+                    .map(|start| start..(start + days).min(n_timesteps))
+                    .collect(),
+            ),
+            TemporalAggregation::Monthly => {
+                let month = |day: f64| month(day as usize);
+                let mut blocks = vec![];
+                let mut start = 0;
+                for t in 1..=n_timesteps {
+                    if t == n_timesteps
+                        || month(day_of_year[t]) != month(day_of_year[t - 1])
+                    {
+                        blocks.push(start..t);
+                        start = t;
+                    }
+                }
+                Some(blocks)
+            }
+        }
+    }
+}
+
+/// Applies `reduce` (e.g. the sum) to each block of the series, a block
+/// with a missing value being missing.
+pub fn aggregate_blocks(
+    series: ArrayView1<f64>,
+    blocks: &[Range<usize>],
+    reduce: fn(ArrayView1<f64>) -> f64,
+) -> Array1<f64> {
+    blocks
+        .iter()
+        .map(|block| reduce(series.slice(s![block.clone()])))
+        .collect()
+}
+
+/// Every evaluated parameter set with its objectives and the iteration it
+/// was evaluated at, up to `max_size` evaluations.
+#[derive(Default)]
+pub struct Trace {
+    pub iteration: usize,
+    pub max_size: Option<usize>,
+    pub iterations: Vec<usize>,
+    pub params: Vec<Array1<f64>>,
+    pub objectives: Vec<Array1<f64>>,
+}
+
+impl Trace {
+    pub fn new(max_size: Option<usize>) -> Self {
+        Trace {
+            max_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, params: Array1<f64>, objectives: Array1<f64>) {
+        if self.max_size.is_some_and(|max_size| self.len() >= max_size) {
+            return;
+        }
+        self.iterations.push(self.iteration);
+        self.params.push(params);
+        self.objectives.push(objectives);
+    }
+
+    pub fn len(&self) -> usize {
+        self.iterations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iterations.is_empty()
+    }
+
+    pub fn to_py_dict<'py>(
+        &self,
+        py: Python<'py>,
+        n_params: usize,
+        n_objectives: usize,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("iteration", self.iterations.to_pyarray(py))?;
+        dict.set_item(
+            "params",
+            stack_rows(&self.params, n_params).to_pyarray(py),
+        )?;
+        dict.set_item(
+            "objectives",
+            stack_rows(&self.objectives, n_objectives).to_pyarray(py),
+        )?;
+        Ok(dict)
+    }
+}
+
+/// Least recently used cache of the objectives of up to `capacity`
+/// parameter sets, the parameters being rounded to multiples of
+/// `resolution` so near-identical points share an entry.
+pub struct EvaluationCache {
+    pub capacity: usize,
+    pub resolution: f64,
+    pub hits: usize,
+    pub misses: usize,
+    tick: u64,
+    entries: HashMap<Vec<i64>, (Array1<f64>, u64)>,
+    // keys by the tick they were last used at
+    recency: BTreeMap<u64, Vec<i64>>,
+}
+
+impl EvaluationCache {
+    pub fn new(capacity: usize, resolution: f64) -> Self {
+        EvaluationCache {
+            capacity,
+            resolution,
+            hits: 0,
+            misses: 0,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    fn key(&self, params: ArrayView1<f64>) -> Vec<i64> {
+        params
+            .iter()
+            .map(|x| (x / self.resolution).round() as i64)
+            .collect()
+    }
+
+    pub fn get(&mut self, params: ArrayView1<f64>) -> Option<Array1<f64>> {
+        let key = self.key(params);
+        self.tick += 1;
+        match self.entries.get_mut(&key) {
+            Some((values, used)) => {
+                self.recency.remove(used);
+                *used = self.tick;
+                self.recency.insert(self.tick, key);
+                self.hits += 1;
+                Some(values.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, params: ArrayView1<f64>, values: Array1<f64>) {
+        let key = self.key(params);
+        self.restore(key, values);
+    }
+
+    /// Keys and values of the entries, from the least to the most recently
+    /// used.
+    pub fn entries(&self) -> impl Iterator<Item = (&[i64], &Array1<f64>)> {
+        self.recency
+            .values()
+            .map(|key| (key.as_slice(), &self.entries[key].0))
+    }
+
+    /// Adds the values under a key of `entries`, as the most recently used
+    /// entry.
+    pub fn restore(&mut self, key: Vec<i64>, values: Array1<f64>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.get(&key) {
+            self.recency.remove(used);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (values, self.tick));
+    }
+}
+
+/// Python function `(observations, simulation) -> float` scoring the
+/// simulations after the metrics of `evaluate_simulation`.
+pub struct CustomObjective {
+    pub function: Py<PyAny>,
+    pub minimize: bool,
+}
+
+/// Scoring of the parameters beyond the streamflow metrics, used by `Sce`.
+#[derive(Default)]
+pub struct Scoring {
+    // the only expensive metric computed, all of them when None
+    pub objective: Option<Objective>,
+    pub custom_objective: Option<CustomObjective>,
+    pub snow_objective: Option<SnowObjective>,
+    pub gauges: Vec<Gauge>,
+    pub transform: Option<ParamTransform>,
+    pub trace: Option<Mutex<Trace>>,
+    // timesteps held out of the calibration to validate it
+    pub validation_range: Option<Range<usize>>,
+    // first timesteps left out of the objectives while the stores settle
+    pub warmup: usize,
+    // applied to the discharge before computing the metrics
+    pub transformation: Transformation,
+    pub flow_weighting: FlowWeighting,
+    // high-flow events the objectives are restricted to
+    pub events: Option<Events>,
+    pub seasonal_weights: Option<SeasonalWeights>,
+    // weight of each timestep of the whole record, e.g. from the
+    // observation uncertainty
+    pub timestep_weights: Option<Array1<f64>>,
+    pub temporal_aggregation: TemporalAggregation,
+    pub prior: Option<Prior>,
+    pub volume_penalty: Option<VolumePenalty>,
+    pub cache: Option<Mutex<EvaluationCache>>,
+}
+
+impl Scoring {
+    /// Model parameters of a point of the searched space.
+    pub fn to_model(&self, params: ArrayView1<f64>) -> Array1<f64> {
+        match &self.transform {
+            Some(transform) => transform.inverse(params),
+            None => params.to_owned(),
+        }
+    }
+
+    /// Timesteps of the series used by the calibration.
+    pub fn calibration_period<'a>(
+        &self,
+        series: ArrayView1<'a, f64>,
+    ) -> CowArray<'a, f64, Ix1> {
+        let warmup = self.warmup.min(series.len());
+        match &self.validation_range {
+            Some(range) => {
+                let indices: Vec<usize> = (warmup..series.len())
+                    .filter(|t| !range.contains(t))
+                    .collect();
+                series.select(Axis(0), &indices).into()
+            }
+            None => series.slice_move(s![warmup..]).into(),
+        }
+    }
+
+    /// Weights of the timesteps of the whole record, the seasonal weights
+    /// times the timestep weights, if any.
+    pub fn record_weights(
+        &self,
+        data: Data,
+    ) -> Result<Option<Array1<f64>>, Error> {
+        let seasonal_weights = self
+            .seasonal_weights
+            .as_ref()
+            .map(|seasonal_weights| seasonal_weights.weights(data.day_of_year));
+        match &self.timestep_weights {
+            Some(weights) if weights.len() != data.day_of_year.len() => {
+                Err(Error::InputLengthMismatch(
+                    "timestep_weights",
+                    "day_of_year",
+                    weights.len(),
+                    data.day_of_year.len(),
+                ))
+            }
+            Some(weights) => Ok(Some(match seasonal_weights {
+                Some(seasonal_weights) => seasonal_weights * weights,
+                None => weights.clone(),
+            })),
+            None => Ok(seasonal_weights),
+        }
+    }
+
+    /// Blocks of the calibration period the discharge is aggregated over, if
+    /// any (see `TemporalAggregation`).
+    pub fn temporal_blocks(&self, data: Data) -> Option<Vec<Range<usize>>> {
+        if matches!(self.temporal_aggregation, TemporalAggregation::None) {
+            return None;
+        }
+        let day_of_year = data.day_of_year.mapv(|day| day as f64);
+        self.temporal_aggregation
+            .blocks(self.calibration_period(day_of_year.view()).view())
+    }
+
+    /// Metrics of `evaluate_flow` followed by the value of the custom
+    /// objective function, called on the untransformed discharge, if any.
+    pub fn evaluate(
+        &self,
+        observations: ArrayView1<f64>,
+        simulations: ArrayView1<f64>,
+        record_weights: Option<ArrayView1<f64>>,
+    ) -> Result<Array1<f64>, Error> {
+        let values =
+            self.evaluate_flow(observations, simulations, record_weights)?;
+        match &self.custom_objective {
+            Some(custom_objective) => {
+                let value = call_objective(
+                    &custom_objective.function,
+                    observations,
+                    simulations,
+                )?;
+                Ok(values.into_iter().chain(std::iter::once(value)).collect())
+            }
+            None => Ok(values),
+        }
+    }
+
+    /// Metrics of `evaluate_simulation` on the transformed discharge, each
+    /// timestep being weighted by the observed discharge and the given
+    /// weights of `record_weights` if required and only the high-flow events
+    /// being kept if any.
+    pub fn evaluate_flow(
+        &self,
+        observations: ArrayView1<f64>,
+        simulations: ArrayView1<f64>,
+        record_weights: Option<ArrayView1<f64>>,
+    ) -> Result<Array1<f64>, Error> {
+        let mut weights = self.flow_weighting.weights(observations);
+        let masks = self
+            .events
+            .map(|events| events.mask(observations))
+            .into_iter()
+            .chain(record_weights.map(|w| w.to_owned()));
+        for mask in masks {
+            weights = Some(match weights {
+                Some(weights) => weights * mask,
+                None => mask,
+            });
+        }
+        let (observations, simulations) =
+            self.transformation.apply_pair(observations, simulations);
+        match weights {
+            Some(weights) => evaluate_weighted_metrics(
+                observations.view(),
+                simulations.view(),
+                weights.view(),
+                self.objective,
+            ),
+            None => evaluate_metrics(
+                observations.view(),
+                simulations.view(),
+                self.objective,
+            ),
+        }
+    }
+
+    /// Sets the iteration the next evaluations are recorded at.
+    pub fn set_iteration(&self, iteration: usize) {
+        if let Some(trace) = &self.trace {
+            trace.lock().unwrap().iteration = iteration;
+        }
+    }
+
+    /// Worsens the values of `evaluate_params` by `penalty` (see
+    /// `penalize`), the custom objective included.
+    pub fn penalize(&self, values: &mut Array1<f64>, penalty: f64) {
+        penalize(values, penalty);
+        if let Some(custom_objective) = &self.custom_objective {
+            values[N_METRICS] += if custom_objective.minimize {
+                penalty
+            } else {
+                -penalty
+            };
+        }
+    }
+
+    /// Number of values returned by `evaluate_params`.
+    pub fn n_objectives(&self) -> usize {
+        N_METRICS + self.custom_objective.is_some() as usize
+    }
+}
+
+/// Simulates the parameters and returns the values of `evaluate_simulation` on
+/// the transformed, and possibly aggregated, discharge, followed by the value
+/// of the custom objective function if any, averaged over the gauges, weighted
+/// with those on the snow observations and penalized by the volume error and
+/// the prior if any. The parameters are in the searched space,
+/// transformed back to the model ones, and are recorded with the values when
+/// tracing. Points found in the cache aren't simulated again, nor recorded.
+/// The function is called with the GIL, so the callers evaluating in parallel
+/// must have released it.
+pub fn evaluate_params(
+    simulate: &SimulateFn,
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+    observations: ArrayView1<f64>,
+    scoring: &Scoring,
+) -> Result<Array1<f64>, Error> {
+    if let Some(cache) = &scoring.cache {
+        if let Some(values) = cache.lock().unwrap().get(params) {
+            return Ok(values);
+        }
+    }
+    let point = params;
+    let params = scoring.to_model(params);
+    let params = params.view();
+    let simulations = simulate(params, data, metadata)?;
+    let blocks = scoring.temporal_blocks(data);
+    // summed over the blocks when aggregated
+    let period = |series: ArrayView1<f64>| -> Array1<f64> {
+        let series = scoring.calibration_period(series);
+        match &blocks {
+            Some(blocks) => {
+                aggregate_blocks(series.view(), blocks, |b| b.sum())
+            }
+            None => series.into_owned(),
+        }
+    };
+    let observations = period(observations);
+    let simulations = period(simulations.view());
+    let record_weights = scoring.record_weights(data)?.map(|weights| {
+        let weights = scoring.calibration_period(weights.view());
+        match &blocks {
+            Some(blocks) => aggregate_blocks(weights.view(), blocks, |b| {
+                b.mean().unwrap_or(0.)
+            }),
+            None => weights.into_owned(),
+        }
+    });
+    let record_weights = record_weights.as_ref().map(|w| w.view());
+    let mut values = scoring.evaluate(
+        observations.view(),
+        simulations.view(),
+        record_weights,
+    )?;
+    if !scoring.gauges.is_empty() {
+        let gauges: Vec<Gauge> = scoring
+            .gauges
+            .iter()
+            .map(|gauge| Gauge {
+                observations: period(gauge.observations.view()),
+                weight: gauge.weight,
+            })
+            .collect();
+        values = combine_gauges(
+            values,
+            simulations.view(),
+            &gauges,
+            scoring,
+            record_weights,
+        )?;
+    }
+    if let Some(snow_objective) = &scoring.snow_objective {
+        values = snow_objective.combine(
+            values,
+            params,
+            data,
+            metadata,
+            scoring.objective,
+        )?;
+    }
+    if let Some(volume_penalty) = &scoring.volume_penalty {
+        let penalty =
+            volume_penalty.penalty(observations.view(), simulations.view());
+        scoring.penalize(&mut values, penalty);
+    }
+    if let Some(prior) = &scoring.prior {
+        scoring.penalize(&mut values, prior.penalty(params));
+    }
+    record(scoring, params, &values);
+    if let Some(cache) = &scoring.cache {
+        cache.lock().unwrap().insert(point, values.clone());
+    }
+    Ok(values)
+}
+
+/// Calls the custom objective function with the GIL.
+pub fn call_objective(
+    function: &Py<PyAny>,
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, Error> {
+    Python::attach(|py| -> PyResult<f64> {
+        function
+            .call1(
+                py,
+                (observations.to_pyarray(py), simulations.to_pyarray(py)),
+            )?
+            .extract(py)
+    })
+    .map_err(|e| Error::CustomObjective(e.to_string()))
+}
+
+fn record(scoring: &Scoring, params: ArrayView1<f64>, values: &Array1<f64>) {
+    if let Some(trace) = &scoring.trace {
+        trace
+            .lock()
+            .unwrap()
+            .record(params.to_owned(), values.clone());
+    }
+}
+
+pub fn sort_population(
+    population: &mut Array2<f64>,
+    objectives: &mut Array2<f64>,
+    objective_idx: usize,
+    is_minimization: bool,
+) {
+    // sorted as minimized, with failed simulations being the worst
+    let to_value = |i: usize| -> f64 {
+        let value = if is_minimization {
+            objectives[[i, objective_idx]]
+        } else {
+            -objectives[[i, objective_idx]]
+        };
+        if value.is_nan() {
+            f64::INFINITY
+        } else {
+            value
+        }
+    };
+    let mut indices: Vec<usize> = (0..objectives.nrows()).collect();
+    indices.sort_by(|&a, &b| to_value(a).total_cmp(&to_value(b)));
+
+    let sorted_population = population.select(Axis(0), &indices);
+    let sorted_objectives = objectives.select(Axis(0), &indices);
+
+    *population = sorted_population;
+    *objectives = sorted_objectives;
+}
+
+/// Stacks the rows into a 2D array, which is empty when there are none.
+pub fn stack_rows(rows: &[Array1<f64>], n_columns: usize) -> Array2<f64> {
+    if rows.is_empty() {
+        return Array2::zeros((0, n_columns));
+    }
+    ndarray::stack(Axis(0), &rows.iter().map(|x| x.view()).collect::<Vec<_>>())
+        .unwrap()
+}
+
+/// Lower Cholesky factor, or None when the matrix isn't positive definite.
+pub fn compute_cholesky(a: Array2<f64>) -> Option<Array2<f64>> {
+    let n = a.nrows();
+    let mut l = Array2::zeros((n, n));
+    for j in 0..n {
+        let diagonal =
+            a[[j, j]] - (0..j).map(|k| l[[j, k]] * l[[j, k]]).sum::<f64>();
+        if diagonal <= 0. || !diagonal.is_finite() {
+            return None;
+        }
+        l[[j, j]] = diagonal.sqrt();
+        for i in (j + 1)..n {
+            let sum: f64 = (0..j).map(|k| l[[i, k]] * l[[j, k]]).sum();
+            l[[i, j]] = (a[[i, j]] - sum) / l[[j, j]];
+        }
+    }
+    Some(l)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn cache_evicts_the_least_recently_used_entry() {
+        let mut cache = EvaluationCache::new(2, 0.1);
+        cache.insert(array![1.].view(), array![10.]);
+        cache.insert(array![2.].view(), array![20.]);
+        assert_eq!(cache.get(array![1.02].view()), Some(array![10.]));
+        cache.insert(array![3.].view(), array![30.]);
+        assert_eq!(cache.get(array![2.].view()), None);
+        assert_eq!(cache.get(array![1.].view()), Some(array![10.]));
+        assert_eq!(cache.get(array![3.].view()), Some(array![30.]));
+        assert_eq!((cache.hits, cache.misses), (3, 1));
+    }
+
+    #[test]
+    fn cache_entries_restore_the_same_cache() {
+        let mut cache = EvaluationCache::new(3, 0.1);
+        cache.insert(array![1., 1.].view(), array![10.]);
+        cache.insert(array![2., 2.].view(), array![20.]);
+        cache.insert(array![3., 3.].view(), array![30.]);
+        cache.get(array![1., 1.].view());
+
+        let mut restored = EvaluationCache::new(3, 0.1);
+        for (key, values) in cache.entries() {
+            restored.restore(key.to_vec(), values.clone());
+        }
+        let entries = |cache: &EvaluationCache| {
+            cache
+                .entries()
+                .map(|(key, values)| (key.to_vec(), values.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(entries(&restored), entries(&cache));
+        assert_eq!(entries(&restored)[0].1, array![20.]);
+
+        // the same entry is evicted next
+        restored.insert(array![4., 4.].view(), array![40.]);
+        assert_eq!(restored.get(array![2., 2.].view()), None);
+    }
+
+    #[test]
+    fn metrics_for_an_objective_skip_the_expensive_ones() {
+        let observations = array![1., 3., f64::NAN, 2., 5., 4., 2., 1.];
+        let simulations = array![1.5, 2., 3., 2.5, 4., 4.5, 1., 1.];
+        let all = evaluate_simulation(observations.view(), simulations.view())
+            .unwrap();
+        assert_eq!(all.len(), N_METRICS);
+        assert!(all.iter().all(|value| value.is_finite()));
+        assert_eq!(
+            evaluate_metrics(observations.view(), simulations.view(), None)
+                .unwrap(),
+            all
+        );
+
+        let nse = evaluate_metrics(
+            observations.view(),
+            simulations.view(),
+            Some(Objective::Nse),
+        )
+        .unwrap();
+        for (i, (&value, &expected)) in nse.iter().zip(&all).enumerate() {
+            if [3, 4, 14].contains(&i) {
+                assert!(value.is_nan());
+            } else {
+                assert_eq!(value, expected);
+            }
+        }
+
+        let dtw = evaluate_metrics(
+            observations.view(),
+            simulations.view(),
+            Some(Objective::Dtw),
+        )
+        .unwrap();
+        assert_eq!(dtw[14], all[14]);
+    }
+}
@@ -0,0 +1,104 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+// standard partial areas of the three surface stores (Boughton, 2004)
+const PARTIAL_AREAS: [f64; 3] = [0.134, 0.433, 0.433];
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to c1, c2, c3, bfi, k_base, k_surface
+    let default_values = array![7.0, 70.0, 150.0, 0.35, 0.95, 0.35];
+    let bounds = array![
+        [0.0, 50.0],
+        [0.0, 200.0],
+        [0.0, 500.0],
+        [0.0, 1.0],
+        [0.0, 1.0],
+        [0.0, 1.0]
+    ];
+    (default_values, bounds)
+}
+
+/// Runs the Australian Water Balance Model (Boughton, 2004) and returns the
+/// streamflow in mm/day.
+///
+/// Three surface stores of capacities c1, c2 and c3 cover fixed partial
+/// areas of the catchment. Their excess is split between the baseflow store
+/// (fraction bfi) and the surface routing store, each draining as a linear
+/// reservoir with daily recession constants k_base and k_surface.
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    _metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let [c1, c2, c3, bfi, k_base, k_surface]: [f64; 6] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(6, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+    let capacities = [c1, c2, c3];
+
+    let mut discharge: Vec<f64> = Vec::with_capacity(precipitation.len());
+
+    let mut surface_stores = [0.0; 3];
+    let mut base_store = 0.0;
+    let mut routing_store = 0.0;
+
+    for t in 0..precipitation.len() {
+        let mut excess = 0.0;
+        for i in 0..3 {
+            surface_stores[i] =
+                (surface_stores[i] + precipitation[t] - pet[t]).max(0.);
+            if surface_stores[i] > capacities[i] {
+                excess +=
+                    PARTIAL_AREAS[i] * (surface_stores[i] - capacities[i]);
+                surface_stores[i] = capacities[i];
+            }
+        }
+
+        base_store += bfi * excess;
+        routing_store += (1. - bfi) * excess;
+
+        let baseflow = (1. - k_base) * base_store;
+        let surface_flow = (1. - k_surface) * routing_store;
+        base_store -= baseflow;
+        routing_store -= surface_flow;
+
+        discharge.push(baseflow + surface_flow);
+    }
+
+    Ok(Array1::from_vec(discharge))
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "awbm")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
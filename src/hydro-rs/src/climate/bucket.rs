@@ -0,0 +1,87 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to capacity, drainage, et_threshold
+    let default_values = array![200.0, 0.05, 0.7];
+    let bounds = array![[10.0, 1000.0], [0.001, 1.0], [0.1, 1.0]];
+    (default_values, bounds)
+}
+
+/// Runs a single-bucket water balance and returns the streamflow in mm/day.
+///
+/// At each timestep:
+/// 1. precipitation is added to the store;
+/// 2. evapotranspiration happens at the potential rate while the store is
+///    above `et_threshold * capacity` and decreases linearly below it;
+/// 3. any water above `capacity` overflows directly to the outlet;
+/// 4. the remaining store drains linearly at the rate `drainage` (1/day).
+///
+/// The streamflow is the sum of the overflow and the drainage.
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    _metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let [capacity, drainage, et_threshold]: [f64; 3] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(3, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+
+    let mut discharge: Vec<f64> = Vec::with_capacity(precipitation.len());
+
+    let mut store = capacity / 2.;
+
+    for t in 0..precipitation.len() {
+        store += precipitation[t];
+
+        let et_limitation = (store / (et_threshold * capacity)).min(1.);
+        let evapotranspiration = (pet[t] * et_limitation).min(store);
+        store -= evapotranspiration;
+
+        let overflow = (store - capacity).max(0.);
+        store -= overflow;
+
+        let drained = drainage * store;
+        store -= drained;
+
+        discharge.push(overflow + drained);
+    }
+
+    Ok(Array1::from_vec(discharge))
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "bucket")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
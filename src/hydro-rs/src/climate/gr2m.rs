@@ -0,0 +1,105 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+// capacity of the routing store (mm), fixed in GR2M
+const ROUTING_CAPACITY: f64 = 60.0;
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to x1, x2
+    let default_values = array![380.0, 0.92];
+    let bounds = array![[1.0, 2000.0], [0.2, 2.0]];
+    (default_values, bounds)
+}
+
+/// Runs GR2M (Mouelhi et al., 2006) and returns the streamflow in mm/month.
+///
+/// Expects monthly precipitation and PET totals. Rainfall and evaporation
+/// update the production store (x1), whose outflow and excess feed the
+/// routing store. The routing store is scaled by the exchange coefficient
+/// x2 before its quadratic outflow is computed.
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    _metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let [x1, x2]: [f64; 2] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(2, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+
+    let mut discharge: Vec<f64> = Vec::with_capacity(precipitation.len());
+
+    let mut production_store = x1 / 2.;
+    let mut routing_store = ROUTING_CAPACITY / 2.;
+
+    for t in 0..precipitation.len() {
+        let routing_precipitation = update_production(
+            &mut production_store,
+            precipitation[t],
+            pet[t],
+            x1,
+        );
+
+        let store = (routing_store + routing_precipitation) * x2;
+        let flow = store * store / (store + ROUTING_CAPACITY);
+        routing_store = store - flow;
+
+        discharge.push(flow);
+    }
+
+    Ok(Array1::from_vec(discharge))
+}
+
+fn update_production(
+    store: &mut f64,
+    precipitation: f64,
+    pet: f64,
+    x1: f64,
+) -> f64 {
+    let phi = (precipitation / x1).tanh();
+    let store_1 = (*store + x1 * phi) / (1. + phi * *store / x1);
+    let excess = precipitation + *store - store_1;
+
+    let psi = (pet / x1).tanh();
+    let store_2 = store_1 * (1. - psi) / (1. + psi * (1. - store_1 / x1));
+
+    *store = store_2 / (1. + (store_2 / x1).powi(3)).powf(1. / 3.);
+    let percolation = store_2 - *store;
+
+    excess + percolation
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "gr2m")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
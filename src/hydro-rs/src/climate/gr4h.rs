@@ -0,0 +1,110 @@
+use ndarray::{array, Array1, Array2, ArrayView1, Axis};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::climate::gr4j::{
+    create_unit_hydrographs_with_exponent, update_production, update_routing,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+// percolation constant of the hourly model (Mathevet, 2005)
+const HOURLY_PERCOLATION_CONSTANT: f64 = 21. / 4.;
+// exponent of the s-curves of the hourly unit hydrographs (Mathevet, 2005)
+const HOURLY_UNIT_HYDROGRAPH_EXPONENT: f64 = 1.25;
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to x1, x2, x3, x4 (x4 in hours)
+    let bounds =
+        array![[10.0, 1500.0], [-1.0, 1.0], [10.0, 500.0], [1.0, 240.0]];
+    let default_values = bounds.sum_axis(Axis(1)) / 2.0;
+    (default_values, bounds)
+}
+
+/// Runs GR4H and returns the streamflow in mm per timestep.
+///
+/// Same structure as GR4J, but x4 is expressed in hours and the unit
+/// hydrographs are built in number of timesteps using
+/// `Metadata::timestep`, hourly when missing. The percolation constant
+/// (21/4) and the exponent of the unit hydrographs (1.25) are those of the
+/// hourly model (Mathevet, 2005).
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let [x1, x2, x3, x4]: [f64; 4] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(4, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+
+    let mut discharge: Vec<f64> = Vec::with_capacity(precipitation.len());
+
+    let mut production_store = x1 / 2.;
+    let mut routing_store = x3 / 2.;
+    let mut routing_precipitation: f64 = 0.0;
+    let mut discharge_: f64 = 0.0;
+
+    let unit_hydrographs = create_unit_hydrographs_with_exponent(
+        (x4 / metadata.timestep.unwrap_or(1.)).max(0.5),
+        HOURLY_UNIT_HYDROGRAPH_EXPONENT,
+    );
+    let mut hydrographs = (
+        vec![0.0; unit_hydrographs.0.len()],
+        vec![0.0; unit_hydrographs.1.len()],
+    );
+
+    for t in 0..precipitation.len() {
+        update_production(
+            &mut production_store,
+            &mut routing_precipitation,
+            precipitation[t],
+            pet[t],
+            x1,
+            HOURLY_PERCOLATION_CONSTANT,
+        );
+        update_routing(
+            &mut routing_store,
+            &mut hydrographs,
+            &mut discharge_,
+            &unit_hydrographs,
+            routing_precipitation,
+            x2,
+            x3,
+        );
+        discharge.push(discharge_);
+    }
+
+    Ok(Array1::from_vec(discharge))
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "gr4h")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
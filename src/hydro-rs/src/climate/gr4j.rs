@@ -4,6 +4,12 @@ use pyo3::prelude::*;
 
 use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
 
+// percolation constant of the daily model (the production store percolates
+// according to (store / (9/4 * x1))^4)
+pub(crate) const PERCOLATION_CONSTANT: f64 = 9. / 4.;
+// exponent of the s-curves of the daily unit hydrographs
+const UNIT_HYDROGRAPH_EXPONENT: f64 = 2.5;
+
 pub fn init() -> (Array1<f64>, Array2<f64>) {
     // corresponds to x1, x2, x3, x4
     let bounds =
@@ -12,10 +18,16 @@ pub fn init() -> (Array1<f64>, Array2<f64>) {
     (default_values, bounds)
 }
 
+/// Runs GR4J (Perrin et al., 2003) and returns the streamflow in mm/day.
+///
+/// Net rainfall feeds the production store (x1), whose percolation and
+/// excess are split 90/10 between the two unit hydrographs (base time x4).
+/// The slow branch goes through the routing store (x3) and both branches
+/// are subject to the groundwater exchange (x2).
 pub fn simulate(
     params: ArrayView1<f64>,
     data: Data,
-    metadata: &Metadata,
+    _metadata: &Metadata,
 ) -> Result<Array1<f64>, Error> {
     let [x1, x2, x3, x4]: [f64; 4] = params
         .as_slice()
@@ -24,7 +36,6 @@ pub fn simulate(
 
     let precipitation = data.precipitation;
     let pet = data.pet;
-    let area = metadata.area * 1000.0 * 1000.0;
 
     let mut discharge: Vec<f64> = vec![];
 
@@ -46,6 +57,7 @@ pub fn simulate(
             precipitation[t],
             pet[t],
             x1,
+            PERCOLATION_CONSTANT,
         );
         update_routing(
             &mut routing_store,
@@ -56,21 +68,29 @@ pub fn simulate(
             x2,
             x3,
         );
-        // discharge_ = discharge_ * 1000.0 * area / (3600.0 * 24.0); // mm/day to m^3/s
         discharge.push(discharge_);
     }
 
     Ok(Array1::from_vec(discharge))
 }
 
-fn create_unit_hydrographs(x4: f64) -> (Vec<f64>, Vec<f64>) {
+pub(crate) fn create_unit_hydrographs(x4: f64) -> (Vec<f64>, Vec<f64>) {
+    create_unit_hydrographs_with_exponent(x4, UNIT_HYDROGRAPH_EXPONENT)
+}
+
+/// Unit hydrographs of base `x4` and `2 * x4` timesteps, from s-curves of
+/// the given `exponent`.
+pub(crate) fn create_unit_hydrographs_with_exponent(
+    x4: f64,
+    exponent: f64,
+) -> (Vec<f64>, Vec<f64>) {
     let s1 = |i: f64| -> f64 {
         if i == 0. {
             0.
         } else if i >= x4 {
             1.
         } else {
-            (i / x4).powf(2.5)
+            (i / x4).powf(exponent)
         }
     };
 
@@ -80,9 +100,9 @@ fn create_unit_hydrographs(x4: f64) -> (Vec<f64>, Vec<f64>) {
         } else if i >= 2. * x4 {
             1.
         } else if i < x4 {
-            0.5 * (i / x4).powf(2.5)
+            0.5 * (i / x4).powf(exponent)
         } else {
-            1. - 0.5 * (2. - i / x4).powf(2.5)
+            1. - 0.5 * (2. - i / x4).powf(exponent)
         }
     };
 
@@ -96,12 +116,13 @@ fn create_unit_hydrographs(x4: f64) -> (Vec<f64>, Vec<f64>) {
     (unit_hydrograph_1, unit_hydrograph_2)
 }
 
-fn update_production(
+pub(crate) fn update_production(
     store: &mut f64,
     routing_precipitation: &mut f64,
     precipitation: f64,
     pet: f64,
     x1: f64,
+    percolation_constant: f64,
 ) {
     let mut store_precipitation: f64 = 0.0;
     let mut net_precipitation: f64 = 0.0;
@@ -126,8 +147,10 @@ fn update_production(
 
     let mut percolation = 0.0;
     if x1 / *store > 1e-3 {
-        percolation =
-            *store * (1. - (1. + (4. / 9. * *store / x1).powi(4)).powf(-0.25));
+        percolation = *store
+            * (1.
+                - (1. + (*store / (percolation_constant * x1)).powi(4))
+                    .powf(-0.25));
         *store -= percolation;
     }
 
@@ -135,7 +158,7 @@ fn update_production(
         net_precipitation - store_precipitation + percolation;
 }
 
-fn update_routing(
+pub(crate) fn update_routing(
     store: &mut f64,
     hydrographs: &mut (Vec<f64>, Vec<f64>),
     total_flow: &mut f64,
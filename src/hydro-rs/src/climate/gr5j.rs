@@ -0,0 +1,132 @@
+use ndarray::{array, Array1, Array2, ArrayView1, Axis};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::climate::gr4j::{
+    create_unit_hydrographs, update_production, PERCOLATION_CONSTANT,
+};
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to x1, x2, x3, x4, x5
+    let bounds = array![
+        [10.0, 1500.0],
+        [-5.0, 3.0],
+        [10.0, 400.0],
+        [0.8, 10.0],
+        [-4.0, 4.0]
+    ];
+    let default_values = bounds.sum_axis(Axis(1)) / 2.0;
+    (default_values, bounds)
+}
+
+/// Runs GR5J (Le Moine, 2008) and returns the streamflow in mm/day.
+///
+/// The production store is the same as GR4J's, but routing uses a single
+/// unit hydrograph (base time 2 * x4) whose output is split 90/10 between
+/// the routing store and direct flow. The groundwater exchange is
+/// proportional to the difference between the routing store filling rate
+/// and the threshold x5, so it can change sign during the simulation.
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    _metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let [x1, x2, x3, x4, x5]: [f64; 5] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(5, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+
+    let mut discharge: Vec<f64> = Vec::with_capacity(precipitation.len());
+
+    let mut production_store = x1 / 2.;
+    let mut routing_store = x3 / 2.;
+    let mut routing_precipitation: f64 = 0.0;
+
+    let (_, unit_hydrograph) = create_unit_hydrographs(x4);
+    let mut hydrograph = vec![0.0; unit_hydrograph.len()];
+
+    for t in 0..precipitation.len() {
+        update_production(
+            &mut production_store,
+            &mut routing_precipitation,
+            precipitation[t],
+            pet[t],
+            x1,
+            PERCOLATION_CONSTANT,
+        );
+        discharge.push(update_routing(
+            &mut routing_store,
+            &mut hydrograph,
+            &unit_hydrograph,
+            routing_precipitation,
+            x2,
+            x3,
+            x5,
+        ));
+    }
+
+    Ok(Array1::from_vec(discharge))
+}
+
+fn update_routing(
+    store: &mut f64,
+    hydrograph: &mut [f64],
+    unit_hydrograph: &[f64],
+    routing_precipitation: f64,
+    x2: f64,
+    x3: f64,
+    x5: f64,
+) -> f64 {
+    let n = hydrograph.len();
+    for i in 0..n - 1 {
+        hydrograph[i] =
+            hydrograph[i + 1] + routing_precipitation * unit_hydrograph[i];
+    }
+    hydrograph[n - 1] = 0.0;
+
+    let q = hydrograph[0];
+
+    let groundwater_exchange = x2 * (*store / x3 - x5);
+
+    *store = (*store + 0.9 * q + groundwater_exchange).max(0.);
+
+    let routed_flow = *store * (1. - (1. + (*store / x3).powi(4)).powf(-0.25));
+    *store -= routed_flow;
+
+    let direct_flow = (0.1 * q + groundwater_exchange).max(0.);
+
+    routed_flow + direct_flow
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "gr5j")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
@@ -1,21 +1,58 @@
+pub mod awbm;
+pub mod bucket;
+pub mod gr2m;
+pub mod gr4h;
 pub mod gr4j;
-use ndarray::{Array1, Array2};
-
-use crate::model::{Error, SimulateFnPtr};
+pub mod gr5j;
+pub mod pdm;
+pub mod thornthwaite_mather;
+pub mod topmodel;
+use crate::model::{Error, InitFn, SimulateFnPtr};
 use crate::utils::register_submodule;
 use pyo3::prelude::*;
 
-pub fn get_model(
-    model: &str,
-) -> Result<(fn() -> (Array1<f64>, Array2<f64>), SimulateFnPtr), Error> {
+pub fn get_model(model: &str) -> Result<(InitFn, SimulateFnPtr), Error> {
     match model {
         "gr4j" => Ok((gr4j::init, gr4j::simulate)),
-        _ => Err(Error::WrongModel(model.to_string(), "gr4j".to_string())),
+        "gr5j" => Ok((gr5j::init, gr5j::simulate)),
+        "gr2m" => Ok((gr2m::init, gr2m::simulate)),
+        "gr4h" => Ok((gr4h::init, gr4h::simulate)),
+        "awbm" => Ok((awbm::init, awbm::simulate)),
+        "topmodel" => Ok((topmodel::init, topmodel::simulate)),
+        "bucket" => Ok((bucket::init, bucket::simulate)),
+        "thornthwaite_mather" => {
+            Ok((thornthwaite_mather::init, thornthwaite_mather::simulate))
+        }
+        "pdm" => Ok((pdm::init, pdm::simulate)),
+        _ => Err(Error::WrongModel(
+            model.to_string(),
+            "gr4j, gr5j, gr2m, gr4h, awbm, topmodel, bucket, \
+             thornthwaite_mather, pdm"
+                .to_string(),
+        )),
     }
 }
 
 pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
     let m = PyModule::new(py, "climate")?;
     register_submodule(py, &m, &gr4j::make_module(py)?, "hydro_rs.climate")?;
+    register_submodule(py, &m, &gr5j::make_module(py)?, "hydro_rs.climate")?;
+    register_submodule(py, &m, &gr2m::make_module(py)?, "hydro_rs.climate")?;
+    register_submodule(py, &m, &gr4h::make_module(py)?, "hydro_rs.climate")?;
+    register_submodule(py, &m, &awbm::make_module(py)?, "hydro_rs.climate")?;
+    register_submodule(
+        py,
+        &m,
+        &topmodel::make_module(py)?,
+        "hydro_rs.climate",
+    )?;
+    register_submodule(py, &m, &bucket::make_module(py)?, "hydro_rs.climate")?;
+    register_submodule(
+        py,
+        &m,
+        &thornthwaite_mather::make_module(py)?,
+        "hydro_rs.climate",
+    )?;
+    register_submodule(py, &m, &pdm::make_module(py)?, "hydro_rs.climate")?;
     Ok(m)
 }
@@ -0,0 +1,114 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to cmax, b, kg, k_surface, k_base
+    let default_values = array![300.0, 0.5, 0.02, 2.0, 50.0];
+    let bounds = array![
+        [10.0, 1000.0],
+        [0.01, 2.0],
+        [0.0, 0.2],
+        [0.5, 10.0],
+        [5.0, 300.0]
+    ];
+    (default_values, bounds)
+}
+
+/// Runs the Probability Distributed Model (Moore, 2007) and returns the
+/// streamflow in mm/day.
+///
+/// Store capacities across the catchment follow a Pareto distribution of
+/// maximum cmax and shape b. Evaporation is proportional to the relative
+/// store content and drainage to the groundwater is linear (kg, 1/day).
+/// Direct runoff comes from the stores that are filled and is routed
+/// through two linear reservoirs in series (time constant k_surface, days)
+/// while drainage goes through a linear groundwater reservoir (k_base).
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    _metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let [cmax, b, kg, k_surface, k_base]: [f64; 5] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(5, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+
+    let smax = cmax / (b + 1.);
+    let surface_recession = (-1. / k_surface).exp();
+    let base_recession = (-1. / k_base).exp();
+
+    let mut discharge: Vec<f64> = Vec::with_capacity(precipitation.len());
+
+    let mut store = smax / 2.;
+    let mut surface_stores = [0.0; 2];
+    let mut base_store = 0.0;
+
+    for t in 0..precipitation.len() {
+        let evaporation = (pet[t] * store / smax).min(store);
+        store -= evaporation;
+        let drainage = (kg * store).min(store);
+        store -= drainage;
+
+        let mut direct_runoff = 0.0;
+        if precipitation[t] > 0. {
+            let critical_capacity =
+                cmax * (1. - (1. - store / smax).powf(1. / (b + 1.)));
+            let new_capacity =
+                (critical_capacity + precipitation[t]).min(cmax);
+            let new_store =
+                smax * (1. - (1. - new_capacity / cmax).powf(b + 1.));
+            direct_runoff = (precipitation[t] - (new_store - store)).max(0.);
+            store = new_store;
+        }
+
+        surface_stores[0] += direct_runoff;
+        let intermediate_flow = (1. - surface_recession) * surface_stores[0];
+        surface_stores[0] -= intermediate_flow;
+        surface_stores[1] += intermediate_flow;
+        let surface_flow = (1. - surface_recession) * surface_stores[1];
+        surface_stores[1] -= surface_flow;
+
+        base_store += drainage;
+        let baseflow = (1. - base_recession) * base_store;
+        base_store -= baseflow;
+
+        discharge.push(surface_flow + baseflow);
+    }
+
+    Ok(Array1::from_vec(discharge))
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "pdm")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,93 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to awc, direct_runoff, surplus_runoff
+    let default_values = array![150.0, 0.05, 0.5];
+    let bounds = array![[25.0, 500.0], [0.0, 0.2], [0.05, 1.0]];
+    (default_values, bounds)
+}
+
+/// Runs the Thornthwaite-Mather monthly water balance (as formulated by
+/// McCabe and Markstrom, 2007) and returns the streamflow in mm/month.
+///
+/// Expects monthly precipitation and PET totals. A fraction
+/// `direct_runoff` of precipitation runs off immediately. The rest
+/// satisfies PET first; any excess refills the soil up to its available
+/// water capacity (awc) and the overflow becomes surplus. During deficit
+/// months, the soil dries exponentially with the accumulated deficit. The
+/// surplus is stored and a fraction `surplus_runoff` of the storage
+/// reaches the outlet each month.
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    _metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let [awc, direct_runoff, surplus_runoff]: [f64; 3] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(3, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+
+    let mut discharge: Vec<f64> = Vec::with_capacity(precipitation.len());
+
+    let mut soil_moisture = awc;
+    let mut surplus_store = 0.0;
+
+    for t in 0..precipitation.len() {
+        let direct_flow = direct_runoff * precipitation[t];
+        let remaining = precipitation[t] - direct_flow;
+
+        let mut surplus = 0.0;
+        if remaining >= pet[t] {
+            soil_moisture += remaining - pet[t];
+            surplus = (soil_moisture - awc).max(0.);
+            soil_moisture -= surplus;
+        } else {
+            let deficit = pet[t] - remaining;
+            soil_moisture *= (-deficit / awc).exp();
+        }
+
+        surplus_store += surplus;
+        let surplus_flow = surplus_runoff * surplus_store;
+        surplus_store -= surplus_flow;
+
+        discharge.push(direct_flow + surplus_flow);
+    }
+
+    Ok(Array1::from_vec(discharge))
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "thornthwaite_mather")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,180 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to m, ln_t0, srmax, td
+    let default_values = array![30.0, 8.0, 50.0, 5.0];
+    let bounds = array![[2.0, 100.0], [0.0, 15.0], [1.0, 300.0], [0.1, 50.0]];
+    (default_values, bounds)
+}
+
+/// Runs a lumped TOPMODEL (Beven and Kirkby, 1979) and returns the
+/// streamflow in mm/day.
+///
+/// The catchment is represented by the topographic index histogram of
+/// `Metadata`. Each class has its own local saturation deficit, derived
+/// from the mean deficit and the exponential transmissivity profile (m).
+/// Water leaving the root zone (srmax) fills the unsaturated zone of each
+/// class, which drains to the saturated zone with time delay td (day/mm)
+/// and produces saturation excess once the local deficit is filled.
+/// Subsurface flow depends on the mean deficit and ln_t0, the log of the
+/// saturated transmissivity (mm^2/day per unit contour length).
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    let (saturation_excess, subsurface_flow) =
+        simulate_components(params, data, metadata)?;
+    Ok(saturation_excess + subsurface_flow)
+}
+
+/// Same as `simulate`, but returns the saturation excess and the subsurface
+/// flow separately, both in mm/day.
+pub fn simulate_components(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<(Array1<f64>, Array1<f64>), Error> {
+    let [m, ln_t0, srmax, td]: [f64; 4] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(4, params.len()))?;
+
+    let topographic_index = metadata.topographic_index;
+    let fractions = metadata.topographic_index_fractions;
+    if topographic_index.is_empty() {
+        return Err(Error::MissingMetadata("topographic_index"));
+    }
+    if topographic_index.len() != fractions.len() {
+        return Err(Error::InputLengthMismatch(
+            "topographic_index",
+            "topographic_index_fractions",
+            topographic_index.len(),
+            fractions.len(),
+        ));
+    }
+
+    let precipitation = data.precipitation;
+    let pet = data.pet;
+    let n_classes = topographic_index.len();
+
+    let total_fraction: f64 = fractions.sum();
+    if total_fraction.is_nan() || total_fraction <= 0. {
+        return Err(Error::TopographicIndexFractions(total_fraction));
+    }
+    let fractions: Vec<f64> =
+        fractions.iter().map(|a| a / total_fraction).collect();
+    let mean_index: f64 = topographic_index
+        .iter()
+        .zip(&fractions)
+        .map(|(t, a)| t * a)
+        .sum();
+    let max_baseflow = (ln_t0 - mean_index).exp();
+
+    let mut saturation_excesses: Vec<f64> =
+        Vec::with_capacity(precipitation.len());
+    let mut subsurface_flows: Vec<f64> =
+        Vec::with_capacity(precipitation.len());
+
+    // initial deficit gives a baseflow of 1 mm/day
+    let mut mean_deficit = (m * (ln_t0 - mean_index)).max(0.);
+    let mut root_zone_deficit = srmax / 2.;
+    let mut unsaturated_stores = vec![0.0; n_classes];
+
+    for t in 0..precipitation.len() {
+        root_zone_deficit -= precipitation[t];
+        let drainage = (-root_zone_deficit).max(0.);
+        root_zone_deficit = root_zone_deficit.max(0.);
+
+        let evapotranspiration = pet[t] * (1. - root_zone_deficit / srmax);
+        root_zone_deficit =
+            (root_zone_deficit + evapotranspiration).min(srmax);
+
+        let mut saturation_excess = 0.0;
+        let mut recharge = 0.0;
+        for i in 0..n_classes {
+            let local_deficit = (mean_deficit
+                + m * (mean_index - topographic_index[i]))
+                .max(0.);
+
+            let store = &mut unsaturated_stores[i];
+            *store += drainage;
+            if *store > local_deficit {
+                saturation_excess += fractions[i] * (*store - local_deficit);
+                *store = local_deficit;
+            }
+            if local_deficit > 0. {
+                let unsaturated_drainage =
+                    (*store / (local_deficit * td)).min(*store);
+                *store -= unsaturated_drainage;
+                recharge += fractions[i] * unsaturated_drainage;
+            }
+        }
+
+        let baseflow = max_baseflow * (-mean_deficit / m).exp();
+        mean_deficit += baseflow - recharge;
+
+        saturation_excesses.push(saturation_excess);
+        subsurface_flows.push(baseflow);
+    }
+
+    Ok((
+        Array1::from_vec(saturation_excesses),
+        Array1::from_vec(subsurface_flows),
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_components")]
+pub fn py_simulate_components<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<(Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>)> {
+    let (saturation_excess, subsurface_flow) = simulate_components(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    Ok((
+        saturation_excess.to_pyarray(py),
+        subsurface_flow.to_pyarray(py),
+    ))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "topmodel")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_components, &m)?)?;
+    Ok(m)
+}
@@ -3,6 +3,7 @@ mod climate;
 mod metrics;
 mod model;
 mod pet;
+mod signatures;
 mod snow;
 mod utils;
 
@@ -18,6 +19,7 @@ fn hydro_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
     register_submodule(py, m, &pet::make_module(py)?, "hydro_rs")?;
     register_submodule(py, m, &snow::make_module(py)?, "hydro_rs")?;
     register_submodule(py, m, &metrics::make_module(py)?, "hydro_rs")?;
+    register_submodule(py, m, &signatures::make_module(py)?, "hydro_rs")?;
 
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
 
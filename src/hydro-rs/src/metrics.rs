@@ -1,13 +1,52 @@
-use ndarray::ArrayView1;
-use numpy::PyReadonlyArray1;
+#![allow(clippy::type_complexity)]
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use ndarray::{
+    s, Array1, Array2, ArrayView1, ArrayView2, Axis, CowArray, Ix1,
+};
+use numpy::{
+    IntoPyArray, PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2,
+    ToPyArray,
+};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use thiserror::Error;
 
+// missing observations (NaN) are skipped, as long as enough are left
+pub(crate) const MIN_VALID_OBSERVATIONS: usize = 2;
+
+// default largest shift of `calculate_dtw`, in timesteps
+pub const DTW_WINDOW: usize = 3;
+
+// last day of year of each month, the leap day counting in december
+const MONTH_ENDS: [usize; 12] =
+    [31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334, 365];
+
+type Series<'a> = CowArray<'a, f64, Ix1>;
+// observations, simulations and weights
+type WeightedSeries = (Vec<f64>, Vec<f64>, Vec<f64>);
+
 #[derive(Error, Debug)]
 pub enum MetricsError {
     #[error("observations and simulations must have the same length (got {0} and {1})")]
     LengthMismatch(usize, usize),
+    #[error("at least {MIN_VALID_OBSERVATIONS} observations must be valid (got {0})")]
+    NotEnoughObservations(usize),
+    #[error("observations and day_of_year must have the same length (got {0} and {1})")]
+    DayOfYearLengthMismatch(usize, usize),
+    #[error("no observation exceeds the peak threshold")]
+    NoPeaks,
+    #[error(
+        "observations and data must have the same length (got {0} and {1})"
+    )]
+    DataLengthMismatch(usize, usize),
+    #[error("the threshold must be exceeded by some but not all observations")]
+    ConstantExceedance,
+    #[error("n_bins must be positive")]
+    NoBins,
 }
 
 impl From<MetricsError> for PyErr {
@@ -20,7 +59,9 @@ pub fn calculate_rmse(
     observations: ArrayView1<f64>,
     simulations: ArrayView1<f64>,
 ) -> Result<f64, MetricsError> {
-    check_lengths(observations, simulations)?;
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let (observations, simulations) =
+        (observations.view(), simulations.view());
     let sum: f64 = observations
         .iter()
         .zip(simulations)
@@ -33,7 +74,9 @@ pub fn calculate_nse(
     observations: ArrayView1<f64>,
     simulations: ArrayView1<f64>,
 ) -> Result<f64, MetricsError> {
-    check_lengths(observations, simulations)?;
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let (observations, simulations) =
+        (observations.view(), simulations.view());
     let mean: f64 =
         observations.iter().sum::<f64>() / observations.len() as f64;
     let (numerator, denominator) = observations.iter().zip(simulations).fold(
@@ -49,7 +92,66 @@ pub fn calculate_kge(
     observations: ArrayView1<f64>,
     simulations: ArrayView1<f64>,
 ) -> Result<f64, MetricsError> {
-    check_lengths(observations, simulations)?;
+    let (r, alpha, beta) = kge_components(observations, simulations)?;
+    Ok(1.
+        - ((r - 1.).powi(2) + (alpha - 1.).powi(2) + (beta - 1.).powi(2))
+            .sqrt())
+}
+
+/// KGE with its components, the correlation, the ratio of the standard
+/// deviations and the ratio of the means, to see which drives the score.
+pub fn calculate_kge_components(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(f64, f64, f64, f64), MetricsError> {
+    let (r, alpha, beta) = kge_components(observations, simulations)?;
+    let kge = 1.
+        - ((r - 1.).powi(2) + (alpha - 1.).powi(2) + (beta - 1.).powi(2))
+            .sqrt();
+    Ok((kge, r, alpha, beta))
+}
+
+/// NSE with its decomposition (Gupta et al., 2009), NSE = 2 alpha r -
+/// alpha^2 - beta_n^2, into the correlation, the ratio of the standard
+/// deviations and the bias normalized by the observed standard deviation.
+pub fn calculate_nse_components(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(f64, f64, f64, f64), MetricsError> {
+    let (r, alpha, beta) = kge_components(observations, simulations)?;
+    let (observations, _) = select_valid(observations, simulations)?;
+    let mean = observations.sum() / observations.len() as f64;
+    let std = (observations.iter().map(|o| (o - mean).powi(2)).sum::<f64>()
+        / observations.len() as f64)
+        .sqrt();
+    let beta_n = (beta - 1.) * mean / std;
+    let nse = 2. * alpha * r - alpha.powi(2) - beta_n.powi(2);
+    Ok((nse, r, alpha, beta_n))
+}
+
+/// Modified Kling-Gupta efficiency (Kling et al., 2012), using the ratio of
+/// the coefficients of variation instead of the standard deviations so the
+/// bias and variability terms aren't cross-correlated.
+pub fn calculate_kge_2012(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (r, alpha, beta) = kge_components(observations, simulations)?;
+    let gamma = alpha / beta;
+    Ok(1.
+        - ((r - 1.).powi(2) + (gamma - 1.).powi(2) + (beta - 1.).powi(2))
+            .sqrt())
+}
+
+/// Correlation, ratio of the standard deviations and ratio of the means of
+/// the simulations to the observations.
+fn kge_components(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(f64, f64, f64), MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let (observations, simulations) =
+        (observations.view(), simulations.view());
     let observations_mean =
         observations.iter().sum::<f64>() / observations.len() as f64;
     let observations_mean_2 =
@@ -78,65 +180,2498 @@ pub fn calculate_kge(
     let alpha: f64 = simulations_std / observations_std;
     let beta: f64 = simulations_mean / observations_mean;
 
-    Ok(1.
+    Ok((r, alpha, beta))
+}
+
+/// Nash-Sutcliffe efficiency on the logarithm of the discharge offset by
+/// `epsilon`, emphasizing the low flows. By default, `epsilon` is a
+/// hundredth of the mean observed discharge, so zero flows can be handled.
+pub fn calculate_log_nse(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    epsilon: Option<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) =
+        offset_pair(observations, simulations, epsilon, f64::ln)?;
+    calculate_nse(observations.view(), simulations.view())
+}
+
+/// Nash-Sutcliffe efficiency on the square root of the discharge, balancing
+/// the high and low flows.
+pub fn calculate_nse_sqrt(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) =
+        offset_pair(observations, simulations, Some(0.), f64::sqrt)?;
+    calculate_nse(observations.view(), simulations.view())
+}
+
+/// Nash-Sutcliffe efficiency on the inverse of the discharge offset by
+/// `epsilon`, focusing on the lowest flows. By default, `epsilon` is a
+/// hundredth of the mean observed discharge.
+pub fn calculate_nse_inv(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    epsilon: Option<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) =
+        offset_pair(observations, simulations, epsilon, f64::recip)?;
+    calculate_nse(observations.view(), simulations.view())
+}
+
+/// Percent bias of the simulated volume, positive when the simulations
+/// overestimate the observations.
+pub fn calculate_pbias(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    Ok(100. * (simulations.sum() - observations.sum()) / observations.sum())
+}
+
+/// RMSE-observations standard deviation ratio (Moriasi et al., 2007), zero
+/// for a perfect fit.
+pub fn calculate_rsr(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let (observations, simulations) =
+        (observations.view(), simulations.view());
+    let mean = observations.sum() / observations.len() as f64;
+    let (numerator, denominator) = observations.iter().zip(simulations).fold(
+        (0.0, 0.0),
+        |(num, den), (&o, &p)| {
+            (num + (o - p).powi(2), den + (o - mean).powi(2))
+        },
+    );
+    Ok((numerator / denominator).sqrt())
+}
+
+/// Mean absolute error, less sensitive to the largest errors than the RMSE.
+pub fn calculate_mae(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let sum: f64 = observations
+        .iter()
+        .zip(simulations.iter())
+        .map(|(o, p)| (o - p).abs())
+        .sum();
+    Ok(sum / observations.len() as f64)
+}
+
+/// Mean absolute percentage error, the errors being relative to the
+/// observations offset by `epsilon` so zero flows can be handled. By
+/// default, `epsilon` is a hundredth of the mean observed discharge.
+pub fn calculate_mape(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    epsilon: Option<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let epsilon =
+        epsilon.unwrap_or_else(|| default_epsilon(observations.view()));
+    let sum: f64 = observations
+        .iter()
+        .zip(simulations.iter())
+        .map(|(o, p)| (p - o).abs() / (o.abs() + epsilon))
+        .sum();
+    Ok(100. * sum / observations.len() as f64)
+}
+
+/// Symmetric mean absolute percentage error, the errors being relative to
+/// the mean of the observation and simulation offset by `epsilon`, so
+/// overestimations aren't penalized more than underestimations. Between 0
+/// and 200 %, with the same default `epsilon` as `calculate_mape`.
+pub fn calculate_smape(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    epsilon: Option<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let epsilon =
+        epsilon.unwrap_or_else(|| default_epsilon(observations.view()));
+    let sum: f64 = observations
+        .iter()
+        .zip(simulations.iter())
+        .map(|(o, p)| (p - o).abs() / ((o.abs() + p.abs()) / 2. + epsilon))
+        .sum();
+    Ok(100. * sum / observations.len() as f64)
+}
+
+/// Volumetric efficiency (Criss and Winston, 2008), the fraction of the
+/// observed volume matched at each timestep.
+pub fn calculate_volumetric_efficiency(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let error: f64 = observations
+        .iter()
+        .zip(simulations.iter())
+        .map(|(o, p)| (p - o).abs())
+        .sum();
+    Ok(1. - error / observations.sum())
+}
+
+/// Willmott's index of agreement d (Willmott, 1981), between 0 and 1 for a
+/// perfect fit.
+pub fn calculate_index_of_agreement(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let (observations, simulations) =
+        (observations.view(), simulations.view());
+    let mean = observations.sum() / observations.len() as f64;
+    let (numerator, denominator) = observations.iter().zip(simulations).fold(
+        (0.0, 0.0),
+        |(num, den), (&o, &p)| {
+            (
+                num + (p - o).powi(2),
+                den + ((p - mean).abs() + (o - mean).abs()).powi(2),
+            )
+        },
+    );
+    Ok(1. - numerator / denominator)
+}
+
+/// Refined index of agreement dr (Willmott et al., 2012), between -1 and 1
+/// for a perfect fit, based on the absolute errors instead of the squared
+/// ones.
+pub fn calculate_refined_index_of_agreement(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let (observations, simulations) =
+        (observations.view(), simulations.view());
+    let mean = observations.sum() / observations.len() as f64;
+    let (error, deviation) = observations.iter().zip(simulations).fold(
+        (0.0, 0.0),
+        |(error, deviation), (&o, &p)| {
+            (error + (p - o).abs(), deviation + 2. * (o - mean).abs())
+        },
+    );
+    if error <= deviation {
+        Ok(1. - error / deviation)
+    } else {
+        Ok(deviation / error - 1.)
+    }
+}
+
+/// Pearson correlation coefficient between the observations and
+/// simulations.
+pub fn calculate_pearson(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (r, _, _) = kge_components(observations, simulations)?;
+    Ok(r)
+}
+
+/// Coefficient of determination, as the square of the Pearson correlation.
+pub fn calculate_r2(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    Ok(calculate_pearson(observations, simulations)?.powi(2))
+}
+
+/// Spearman rank correlation coefficient, the Pearson correlation of the
+/// ranks, tied values sharing their mean rank.
+pub fn calculate_spearman(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) = select_valid(observations, simulations)?;
+    let observations = rank(observations.view());
+    let simulations = rank(simulations.view());
+    calculate_pearson(observations.view(), simulations.view())
+}
+
+/// Persistence index (Kitanidis and Bras, 1980), the skill against
+/// forecasting the previous observation, positive when the simulations beat
+/// it. Only the timesteps following a valid observation count.
+pub fn calculate_persistence_index(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    check_lengths(observations, simulations)?;
+    let timesteps: Vec<usize> = (1..observations.len())
+        .filter(|&t| {
+            !observations[t].is_nan() && !observations[t - 1].is_nan()
+        })
+        .collect();
+    if timesteps.len() < MIN_VALID_OBSERVATIONS {
+        return Err(MetricsError::NotEnoughObservations(timesteps.len()));
+    }
+    let (numerator, denominator) =
+        timesteps.iter().fold((0.0, 0.0), |(num, den), &t| {
+            (
+                num + (observations[t] - simulations[t]).powi(2),
+                den + (observations[t] - observations[t - 1]).powi(2),
+            )
+        });
+    Ok(1. - numerator / denominator)
+}
+
+/// All the scalar metrics of the observations and simulations with their
+/// default settings, keyed by name, the sums shared by the metrics being
+/// computed once. The KGE components are `pearson`, `alpha` and `beta`,
+/// `beta_n` is the normalized bias of `calculate_nse_components` and
+/// `dtw_timing` is the mean shift of `calculate_dtw`. The persistence index
+/// and the DTW keep the series in place, skipping the missing observations
+/// without joining the timesteps around them.
+pub fn summarize(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<Vec<(&'static str, f64)>, MetricsError> {
+    let (valid_observations, valid_simulations) =
+        select_valid(observations, simulations)?;
+    let (valid_observations, valid_simulations) =
+        (valid_observations.view(), valid_simulations.view());
+    let n = valid_observations.len() as f64;
+
+    let (mut observations_sum, mut simulations_sum) = (0., 0.);
+    let (mut observations_sum_2, mut simulations_sum_2) = (0., 0.);
+    let mut observations_simulations_sum = 0.;
+    let (mut squared_error, mut absolute_error) = (0., 0.);
+    for (&o, &p) in valid_observations.iter().zip(valid_simulations) {
+        observations_sum += o;
+        simulations_sum += p;
+        observations_sum_2 += o.powi(2);
+        simulations_sum_2 += p.powi(2);
+        observations_simulations_sum += o * p;
+        squared_error += (o - p).powi(2);
+        absolute_error += (o - p).abs();
+    }
+    let observations_mean = observations_sum / n;
+    let simulations_mean = simulations_sum / n;
+
+    let (mut deviation, mut absolute_deviation, mut agreement) = (0., 0., 0.);
+    for (&o, &p) in valid_observations.iter().zip(valid_simulations) {
+        deviation += (o - observations_mean).powi(2);
+        absolute_deviation += 2. * (o - observations_mean).abs();
+        agreement += ((p - observations_mean).abs()
+            + (o - observations_mean).abs())
+        .powi(2);
+    }
+
+    let observations_std =
+        (observations_sum_2 / n - observations_mean.powi(2)).sqrt();
+    let simulations_std =
+        (simulations_sum_2 / n - simulations_mean.powi(2)).sqrt();
+    let r = (observations_simulations_sum / n
+        - observations_mean * simulations_mean)
+        / (observations_std * simulations_std);
+    let alpha = simulations_std / observations_std;
+    let beta = simulations_mean / observations_mean;
+    let kge = 1.
         - ((r - 1.).powi(2) + (alpha - 1.).powi(2) + (beta - 1.).powi(2))
-            .sqrt())
+            .sqrt();
+    let gamma = alpha / beta;
+    let kge_2012 = 1.
+        - ((r - 1.).powi(2) + (gamma - 1.).powi(2) + (beta - 1.).powi(2))
+            .sqrt();
+    let nse = 1. - squared_error / deviation;
+    let refined_index_of_agreement = if absolute_error <= absolute_deviation {
+        1. - absolute_error / absolute_deviation
+    } else {
+        absolute_deviation / absolute_error - 1.
+    };
+
+    let (inverse_observations, inverse_simulations) =
+        offset_pair(observations, simulations, None, f64::recip)?;
+    let (inverse_observations, inverse_simulations) =
+        (inverse_observations.view(), inverse_simulations.view());
+    let (dtw, dtw_timing) =
+        calculate_dtw(observations, simulations, DTW_WINDOW)?;
+
+    Ok(vec![
+        ("rmse", (squared_error / n).sqrt()),
+        ("nse", nse),
+        ("kge", kge),
+        ("pearson", r),
+        ("alpha", alpha),
+        ("beta", beta),
+        (
+            "beta_n",
+            (simulations_mean - observations_mean) / observations_std,
+        ),
+        ("kge_2012", kge_2012),
+        (
+            "log_nse",
+            calculate_log_nse(observations, simulations, None)?,
+        ),
+        ("nse_sqrt", calculate_nse_sqrt(observations, simulations)?),
+        (
+            "nse_inv",
+            calculate_nse(inverse_observations, inverse_simulations)?,
+        ),
+        (
+            "kge_inv",
+            calculate_kge(inverse_observations, inverse_simulations)?,
+        ),
+        (
+            "pbias",
+            100. * (simulations_sum - observations_sum) / observations_sum,
+        ),
+        ("rsr", (squared_error / deviation).sqrt()),
+        ("mae", absolute_error / n),
+        ("mape", calculate_mape(observations, simulations, None)?),
+        ("smape", calculate_smape(observations, simulations, None)?),
+        (
+            "volumetric_efficiency",
+            1. - absolute_error / observations_sum,
+        ),
+        ("index_of_agreement", 1. - squared_error / agreement),
+        ("refined_index_of_agreement", refined_index_of_agreement),
+        ("r2", r.powi(2)),
+        ("spearman", calculate_spearman(observations, simulations)?),
+        ("bounded_nse", nse / (2. - nse)),
+        ("bounded_kge", kge / (2. - kge)),
+        (
+            "persistence_index",
+            calculate_persistence_index(observations, simulations)?,
+        ),
+        ("dtw", dtw),
+        ("dtw_timing", dtw_timing),
+    ])
 }
 
-fn check_lengths(
+/// Mean and worst KGE over the hydrological years starting on day
+/// `start_day`, exposing poorly simulated years which the KGE over the
+/// whole record hides. Years without enough observations are skipped. It
+/// evaluates a simulation after the fact, the calibration objectives not
+/// knowing the day of year.
+pub fn calculate_split_kge(
     observations: ArrayView1<f64>,
     simulations: ArrayView1<f64>,
-) -> Result<(), MetricsError> {
-    if observations.len() != simulations.len() {
-        Err(MetricsError::LengthMismatch(
+    day_of_year: ArrayView1<usize>,
+    start_day: usize,
+) -> Result<(f64, f64), MetricsError> {
+    check_lengths(observations, simulations)?;
+    if observations.len() != day_of_year.len() {
+        return Err(MetricsError::DayOfYearLengthMismatch(
             observations.len(),
-            simulations.len(),
-        ))
+            day_of_year.len(),
+        ));
+    }
+    let kges: Vec<f64> = hydrological_years(day_of_year, start_day)
+        .into_iter()
+        .filter_map(|year| {
+            calculate_kge(
+                observations.slice(s![year.clone()]),
+                simulations.slice(s![year]),
+            )
+            .ok()
+        })
+        .collect();
+    if kges.is_empty() {
+        return Err(MetricsError::NotEnoughObservations(
+            observations.iter().filter(|o| !o.is_nan()).count(),
+        ));
+    }
+    let mean = kges.iter().sum::<f64>() / kges.len() as f64;
+    let worst = kges.iter().copied().fold(f64::INFINITY, f64::min);
+    Ok((mean, worst))
+}
+
+/// Independent peak-over-threshold events of the observations, the
+/// exceedances of their `quantile` less than `separation` timesteps apart
+/// belonging to the same event. Returns the timesteps from the first to
+/// the last exceedance of each event.
+pub fn find_events(
+    observations: ArrayView1<f64>,
+    quantile: f64,
+    separation: usize,
+) -> Vec<Range<usize>> {
+    let mut valid: Vec<f64> = observations
+        .iter()
+        .copied()
+        .filter(|o| !o.is_nan())
+        .collect();
+    if valid.is_empty() {
+        return vec![];
+    }
+    valid.sort_by(f64::total_cmp);
+    let threshold = valid
+        [(quantile.clamp(0., 1.) * (valid.len() - 1) as f64).round() as usize];
+
+    let mut events: Vec<Range<usize>> = vec![];
+    for t in (0..observations.len()).filter(|&t| observations[t] > threshold) {
+        match events.last_mut() {
+            Some(event) if t + 1 - event.end < separation => event.end = t + 1,
+            _ => events.push(t..t + 1),
+        }
+    }
+    events
+}
+
+/// Timesteps of the observed peak of each event of `find_events`.
+pub fn find_peaks(
+    observations: ArrayView1<f64>,
+    quantile: f64,
+    separation: usize,
+) -> Vec<usize> {
+    find_events(observations, quantile, separation)
+        .into_iter()
+        .filter_map(|event| argmax(observations, event))
+        .collect()
+}
+
+/// Mean and median absolute relative error of the simulated peaks of the
+/// events of `find_events`, and the relative error of the largest event,
+/// positive when the simulations overestimate it. The simulated peak is the
+/// largest simulation within `separation` timesteps of the event, so small
+/// timing errors don't count.
+pub fn calculate_peak_errors(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    quantile: f64,
+    separation: usize,
+) -> Result<(f64, f64, f64), MetricsError> {
+    check_lengths(observations, simulations)?;
+    let n_timesteps = observations.len();
+    // observed peak and relative error of each event
+    let peaks: Vec<(f64, f64)> =
+        find_events(observations, quantile, separation)
+            .into_iter()
+            .filter_map(|event| {
+                let peak = observations[argmax(observations, event.clone())?];
+                let window = event_window(event, separation, n_timesteps);
+                let simulated = simulations
+                    .slice(s![window])
+                    .fold(f64::NAN, |max, &p| max.max(p));
+                Some((peak, (simulated - peak) / peak))
+            })
+            .collect();
+    if peaks.is_empty() {
+        return Err(MetricsError::NoPeaks);
+    }
+
+    let mut errors: Vec<f64> =
+        peaks.iter().map(|(_, error)| error.abs()).collect();
+    errors.sort_by(f64::total_cmp);
+    let mean = errors.iter().sum::<f64>() / errors.len() as f64;
+    let middle = errors.len() / 2;
+    let median = if errors.len().is_multiple_of(2) {
+        (errors[middle - 1] + errors[middle]) / 2.
     } else {
-        Ok(())
+        errors[middle]
+    };
+    let (_, largest) = peaks
+        .iter()
+        .copied()
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .unwrap_or((f64::NAN, f64::NAN));
+    Ok((mean, median, largest))
+}
+
+/// Mean and mean absolute timing error, in timesteps, of the simulated
+/// peaks of the events of `find_events`, positive when the simulations
+/// peak late. The simulated peak is the largest simulation within
+/// `separation` timesteps of the event.
+pub fn calculate_peak_timing(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    quantile: f64,
+    separation: usize,
+) -> Result<(f64, f64), MetricsError> {
+    check_lengths(observations, simulations)?;
+    let n_timesteps = observations.len();
+    let errors: Vec<f64> = find_events(observations, quantile, separation)
+        .into_iter()
+        .filter_map(|event| {
+            let observed = argmax(observations, event.clone())?;
+            let window = event_window(event, separation, n_timesteps);
+            let simulated = argmax(simulations, window)?;
+            Some(simulated as f64 - observed as f64)
+        })
+        .collect();
+    if errors.is_empty() {
+        return Err(MetricsError::NoPeaks);
     }
+    let n = errors.len() as f64;
+    Ok((
+        errors.iter().sum::<f64>() / n,
+        errors.iter().map(|e| e.abs()).sum::<f64>() / n,
+    ))
 }
 
-#[pyfunction]
-#[pyo3(name = "calculate_rmse")]
-pub fn py_calculate_rmse<'py>(
-    observations: PyReadonlyArray1<'py, f64>,
-    simulations: PyReadonlyArray1<'py, f64>,
-) -> PyResult<f64> {
-    Ok(calculate_rmse(
-        observations.as_array(),
-        simulations.as_array(),
-    )?)
+/// Lag, in timesteps between `-max_lag` and `max_lag`, maximizing the
+/// correlation of the simulations with the observations, positive when the
+/// simulations are late.
+pub fn calculate_lag(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    max_lag: usize,
+) -> Result<isize, MetricsError> {
+    check_lengths(observations, simulations)?;
+    let n_timesteps = observations.len();
+    let max_lag = max_lag.min(n_timesteps.saturating_sub(1)) as isize;
+    let correlation = |lag: isize| {
+        let shift = lag.unsigned_abs();
+        let (observations, simulations) = if lag >= 0 {
+            (
+                observations.slice(s![..n_timesteps - shift]),
+                simulations.slice(s![shift..]),
+            )
+        } else {
+            (
+                observations.slice(s![shift..]),
+                simulations.slice(s![..n_timesteps - shift]),
+            )
+        };
+        calculate_pearson(observations, simulations)
+            .ok()
+            .filter(|r| !r.is_nan())
+    };
+    (-max_lag..=max_lag)
+        .filter_map(|lag| Some((lag, correlation(lag)?)))
+        .max_by(|(a, r_a), (b, r_b)| {
+            // the smallest lag wins ties
+            r_a.total_cmp(r_b).then(b.abs().cmp(&a.abs()))
+        })
+        .map(|(lag, _)| lag)
+        .ok_or(MetricsError::NotEnoughObservations(
+            observations.iter().filter(|o| !o.is_nan()).count(),
+        ))
 }
 
-#[pyfunction]
-#[pyo3(name = "calculate_nse")]
-pub fn py_calculate_nse<'py>(
-    observations: PyReadonlyArray1<'py, f64>,
-    simulations: PyReadonlyArray1<'py, f64>,
-) -> PyResult<f64> {
-    Ok(calculate_nse(
-        observations.as_array(),
-        simulations.as_array(),
-    )?)
+/// Series distance after a dynamic time warping of the simulations onto the
+/// observations, the shifts being constrained to `window` timesteps (Sakoe
+/// and Chiba, 1978), separating the magnitude errors from the timing
+/// errors. Returns the mean absolute error and the mean absolute shift, in
+/// timesteps, along the warping path, the former being the MAE when
+/// `window` is 0. The series are warped separately between the missing
+/// observations, so no shift reaches across a gap.
+pub fn calculate_dtw(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    window: usize,
+) -> Result<(f64, f64), MetricsError> {
+    check_lengths(observations, simulations)?;
+    let n_valid = observations.iter().filter(|o| !o.is_nan()).count();
+    if n_valid < MIN_VALID_OBSERVATIONS {
+        return Err(MetricsError::NotEnoughObservations(n_valid));
+    }
+    let (mut error, mut shift, mut n) = (0., 0., 0);
+    for run in valid_runs(observations) {
+        let observations = observations.slice(s![run.clone()]);
+        let simulations = simulations.slice(s![run]);
+        for (i, j) in warping_path(observations, simulations, window) {
+            error += (observations[i] - simulations[j]).abs();
+            shift += i.abs_diff(j) as f64;
+            n += 1;
+        }
+    }
+    Ok((error / n as f64, shift / n as f64))
 }
 
-#[pyfunction]
-#[pyo3(name = "calculate_kge")]
-pub fn py_calculate_kge<'py>(
-    observations: PyReadonlyArray1<'py, f64>,
-    simulations: PyReadonlyArray1<'py, f64>,
-) -> PyResult<f64> {
-    Ok(calculate_kge(
-        observations.as_array(),
-        simulations.as_array(),
-    )?)
+/// How `segment_events` delimits the events, `Baseflow` extending the
+/// events of `find_events` until the observations fall back to the
+/// baseflow.
+#[derive(Debug, Clone, Copy)]
+pub enum EventMethod {
+    Threshold,
+    Baseflow,
 }
 
-pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
-    let m = PyModule::new(py, "metrics")?;
-    m.add_function(wrap_pyfunction!(py_calculate_rmse, &m)?)?;
-    m.add_function(wrap_pyfunction!(py_calculate_nse, &m)?)?;
-    m.add_function(wrap_pyfunction!(py_calculate_kge, &m)?)?;
-    Ok(m)
+impl FromStr for EventMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "threshold" => Ok(Self::Threshold),
+            "baseflow" => Ok(Self::Baseflow),
+            _ => Err(format!(
+                "Unknown event method '{}'. Valid options: threshold, \
+                 baseflow",
+                s
+            )),
+        }
+    }
+}
+
+/// Events of the observations, the baseflow ones starting when the
+/// quickflow of the Lyne-Hollick filter rises above a hundredth of the mean
+/// observed discharge and ending when it falls back below, overlapping
+/// events being merged.
+pub fn segment_events(
+    observations: ArrayView1<f64>,
+    method: EventMethod,
+    quantile: f64,
+    separation: usize,
+) -> Vec<Range<usize>> {
+    let events = find_events(observations, quantile, separation);
+    if matches!(method, EventMethod::Threshold) {
+        return events;
+    }
+
+    let n_timesteps = observations.len();
+    let quickflow = quickflow(observations);
+    let (sum, n) = observations
+        .iter()
+        .filter(|o| !o.is_nan())
+        .fold((0., 0), |(sum, n), o| (sum + o, n + 1));
+    let tolerance = 0.01 * (sum / n.max(1) as f64).max(0.);
+    let mut extended: Vec<Range<usize>> = vec![];
+    for event in events {
+        let mut start = event.start;
+        while start > 0 && quickflow[start - 1] > tolerance {
+            start -= 1;
+        }
+        let mut end = event.end;
+        while end < n_timesteps && quickflow[end] > tolerance {
+            end += 1;
+        }
+        match extended.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => extended.push(start..end),
+        }
+    }
+    extended
+}
+
+/// Start, end (exclusive), observed and simulated volumes and peaks, and
+/// peak timing error (positive when late) of each event. The volumes only
+/// cover the timesteps with a valid observation, and the simulated peak is
+/// the largest simulation within `separation` timesteps of the event.
+#[derive(Debug, Clone, Default)]
+pub struct EventTable {
+    pub start: Vec<usize>,
+    pub end: Vec<usize>,
+    pub observed_volume: Vec<f64>,
+    pub simulated_volume: Vec<f64>,
+    pub observed_peak: Vec<f64>,
+    pub simulated_peak: Vec<f64>,
+    pub timing_error: Vec<f64>,
+}
+
+impl EventTable {
+    pub fn to_py_dict<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("start", self.start.to_pyarray(py))?;
+        dict.set_item("end", self.end.to_pyarray(py))?;
+        dict.set_item("observed_volume", self.observed_volume.to_pyarray(py))?;
+        dict.set_item(
+            "simulated_volume",
+            self.simulated_volume.to_pyarray(py),
+        )?;
+        dict.set_item("observed_peak", self.observed_peak.to_pyarray(py))?;
+        dict.set_item("simulated_peak", self.simulated_peak.to_pyarray(py))?;
+        dict.set_item("timing_error", self.timing_error.to_pyarray(py))?;
+        Ok(dict)
+    }
+}
+
+/// Table of the events of `segment_events`, to verify the simulations
+/// event by event.
+pub fn event_table(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    method: EventMethod,
+    quantile: f64,
+    separation: usize,
+) -> Result<EventTable, MetricsError> {
+    check_lengths(observations, simulations)?;
+    let n_timesteps = observations.len();
+    let mut table = EventTable::default();
+    for event in segment_events(observations, method, quantile, separation) {
+        let Some(observed) = argmax(observations, event.clone()) else {
+            continue;
+        };
+        let window = event_window(event.clone(), separation, n_timesteps);
+        let simulated = argmax(simulations, window);
+        let (observed_volume, simulated_volume) = event
+            .clone()
+            .filter(|&t| !observations[t].is_nan())
+            .fold((0., 0.), |(o, p), t| {
+                (o + observations[t], p + simulations[t])
+            });
+
+        table.start.push(event.start);
+        table.end.push(event.end);
+        table.observed_volume.push(observed_volume);
+        table.simulated_volume.push(simulated_volume);
+        table.observed_peak.push(observations[observed]);
+        table
+            .simulated_peak
+            .push(simulated.map_or(f64::NAN, |t| simulations[t]));
+        table
+            .timing_error
+            .push(simulated.map_or(f64::NAN, |t| t as f64 - observed as f64));
+    }
+    Ok(table)
+}
+
+/// NSE, KGE and percent bias (columns) of each calendar month and of each
+/// season (rows), the seasons being winter (december to february), spring,
+/// summer and autumn. A month or season without enough observations is
+/// missing.
+pub fn calculate_seasonal_metrics(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    day_of_year: ArrayView1<usize>,
+) -> Result<(Array2<f64>, Array2<f64>), MetricsError> {
+    check_lengths(observations, simulations)?;
+    if observations.len() != day_of_year.len() {
+        return Err(MetricsError::DayOfYearLengthMismatch(
+            observations.len(),
+            day_of_year.len(),
+        ));
+    }
+    let months = day_of_year.mapv(month);
+    let seasons = months.mapv(|month| (month + 1) % 12 / 3);
+    Ok((
+        group_metrics(observations, simulations, months.view(), 12),
+        group_metrics(observations, simulations, seasons.view(), 4),
+    ))
+}
+
+/// Calendar-day climatology of the observations, the interannual mean of
+/// the valid observations of each day of year, missing for days without
+/// any.
+pub fn calculate_climatology(
+    observations: ArrayView1<f64>,
+    day_of_year: ArrayView1<usize>,
+) -> Result<Array1<f64>, MetricsError> {
+    if observations.len() != day_of_year.len() {
+        return Err(MetricsError::DayOfYearLengthMismatch(
+            observations.len(),
+            day_of_year.len(),
+        ));
+    }
+    let n_days = day_of_year.iter().max().map_or(0, |&day| day + 1);
+    let mut sums = vec![0.; n_days];
+    let mut counts = vec![0; n_days];
+    for (&o, &day) in observations.iter().zip(day_of_year) {
+        if !o.is_nan() {
+            sums[day] += o;
+            counts[day] += 1;
+        }
+    }
+    Ok(day_of_year.mapv(|day| sums[day] / counts[day] as f64))
+}
+
+/// Benchmark efficiency (Schaefli and Gupta, 2007), the NSE with the
+/// calendar-day climatology as the reference instead of the mean
+/// observation, positive when the simulations beat the seasonal cycle.
+pub fn calculate_benchmark_nse(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    day_of_year: ArrayView1<usize>,
+) -> Result<f64, MetricsError> {
+    let benchmark = calculate_climatology(observations, day_of_year)?;
+    // the squared errors over the observed variance cancel out
+    let nse = calculate_nse(observations, simulations)?;
+    let benchmark_nse = calculate_nse(observations, benchmark.view())?;
+    Ok(1. - (1. - nse) / (1. - benchmark_nse))
+}
+
+/// KGE skill score against the calendar-day climatology, the improvement of
+/// the KGE over the climatology's relative to a perfect KGE.
+pub fn calculate_benchmark_kge(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    day_of_year: ArrayView1<usize>,
+) -> Result<f64, MetricsError> {
+    let benchmark = calculate_climatology(observations, day_of_year)?;
+    let kge = calculate_kge(observations, simulations)?;
+    let benchmark_kge = calculate_kge(observations, benchmark.view())?;
+    Ok((kge - benchmark_kge) / (1. - benchmark_kge))
+}
+
+/// Probability of exceeding `threshold` at each timestep, the fraction of
+/// the members of the ensemble (members x timesteps) above it.
+pub fn calculate_exceedance_probabilities(
+    ensemble: ArrayView2<f64>,
+    threshold: f64,
+) -> Array1<f64> {
+    let n_members = ensemble.nrows() as f64;
+    ensemble.map_axis(Axis(0), |members| {
+        members.iter().filter(|&&q| q > threshold).count() as f64 / n_members
+    })
+}
+
+/// Brier score of the probabilities of exceeding `threshold`, the mean
+/// squared difference with the observed exceedances, zero being perfect.
+pub fn calculate_brier_score(
+    observations: ArrayView1<f64>,
+    probabilities: ArrayView1<f64>,
+    threshold: f64,
+) -> Result<f64, MetricsError> {
+    let (observations, probabilities) =
+        select_valid(observations, probabilities)?;
+    let sum: f64 = observations
+        .iter()
+        .zip(probabilities.iter())
+        .map(|(&o, p)| (p - exceeds(o, threshold)).powi(2))
+        .sum();
+    Ok(sum / observations.len() as f64)
+}
+
+/// Brier skill score of the probabilities of exceeding `threshold` against
+/// the climatology, always forecasting the observed exceedance frequency,
+/// which has no skill to beat when the threshold is never or always
+/// exceeded.
+pub fn calculate_brier_skill_score(
+    observations: ArrayView1<f64>,
+    probabilities: ArrayView1<f64>,
+    threshold: f64,
+) -> Result<f64, MetricsError> {
+    let score = calculate_brier_score(observations, probabilities, threshold)?;
+    let (observations, _) = select_valid(observations, probabilities)?;
+    let frequency = observations
+        .iter()
+        .map(|&o| exceeds(o, threshold))
+        .sum::<f64>()
+        / observations.len() as f64;
+    if frequency == 0. || frequency == 1. {
+        return Err(MetricsError::ConstantExceedance);
+    }
+    Ok(1. - score / (frequency * (1. - frequency)))
+}
+
+/// Rank histogram of the observations in the ensemble (members x
+/// timesteps), the number of timesteps where the observation is preceded
+/// by 0 to all the members, flat for a reliable ensemble. The observations
+/// tied with members are ranked in the middle of them.
+pub fn calculate_rank_histogram(
+    observations: ArrayView1<f64>,
+    ensemble: ArrayView2<f64>,
+) -> Result<Array1<usize>, MetricsError> {
+    if observations.len() != ensemble.ncols() {
+        return Err(MetricsError::LengthMismatch(
+            observations.len(),
+            ensemble.ncols(),
+        ));
+    }
+    let mut counts = Array1::zeros(ensemble.nrows() + 1);
+    for (&o, members) in observations.iter().zip(ensemble.columns()) {
+        if o.is_nan() {
+            continue;
+        }
+        let below = members.iter().filter(|&&q| q < o).count();
+        let tied = members.iter().filter(|&&q| q == o).count();
+        counts[below + tied / 2] += 1;
+    }
+    Ok(counts)
+}
+
+/// Reliability diagram of the probabilities of exceeding `threshold`, with
+/// `n_bins` bins of equal width between 0 and 1: the mean probability, the
+/// observed exceedance frequency and the number of timesteps of each bin,
+/// the empty bins being missing.
+pub fn calculate_reliability(
+    observations: ArrayView1<f64>,
+    probabilities: ArrayView1<f64>,
+    threshold: f64,
+    n_bins: usize,
+) -> Result<(Array1<f64>, Array1<f64>, Array1<usize>), MetricsError> {
+    if n_bins == 0 {
+        return Err(MetricsError::NoBins);
+    }
+    let (observations, probabilities) =
+        select_valid(observations, probabilities)?;
+    let mut probability = Array1::<f64>::zeros(n_bins);
+    let mut frequency = Array1::<f64>::zeros(n_bins);
+    let mut counts = Array1::<usize>::zeros(n_bins);
+    for (&o, &p) in observations.iter().zip(probabilities.iter()) {
+        let bin = ((p.clamp(0., 1.) * n_bins as f64) as usize).min(n_bins - 1);
+        probability[bin] += p;
+        frequency[bin] += exceeds(o, threshold);
+        counts[bin] += 1;
+    }
+    let n = counts.mapv(|count| count as f64);
+    Ok((probability / &n, frequency / &n, counts))
+}
+
+/// Bounded NSE (Mathevet et al., 2006), NSE / (2 - NSE), between -1 and 1
+/// so poor fits don't dominate averages over catchments.
+pub fn calculate_bounded_nse(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let nse = calculate_nse(observations, simulations)?;
+    Ok(nse / (2. - nse))
+}
+
+/// Bounded KGE, KGE / (2 - KGE), between -1 and 1 like the bounded NSE.
+pub fn calculate_bounded_kge(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let kge = calculate_kge(observations, simulations)?;
+    Ok(kge / (2. - kge))
+}
+
+/// Root mean square error in which each timestep's squared error is
+/// weighted, the timesteps with a zero weight being left out.
+pub fn calculate_weighted_rmse(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations, weights) =
+        select_weighted(observations, simulations, weights)?;
+    let total = weights.iter().sum::<f64>();
+    let sum: f64 = observations
+        .iter()
+        .zip(&simulations)
+        .zip(&weights)
+        .map(|((o, p), w)| w * (o - p).powi(2))
+        .sum();
+    Ok((sum / total).sqrt())
+}
+
+/// Nash-Sutcliffe efficiency in which each timestep is weighted, including
+/// in the mean of the observations.
+pub fn calculate_weighted_nse(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations, weights) =
+        select_weighted(observations, simulations, weights)?;
+    let mean = weighted_mean(&observations, &weights);
+    let (numerator, denominator) = observations
+        .iter()
+        .zip(&simulations)
+        .zip(&weights)
+        .fold((0.0, 0.0), |(num, den), ((&o, &p), &w)| {
+            (num + w * (o - p).powi(2), den + w * (o - mean).powi(2))
+        });
+    Ok(1.0 - numerator / denominator)
+}
+
+/// Kling-Gupta efficiency from the weighted means, standard deviations and
+/// correlation of the observations and simulations.
+pub fn calculate_weighted_kge(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (r, alpha, beta) =
+        weighted_kge_components(observations, simulations, weights)?;
+    Ok(1.
+        - ((r - 1.).powi(2) + (alpha - 1.).powi(2) + (beta - 1.).powi(2))
+            .sqrt())
+}
+
+/// `calculate_kge_components` from the weighted statistics.
+pub fn calculate_weighted_kge_components(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<(f64, f64, f64, f64), MetricsError> {
+    let (r, alpha, beta) =
+        weighted_kge_components(observations, simulations, weights)?;
+    let kge = 1.
+        - ((r - 1.).powi(2) + (alpha - 1.).powi(2) + (beta - 1.).powi(2))
+            .sqrt();
+    Ok((kge, r, alpha, beta))
+}
+
+/// `calculate_kge_2012` from the weighted statistics.
+pub fn calculate_weighted_kge_2012(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (r, alpha, beta) =
+        weighted_kge_components(observations, simulations, weights)?;
+    let gamma = alpha / beta;
+    Ok(1.
+        - ((r - 1.).powi(2) + (gamma - 1.).powi(2) + (beta - 1.).powi(2))
+            .sqrt())
+}
+
+fn weighted_kge_components(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<(f64, f64, f64), MetricsError> {
+    let (observations, simulations, weights) =
+        select_weighted(observations, simulations, weights)?;
+    let observations_mean = weighted_mean(&observations, &weights);
+    let simulations_mean = weighted_mean(&simulations, &weights);
+    let (observations_variance, simulations_variance, covariance) =
+        observations.iter().zip(&simulations).zip(&weights).fold(
+            (0.0, 0.0, 0.0),
+            |(vo, vp, c), ((&o, &p), &w)| {
+                let (do_, dp) = (o - observations_mean, p - simulations_mean);
+                (vo + w * do_ * do_, vp + w * dp * dp, c + w * do_ * dp)
+            },
+        );
+
+    let r: f64 =
+        covariance / (observations_variance * simulations_variance).sqrt();
+    let alpha: f64 = (simulations_variance / observations_variance).sqrt();
+    let beta: f64 = simulations_mean / observations_mean;
+
+    Ok((r, alpha, beta))
+}
+
+/// Mean absolute error in which each timestep's error is weighted.
+pub fn calculate_weighted_mae(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations, weights) =
+        select_weighted(observations, simulations, weights)?;
+    let sum: f64 = observations
+        .iter()
+        .zip(&simulations)
+        .zip(&weights)
+        .map(|((o, p), w)| w * (o - p).abs())
+        .sum();
+    Ok(sum / weights.iter().sum::<f64>())
+}
+
+/// `calculate_dtw` with each error weighted by the weight of its
+/// observation, the series being warped separately between the timesteps
+/// with missing observations or no weight.
+pub fn calculate_weighted_dtw(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+    window: usize,
+) -> Result<(f64, f64), MetricsError> {
+    check_lengths(observations, simulations)?;
+    check_lengths(observations, weights)?;
+    let runs = runs_where(observations.len(), |t| {
+        !observations[t].is_nan() && weights[t] > 0.
+    });
+    let n_valid: usize = runs.iter().map(|run| run.len()).sum();
+    if n_valid < MIN_VALID_OBSERVATIONS {
+        return Err(MetricsError::NotEnoughObservations(n_valid));
+    }
+    let (mut error, mut shift, mut total) = (0., 0., 0.);
+    for run in runs {
+        let observations = observations.slice(s![run.clone()]);
+        let simulations = simulations.slice(s![run.clone()]);
+        let weights = weights.slice(s![run]);
+        for (i, j) in warping_path(observations, simulations, window) {
+            error += weights[i] * (observations[i] - simulations[j]).abs();
+            shift += weights[i] * i.abs_diff(j) as f64;
+            total += weights[i];
+        }
+    }
+    Ok((error / total, shift / total))
+}
+
+/// `calculate_volumetric_efficiency` with each timestep weighted.
+pub fn calculate_weighted_volumetric_efficiency(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations, weights) =
+        select_weighted(observations, simulations, weights)?;
+    let (observed, error) = observations
+        .iter()
+        .zip(&simulations)
+        .zip(&weights)
+        .fold((0.0, 0.0), |(observed, error), ((&o, &p), &w)| {
+            (observed + w * o, error + w * (p - o).abs())
+        });
+    Ok(1. - error / observed)
+}
+
+/// `calculate_pbias` with each timestep's volume weighted.
+pub fn calculate_weighted_pbias(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations, weights) =
+        select_weighted(observations, simulations, weights)?;
+    let (observed, simulated) = observations
+        .iter()
+        .zip(&simulations)
+        .zip(&weights)
+        .fold((0.0, 0.0), |(observed, simulated), ((&o, &p), &w)| {
+            (observed + w * o, simulated + w * p)
+        });
+    Ok(100. * (simulated - observed) / observed)
+}
+
+/// `calculate_log_nse` with each timestep weighted.
+pub fn calculate_weighted_log_nse(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+    epsilon: Option<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) =
+        offset_pair(observations, simulations, epsilon, f64::ln)?;
+    calculate_weighted_nse(observations.view(), simulations.view(), weights)
+}
+
+/// `calculate_nse_sqrt` with each timestep weighted.
+pub fn calculate_weighted_nse_sqrt(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<f64, MetricsError> {
+    let (observations, simulations) =
+        offset_pair(observations, simulations, Some(0.), f64::sqrt)?;
+    calculate_weighted_nse(observations.view(), simulations.view(), weights)
+}
+
+/// `transform` of the observations and simulations offset by `epsilon`, or
+/// a hundredth of the mean observed discharge, negative flows counting as
+/// zero and missing observations staying missing.
+pub(crate) fn offset_pair(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    epsilon: Option<f64>,
+    transform: fn(f64) -> f64,
+) -> Result<(Array1<f64>, Array1<f64>), MetricsError> {
+    check_lengths(observations, simulations)?;
+    let epsilon = epsilon.unwrap_or_else(|| default_epsilon(observations));
+    let offset = |x: f64| {
+        if x.is_nan() {
+            x
+        } else {
+            transform(x.max(0.) + epsilon)
+        }
+    };
+    Ok((observations.mapv(offset), simulations.mapv(offset)))
+}
+
+/// Warping path of `calculate_dtw`, the (observation, simulation) index
+/// pairs from the start to the end of the series minimizing the sum of the
+/// absolute errors, with shifts of at most `window` timesteps.
+fn warping_path(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    window: usize,
+) -> Vec<(usize, usize)> {
+    let n_timesteps = observations.len();
+    // cumulated costs in a band, column `j + window - i` holding the
+    // simulation `j`
+    let mut costs =
+        Array2::from_elem((n_timesteps, 2 * window + 1), f64::INFINITY);
+    let cost = |costs: &Array2<f64>, i: usize, j: usize| {
+        if i.abs_diff(j) > window {
+            f64::INFINITY
+        } else {
+            costs[[i, j + window - i]]
+        }
+    };
+    let predecessors = |i: usize, j: usize| {
+        [
+            (i > 0 && j > 0).then(|| (i - 1, j - 1)),
+            (i > 0).then(|| (i - 1, j)),
+            (j > 0).then(|| (i, j - 1)),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&(i, j)| i.abs_diff(j) <= window)
+    };
+    for i in 0..n_timesteps {
+        for j in i.saturating_sub(window)..(i + window + 1).min(n_timesteps) {
+            let previous = predecessors(i, j)
+                .map(|(i, j)| cost(&costs, i, j))
+                .reduce(f64::min)
+                .unwrap_or(0.);
+            costs[[i, j + window - i]] =
+                (observations[i] - simulations[j]).abs() + previous;
+        }
+    }
+    let mut path = vec![(n_timesteps - 1, n_timesteps - 1)];
+    let (mut i, mut j) = (n_timesteps - 1, n_timesteps - 1);
+    // the diagonal wins ties
+    while let Some(previous) = predecessors(i, j).min_by(|&(a, b), &(c, d)| {
+        cost(&costs, a, b).total_cmp(&cost(&costs, c, d))
+    }) {
+        (i, j) = previous;
+        path.push(previous);
+    }
+    path.reverse();
+    path
+}
+
+/// Runs of consecutive timesteps where the observations aren't missing.
+fn valid_runs(observations: ArrayView1<f64>) -> Vec<Range<usize>> {
+    runs_where(observations.len(), |t| !observations[t].is_nan())
+}
+
+/// Runs of consecutive timesteps, among the first `n`, where `is_valid`
+/// holds.
+fn runs_where(
+    n: usize,
+    is_valid: impl Fn(usize) -> bool,
+) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = vec![];
+    for t in (0..n).filter(|&t| is_valid(t)) {
+        match runs.last_mut() {
+            Some(run) if run.end == t => run.end = t + 1,
+            _ => runs.push(t..t + 1),
+        }
+    }
+    runs
+}
+
+/// Hundredth of the mean observed discharge, offsetting the discharge so
+/// zero flows can be handled.
+pub(crate) fn default_epsilon(observations: ArrayView1<f64>) -> f64 {
+    let (sum, n) = observations
+        .iter()
+        .filter(|o| !o.is_nan())
+        .fold((0., 0), |(sum, n), o| (sum + o, n + 1));
+    0.01 * (sum / n.max(1) as f64).max(0.)
+}
+
+/// Ranks of the values starting at 1, tied values sharing their mean rank.
+fn rank(values: ArrayView1<f64>) -> Array1<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = Array1::zeros(values.len());
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Consecutive timesteps of each hydrological year, a year starting
+/// whenever the day of year goes past `start_day`, so partial years at the
+/// ends are kept. Day 366 counts as the last day of the year.
+fn hydrological_years(
+    day_of_year: ArrayView1<usize>,
+    start_day: usize,
+) -> Vec<Range<usize>> {
+    let shifted = |day: usize| (day.min(365) + 365 - start_day % 365) % 365;
+    let mut years = vec![];
+    let mut start = 0;
+    for t in 1..=day_of_year.len() {
+        if t == day_of_year.len()
+            || shifted(day_of_year[t]) < shifted(day_of_year[t - 1])
+        {
+            years.push(start..t);
+            start = t;
+        }
+    }
+    years
+}
+
+/// Quickflow of the observations from one forward pass of the Lyne-Hollick
+/// filter, a missing observation resetting it.
+fn quickflow(observations: ArrayView1<f64>) -> Array1<f64> {
+    const ALPHA: f64 = 0.925;
+    let mut quickflow: Array1<f64> = Array1::zeros(observations.len());
+    for t in 1..observations.len() {
+        let (current, previous) = (observations[t], observations[t - 1]);
+        if current.is_nan() || previous.is_nan() {
+            continue;
+        }
+        quickflow[t] = (ALPHA * quickflow[t - 1]
+            + (1. + ALPHA) / 2. * (current - previous))
+            .clamp(0., current.max(0.));
+    }
+    quickflow
+}
+
+/// Timesteps of the event extended by `separation` timesteps on both sides.
+fn event_window(
+    event: Range<usize>,
+    separation: usize,
+    n_timesteps: usize,
+) -> Range<usize> {
+    event.start.saturating_sub(separation)
+        ..(event.end + separation).min(n_timesteps)
+}
+
+/// Timestep of the largest value of `series` in `window`, missing values
+/// being skipped.
+fn argmax(series: ArrayView1<f64>, window: Range<usize>) -> Option<usize> {
+    window
+        .filter(|&t| !series[t].is_nan())
+        .max_by(|&a, &b| series[a].total_cmp(&series[b]))
+}
+
+/// NSE, KGE and percent bias of each group of timesteps, the timesteps of
+/// the other groups being masked as missing observations.
+fn group_metrics(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    groups: ArrayView1<usize>,
+    n_groups: usize,
+) -> Array2<f64> {
+    let mut metrics = Array2::from_elem((n_groups, 3), f64::NAN);
+    for group in 0..n_groups {
+        let mut masked = observations.to_owned();
+        masked.zip_mut_with(&groups, |o, &g| {
+            if g != group {
+                *o = f64::NAN;
+            }
+        });
+        let observations = masked.view();
+        if let (Ok(nse), Ok(kge), Ok(pbias)) = (
+            calculate_nse(observations, simulations),
+            calculate_kge(observations, simulations),
+            calculate_pbias(observations, simulations),
+        ) {
+            metrics
+                .row_mut(group)
+                .assign(&Array1::from_vec(vec![nse, kge, pbias]));
+        }
+    }
+    metrics
+}
+
+/// One if the observation exceeds `threshold`, zero otherwise.
+fn exceeds(observation: f64, threshold: f64) -> f64 {
+    if observation > threshold {
+        1.
+    } else {
+        0.
+    }
+}
+
+/// Month (0 to 11) of the day of year.
+pub fn month(day_of_year: usize) -> usize {
+    MONTH_ENDS
+        .iter()
+        .position(|&end| day_of_year <= end)
+        .unwrap_or(11)
+}
+
+fn check_lengths(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(), MetricsError> {
+    if observations.len() != simulations.len() {
+        Err(MetricsError::LengthMismatch(
+            observations.len(),
+            simulations.len(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Observations and simulations at the timesteps where neither is missing,
+/// unlike `select_valid` which keeps the missing simulations so they show
+/// in the metrics.
+pub fn mask_pairs(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(Array1<f64>, Array1<f64>), MetricsError> {
+    check_lengths(observations, simulations)?;
+    let (observations, simulations): (Vec<f64>, Vec<f64>) = observations
+        .iter()
+        .zip(simulations)
+        .filter(|(o, p)| !o.is_nan() && !p.is_nan())
+        .unzip();
+    if observations.len() < MIN_VALID_OBSERVATIONS {
+        return Err(MetricsError::NotEnoughObservations(observations.len()));
+    }
+    Ok((
+        Array1::from_vec(observations),
+        Array1::from_vec(simulations),
+    ))
+}
+
+/// Observations marked missing where either series is, unlike `mask_pairs`
+/// which drops these timesteps, so the metrics relying on the order of the
+/// timesteps don't join the series across the gaps.
+pub fn mark_pairs(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<Array1<f64>, MetricsError> {
+    check_lengths(observations, simulations)?;
+    Ok(observations
+        .iter()
+        .zip(simulations)
+        .map(|(&o, p)| if p.is_nan() { f64::NAN } else { o })
+        .collect())
+}
+
+/// `metric` on the timesteps of `mask_pairs`, e.g.
+/// `calculate_masked(calculate_nse, observations, simulations)`.
+pub fn calculate_masked<F>(
+    metric: F,
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<f64, MetricsError>
+where
+    F: Fn(ArrayView1<f64>, ArrayView1<f64>) -> Result<f64, MetricsError>,
+{
+    let (observations, simulations) = mask_pairs(observations, simulations)?;
+    metric(observations.view(), simulations.view())
+}
+
+/// Observations and simulations at the timesteps where the observations
+/// aren't missing, only copied when some are.
+fn select_valid<'a>(
+    observations: ArrayView1<'a, f64>,
+    simulations: ArrayView1<'a, f64>,
+) -> Result<(Series<'a>, Series<'a>), MetricsError> {
+    check_lengths(observations, simulations)?;
+    let n_valid = observations.iter().filter(|o| !o.is_nan()).count();
+    if n_valid < MIN_VALID_OBSERVATIONS {
+        return Err(MetricsError::NotEnoughObservations(n_valid));
+    }
+    if n_valid == observations.len() {
+        return Ok((observations.into(), simulations.into()));
+    }
+    let indices: Vec<usize> = (0..observations.len())
+        .filter(|&t| !observations[t].is_nan())
+        .collect();
+    Ok((
+        observations.select(Axis(0), &indices).into(),
+        simulations.select(Axis(0), &indices).into(),
+    ))
+}
+
+/// Observations, simulations and weights at the timesteps where the
+/// observations aren't missing and the weight is positive.
+fn select_weighted(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    weights: ArrayView1<f64>,
+) -> Result<WeightedSeries, MetricsError> {
+    check_lengths(observations, simulations)?;
+    check_lengths(observations, weights)?;
+    let indices: Vec<usize> = (0..observations.len())
+        .filter(|&t| !observations[t].is_nan() && weights[t] > 0.)
+        .collect();
+    if indices.len() < MIN_VALID_OBSERVATIONS {
+        return Err(MetricsError::NotEnoughObservations(indices.len()));
+    }
+    Ok((
+        indices.iter().map(|&t| observations[t]).collect(),
+        indices.iter().map(|&t| simulations[t]).collect(),
+        indices.iter().map(|&t| weights[t]).collect(),
+    ))
+}
+
+fn weighted_mean(values: &[f64], weights: &[f64]) -> f64 {
+    values.iter().zip(weights).map(|(x, w)| x * w).sum::<f64>()
+        / weights.iter().sum::<f64>()
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_rmse", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_rmse<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_rmse)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_weighted_rmse", signature = (
+    observations,
+    simulations,
+    weights,
+    skip_nan=false,
+))]
+pub fn py_calculate_weighted_rmse<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    weights: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    let (observations, simulations) =
+        (observations.as_array(), simulations.as_array());
+    let mut weights = weights.as_array().to_owned();
+    // the missing simulations are dropped through their weight
+    if skip_nan && weights.len() == simulations.len() {
+        weights.zip_mut_with(&simulations, |w, p| {
+            if p.is_nan() {
+                *w = 0.;
+            }
+        });
+    }
+    Ok(calculate_weighted_rmse(
+        observations,
+        simulations,
+        weights.view(),
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_nse", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_nse<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_nse)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_kge", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_kge<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_kge)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_kge_components", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_kge_components<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<(f64, f64, f64, f64)> {
+    let (observations, simulations) =
+        (observations.as_array(), simulations.as_array());
+    Ok(if skip_nan {
+        let (observations, simulations) =
+            mask_pairs(observations, simulations)?;
+        calculate_kge_components(observations.view(), simulations.view())?
+    } else {
+        calculate_kge_components(observations, simulations)?
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_nse_components", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_nse_components<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<(f64, f64, f64, f64)> {
+    let (observations, simulations) =
+        (observations.as_array(), simulations.as_array());
+    Ok(if skip_nan {
+        let (observations, simulations) =
+            mask_pairs(observations, simulations)?;
+        calculate_nse_components(observations.view(), simulations.view())?
+    } else {
+        calculate_nse_components(observations, simulations)?
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_kge_2012", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_kge_2012<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_kge_2012)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_log_nse", signature = (
+    observations,
+    simulations,
+    epsilon=None,
+    skip_nan=false,
+))]
+pub fn py_calculate_log_nse<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    epsilon: Option<f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, |o, p| {
+        calculate_log_nse(o, p, epsilon)
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_nse_sqrt", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_nse_sqrt<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_nse_sqrt)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_nse_inv", signature = (
+    observations,
+    simulations,
+    epsilon=None,
+    skip_nan=false,
+))]
+pub fn py_calculate_nse_inv<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    epsilon: Option<f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, |o, p| {
+        calculate_nse_inv(o, p, epsilon)
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_pbias", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_pbias<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_pbias)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_mae", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_mae<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_mae)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_mape", signature = (
+    observations,
+    simulations,
+    epsilon=None,
+    skip_nan=false,
+))]
+pub fn py_calculate_mape<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    epsilon: Option<f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, |o, p| {
+        calculate_mape(o, p, epsilon)
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_smape", signature = (
+    observations,
+    simulations,
+    epsilon=None,
+    skip_nan=false,
+))]
+pub fn py_calculate_smape<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    epsilon: Option<f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, |o, p| {
+        calculate_smape(o, p, epsilon)
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_rsr", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_rsr<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_rsr)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_volumetric_efficiency", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_volumetric_efficiency<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(
+        observations,
+        simulations,
+        skip_nan,
+        calculate_volumetric_efficiency,
+    )
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_index_of_agreement", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_index_of_agreement<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(
+        observations,
+        simulations,
+        skip_nan,
+        calculate_index_of_agreement,
+    )
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_refined_index_of_agreement", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_refined_index_of_agreement<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(
+        observations,
+        simulations,
+        skip_nan,
+        calculate_refined_index_of_agreement,
+    )
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_r2", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_r2<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_r2)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_pearson", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_pearson<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_pearson)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_spearman", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_spearman<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_spearman)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_bounded_nse", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_bounded_nse<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_bounded_nse)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_bounded_kge", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_bounded_kge<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    call_metric(observations, simulations, skip_nan, calculate_bounded_kge)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_persistence_index", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_calculate_persistence_index<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<f64> {
+    let (observations, simulations) =
+        (observations.as_array(), simulations.as_array());
+    Ok(if skip_nan {
+        let observations = mark_pairs(observations, simulations)?;
+        calculate_persistence_index(observations.view(), simulations)?
+    } else {
+        calculate_persistence_index(observations, simulations)?
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "summarize", signature = (
+    observations,
+    simulations,
+    skip_nan=false,
+))]
+pub fn py_summarize<'py>(
+    py: Python<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+) -> PyResult<Bound<'py, PyDict>> {
+    let (observations, simulations) =
+        (observations.as_array(), simulations.as_array());
+    let metrics = if skip_nan {
+        let observations = mark_pairs(observations, simulations)?;
+        summarize(observations.view(), simulations)?
+    } else {
+        summarize(observations, simulations)?
+    };
+    let dict = PyDict::new(py);
+    for (name, value) in metrics {
+        dict.set_item(name, value)?;
+    }
+    Ok(dict)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_split_kge", signature = (
+    observations,
+    simulations,
+    day_of_year,
+    start_day=274,
+))]
+pub fn py_calculate_split_kge<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, usize>,
+    start_day: usize,
+) -> PyResult<(f64, f64)> {
+    Ok(calculate_split_kge(
+        observations.as_array(),
+        simulations.as_array(),
+        day_of_year.as_array(),
+        start_day,
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "find_peaks", signature = (
+    observations,
+    quantile=0.95,
+    separation=7,
+))]
+pub fn py_find_peaks<'py>(
+    py: Python<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    quantile: f64,
+    separation: usize,
+) -> Bound<'py, PyArray1<usize>> {
+    find_peaks(observations.as_array(), quantile, separation).into_pyarray(py)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_peak_errors", signature = (
+    observations,
+    simulations,
+    quantile=0.95,
+    separation=7,
+))]
+pub fn py_calculate_peak_errors<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    quantile: f64,
+    separation: usize,
+) -> PyResult<(f64, f64, f64)> {
+    Ok(calculate_peak_errors(
+        observations.as_array(),
+        simulations.as_array(),
+        quantile,
+        separation,
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_peak_timing", signature = (
+    observations,
+    simulations,
+    quantile=0.95,
+    separation=7,
+))]
+pub fn py_calculate_peak_timing<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    quantile: f64,
+    separation: usize,
+) -> PyResult<(f64, f64)> {
+    Ok(calculate_peak_timing(
+        observations.as_array(),
+        simulations.as_array(),
+        quantile,
+        separation,
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_lag", signature = (
+    observations,
+    simulations,
+    max_lag=10,
+))]
+pub fn py_calculate_lag<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    max_lag: usize,
+) -> PyResult<isize> {
+    Ok(calculate_lag(
+        observations.as_array(),
+        simulations.as_array(),
+        max_lag,
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_dtw", signature = (
+    observations,
+    simulations,
+    window=DTW_WINDOW,
+))]
+pub fn py_calculate_dtw<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    window: usize,
+) -> PyResult<(f64, f64)> {
+    Ok(calculate_dtw(
+        observations.as_array(),
+        simulations.as_array(),
+        window,
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "event_table", signature = (
+    observations,
+    simulations,
+    method="threshold",
+    quantile=0.95,
+    separation=7,
+))]
+pub fn py_event_table<'py>(
+    py: Python<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    method: &str,
+    quantile: f64,
+    separation: usize,
+) -> PyResult<Bound<'py, PyDict>> {
+    let method =
+        EventMethod::from_str(method).map_err(PyValueError::new_err)?;
+    event_table(
+        observations.as_array(),
+        simulations.as_array(),
+        method,
+        quantile,
+        separation,
+    )?
+    .to_py_dict(py)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_seasonal_metrics")]
+pub fn py_calculate_seasonal_metrics<'py>(
+    py: Python<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, usize>,
+) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>)> {
+    let (monthly, seasonal) = calculate_seasonal_metrics(
+        observations.as_array(),
+        simulations.as_array(),
+        day_of_year.as_array(),
+    )?;
+    Ok((monthly.to_pyarray(py), seasonal.to_pyarray(py)))
+}
+
+/// Calls `metric` from Python, first dropping the timesteps where either
+/// series is missing if `skip_nan`.
+fn call_metric<'py, F>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    skip_nan: bool,
+    metric: F,
+) -> PyResult<f64>
+where
+    F: Fn(ArrayView1<f64>, ArrayView1<f64>) -> Result<f64, MetricsError>,
+{
+    let (observations, simulations) =
+        (observations.as_array(), simulations.as_array());
+    Ok(if skip_nan {
+        calculate_masked(metric, observations, simulations)?
+    } else {
+        metric(observations, simulations)?
+    })
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_climatology")]
+pub fn py_calculate_climatology<'py>(
+    py: Python<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, usize>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let climatology = calculate_climatology(
+        observations.as_array(),
+        day_of_year.as_array(),
+    )?;
+    Ok(climatology.into_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_benchmark_nse")]
+pub fn py_calculate_benchmark_nse<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, usize>,
+) -> PyResult<f64> {
+    Ok(calculate_benchmark_nse(
+        observations.as_array(),
+        simulations.as_array(),
+        day_of_year.as_array(),
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_benchmark_kge")]
+pub fn py_calculate_benchmark_kge<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, usize>,
+) -> PyResult<f64> {
+    Ok(calculate_benchmark_kge(
+        observations.as_array(),
+        simulations.as_array(),
+        day_of_year.as_array(),
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_exceedance_probabilities")]
+pub fn py_calculate_exceedance_probabilities<'py>(
+    py: Python<'py>,
+    ensemble: PyReadonlyArray2<'py, f64>,
+    threshold: f64,
+) -> Bound<'py, PyArray1<f64>> {
+    calculate_exceedance_probabilities(ensemble.as_array(), threshold)
+        .into_pyarray(py)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_brier_score")]
+pub fn py_calculate_brier_score<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    probabilities: PyReadonlyArray1<'py, f64>,
+    threshold: f64,
+) -> PyResult<f64> {
+    Ok(calculate_brier_score(
+        observations.as_array(),
+        probabilities.as_array(),
+        threshold,
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_brier_skill_score")]
+pub fn py_calculate_brier_skill_score<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    probabilities: PyReadonlyArray1<'py, f64>,
+    threshold: f64,
+) -> PyResult<f64> {
+    Ok(calculate_brier_skill_score(
+        observations.as_array(),
+        probabilities.as_array(),
+        threshold,
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_rank_histogram")]
+pub fn py_calculate_rank_histogram<'py>(
+    py: Python<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    ensemble: PyReadonlyArray2<'py, f64>,
+) -> PyResult<Bound<'py, PyArray1<usize>>> {
+    let counts = calculate_rank_histogram(
+        observations.as_array(),
+        ensemble.as_array(),
+    )?;
+    Ok(counts.into_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_reliability", signature = (
+    observations,
+    probabilities,
+    threshold,
+    n_bins=10,
+))]
+pub fn py_calculate_reliability<'py>(
+    py: Python<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    probabilities: PyReadonlyArray1<'py, f64>,
+    threshold: f64,
+    n_bins: usize,
+) -> PyResult<(
+    Bound<'py, PyArray1<f64>>,
+    Bound<'py, PyArray1<f64>>,
+    Bound<'py, PyArray1<usize>>,
+)> {
+    let (probability, frequency, counts) = calculate_reliability(
+        observations.as_array(),
+        probabilities.as_array(),
+        threshold,
+        n_bins,
+    )?;
+    Ok((
+        probability.into_pyarray(py),
+        frequency.into_pyarray(py),
+        counts.into_pyarray(py),
+    ))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "metrics")?;
+    m.add_function(wrap_pyfunction!(py_calculate_rmse, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_weighted_rmse, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_nse, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_kge, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_kge_components, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_nse_components, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_kge_2012, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_log_nse, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_nse_sqrt, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_nse_inv, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_pbias, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_mae, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_mape, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_smape, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_rsr, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_volumetric_efficiency, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_index_of_agreement, &m)?)?;
+    m.add_function(wrap_pyfunction!(
+        py_calculate_refined_index_of_agreement,
+        &m
+    )?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_r2, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_pearson, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_spearman, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_bounded_nse, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_bounded_kge, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_persistence_index, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_summarize, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_split_kge, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_seasonal_metrics, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_climatology, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_benchmark_nse, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_benchmark_kge, &m)?)?;
+    m.add_function(wrap_pyfunction!(
+        py_calculate_exceedance_probabilities,
+        &m
+    )?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_brier_score, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_brier_skill_score, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_rank_histogram, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_reliability, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_find_peaks, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_peak_errors, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_peak_timing, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_lag, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_dtw, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_event_table, &m)?)?;
+    Ok(m)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    type Metric =
+        fn(ArrayView1<f64>, ArrayView1<f64>) -> Result<f64, MetricsError>;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn perfect_simulations_score_best() {
+        let observations = array![1., 3., 2., 5., 4.];
+        let observations = observations.view();
+        assert_close(calculate_rmse(observations, observations).unwrap(), 0.);
+        assert_close(calculate_nse(observations, observations).unwrap(), 1.);
+        assert_close(calculate_kge(observations, observations).unwrap(), 1.);
+        assert_close(calculate_mae(observations, observations).unwrap(), 0.);
+    }
+
+    #[test]
+    fn missing_observations_are_skipped() {
+        let observations = array![1., f64::NAN, 2., 5., f64::NAN, 4.];
+        let simulations = array![1.5, 7., 2.5, 4., 9., 3.];
+        let valid = [0, 2, 3, 5];
+        let metrics: [Metric; 5] = [
+            calculate_rmse,
+            calculate_nse,
+            calculate_kge,
+            calculate_mae,
+            calculate_pbias,
+        ];
+        for metric in metrics {
+            assert_close(
+                metric(observations.view(), simulations.view()).unwrap(),
+                metric(
+                    observations.select(Axis(0), &valid).view(),
+                    simulations.select(Axis(0), &valid).view(),
+                )
+                .unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn too_few_observations_are_an_error() {
+        let observations = array![f64::NAN, 1., f64::NAN];
+        let simulations = array![1., 1., 1.];
+        assert!(matches!(
+            calculate_nse(observations.view(), simulations.view()),
+            Err(MetricsError::NotEnoughObservations(1))
+        ));
+    }
+
+    #[test]
+    fn unit_weights_match_the_unweighted_metrics() {
+        let observations = array![1., f64::NAN, 2., 5., 3., 4.];
+        let simulations = array![1.5, 7., 2.5, 4., 3.5, 3.];
+        let weights = Array1::ones(observations.len());
+        let (observations, simulations, weights) =
+            (observations.view(), simulations.view(), weights.view());
+        let pairs: [(Metric, fn(_, _, _) -> _); 4] = [
+            (calculate_rmse, calculate_weighted_rmse),
+            (calculate_nse, calculate_weighted_nse),
+            (calculate_kge, calculate_weighted_kge),
+            (calculate_mae, calculate_weighted_mae),
+        ];
+        for (metric, weighted_metric) in pairs {
+            assert_close(
+                weighted_metric(observations, simulations, weights).unwrap(),
+                metric(observations, simulations).unwrap(),
+            );
+        }
+        let (error, shift) =
+            calculate_dtw(observations, simulations, DTW_WINDOW).unwrap();
+        let (weighted_error, weighted_shift) = calculate_weighted_dtw(
+            observations,
+            simulations,
+            weights,
+            DTW_WINDOW,
+        )
+        .unwrap();
+        assert_close(weighted_error, error);
+        assert_close(weighted_shift, shift);
+    }
+
+    #[test]
+    fn zero_weights_act_as_missing_observations() {
+        let observations = array![1., 6., 2., 5., 3., 4.];
+        let simulations = array![1.5, 7., 2.5, 4., 3.5, 3.];
+        let weights = array![1., 0., 1., 1., 1., 1.];
+        let missing = array![1., f64::NAN, 2., 5., 3., 4.];
+        assert_close(
+            calculate_weighted_nse(
+                observations.view(),
+                simulations.view(),
+                weights.view(),
+            )
+            .unwrap(),
+            calculate_nse(missing.view(), simulations.view()).unwrap(),
+        );
+        // the warping doesn't reach across the timestep without weight
+        assert_close(
+            calculate_weighted_dtw(
+                observations.view(),
+                simulations.view(),
+                weights.view(),
+                DTW_WINDOW,
+            )
+            .unwrap()
+            .0,
+            calculate_dtw(missing.view(), simulations.view(), DTW_WINDOW)
+                .unwrap()
+                .0,
+        );
+    }
+
+    #[test]
+    fn dtw_without_window_is_the_mae() {
+        let observations = array![1., 3., 2., 5., 4.];
+        let simulations = array![2., 1., 2., 4., 6.];
+        let (error, shift) =
+            calculate_dtw(observations.view(), simulations.view(), 0).unwrap();
+        assert_close(
+            error,
+            calculate_mae(observations.view(), simulations.view()).unwrap(),
+        );
+        assert_close(shift, 0.);
+    }
+
+    #[test]
+    fn dtw_absorbs_timing_errors_within_the_window() {
+        let observations = array![0., 0., 1., 5., 1., 0., 0., 0.];
+        let simulations = array![0., 0., 0., 1., 5., 1., 0., 0.];
+        let (error, shift) =
+            calculate_dtw(observations.view(), simulations.view(), 1).unwrap();
+        assert_close(error, 0.);
+        assert!(shift > 0.);
+    }
+
+    #[test]
+    fn dtw_does_not_warp_across_missing_observations() {
+        // joined over the gap, the simulated peak would match the observed
+        // one without error
+        let observations = array![0., 0., f64::NAN, 5., 0.];
+        let simulations = array![0., 5., 0., 0., 0.];
+        let (error, _) =
+            calculate_dtw(observations.view(), simulations.view(), 3).unwrap();
+        assert!(error > 0.);
+    }
+
+    #[test]
+    fn events_merge_exceedances_closer_than_the_separation() {
+        let observations =
+            array![0., 10., 0., 0., 10., 0., 0., 0., 0., 10., 0.];
+        assert_eq!(
+            find_events(observations.view(), 0.5, 3),
+            vec![1..2, 4..5, 9..10]
+        );
+        assert_eq!(
+            find_events(observations.view(), 0.5, 4),
+            vec![1..5, 9..10]
+        );
+    }
+
+    #[test]
+    fn hydrological_years_start_on_the_start_day() {
+        let day_of_year = array![272, 273, 274, 275];
+        assert_eq!(
+            hydrological_years(day_of_year.view(), 274),
+            vec![0..2, 2..4]
+        );
+        // the leap day ends the year
+        let day_of_year = array![364, 365, 366, 1, 2];
+        assert_eq!(
+            hydrological_years(day_of_year.view(), 1),
+            vec![0..3, 3..5]
+        );
+    }
+
+    #[test]
+    fn split_kge_reports_the_worst_year() {
+        let day_of_year: Array1<usize> = (1..=365).chain(1..=365).collect();
+        let observations: Array1<f64> = day_of_year
+            .iter()
+            .map(|&d| 1. + (d as f64 / 30.).sin())
+            .collect();
+        let mut simulations = observations.clone();
+        simulations.slice_mut(s![365..]).mapv_inplace(|s| 1.5 * s);
+        let (mean, worst) = calculate_split_kge(
+            observations.view(),
+            simulations.view(),
+            day_of_year.view(),
+            1,
+        )
+        .unwrap();
+        let biased = calculate_kge(
+            observations.slice(s![365..]),
+            simulations.slice(s![365..]),
+        )
+        .unwrap();
+        assert_close(worst, biased);
+        assert_close(mean, (1. + biased) / 2.);
+    }
 }
@@ -1,6 +1,7 @@
 #![allow(clippy::type_complexity)]
 
 use crate::metrics::MetricsError;
+use crate::snow::utils::Sublimation;
 use ndarray::{s, Array1, Array2, ArrayView1, Axis};
 use numpy::PyReadonlyArray1;
 use pyo3::exceptions::PyValueError;
@@ -15,8 +16,34 @@ pub enum Error {
     ParamsMismatch(usize, usize),
     #[error("Unknown model '{0}'. Valid options: {1}")]
     WrongModel(String, String),
+    #[error("metadata is missing '{0}', which is required by this model")]
+    MissingMetadata(&'static str),
+    #[error("{0} and {1} must have the same length (got {2} and {3})")]
+    InputLengthMismatch(&'static str, &'static str, usize, usize),
+    #[error("data is missing '{0}', which is required by this model")]
+    MissingData(&'static str),
+    #[error("topographic_index_fractions must sum to a positive value (got {0})")]
+    TopographicIndexFractions(f64),
     #[error(transparent)]
     Metrics(#[from] MetricsError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid checkpoint: {0}")]
+    InvalidCheckpoint(String),
+    #[error("objective function failed: {0}")]
+    CustomObjective(String),
+    #[error("invalid fixed parameters: {0}")]
+    FixedParams(String),
+    #[error("invalid validation: {0}")]
+    Validation(String),
+    #[error("invalid sensitivity analysis: {0}")]
+    Sensitivity(String),
+    #[error("invalid DREAM sampler: {0}")]
+    Dream(String),
+    #[error("shrink factor must be in (0, 1] (got {0})")]
+    ShrinkFactor(f64),
+    #[error("restart fraction must be in [0, 1) (got {0})")]
+    RestartFraction(f64),
 }
 
 impl From<Error> for PyErr {
@@ -61,10 +88,31 @@ impl<'a> Data<'a> {
     }
 }
 
+/// Forcings of the PET models, the optional ones being only required by
+/// some of them.
+#[derive(Clone, Copy)]
+pub struct PetData<'a> {
+    pub temperature: ArrayView1<'a, f64>, // °C
+    pub day_of_year: ArrayView1<'a, f64>, // 1-366
+    pub temperature_min: Option<ArrayView1<'a, f64>>, // °C
+    pub temperature_max: Option<ArrayView1<'a, f64>>, // °C
+    pub net_radiation: Option<ArrayView1<'a, f64>>, // MJ m^-2 day^-1
+}
+
 pub struct Metadata<'a> {
     pub area: f64,                             // km^2
     pub elevation_layers: ArrayView1<'a, f64>, // m
     pub median_elevation: f64,                 // m
+    pub timestep: Option<f64>,                 // h
+    pub latitude: f64,                         // °
+    pub glacier_fraction: f64,                 // 0-1
+    pub temperature_lapse_rate: Option<f64>,   // °C/100m
+    pub precipitation_gradient: Option<f64>,   // 1/m
+    pub snow_cover_threshold: f64,             // mm
+    pub sublimation: Sublimation,
+    // histogram of ln(a / tan(b)) classes and their area fractions
+    pub topographic_index: ArrayView1<'a, f64>,
+    pub topographic_index_fractions: ArrayView1<'a, f64>,
 }
 
 pub type SimulateFn = Box<
@@ -77,9 +125,13 @@ pub type SimulateFn = Box<
         + Sync,
 >;
 
+pub type PetFn = for<'a> fn(PetData<'a>, f64) -> Result<Array1<f64>, Error>;
+
+pub type InitFn = fn() -> (Array1<f64>, Array2<f64>);
+
 pub fn compose_init(
-    snow_init: fn() -> (Array1<f64>, Array2<f64>),
-    climate_init: fn() -> (Array1<f64>, Array2<f64>),
+    snow_init: InitFn,
+    climate_init: InitFn,
 ) -> impl Fn() -> (Array1<f64>, Array2<f64>, usize) {
     move || {
         let (snow_defaults, snow_bounds) = snow_init();
@@ -152,6 +204,26 @@ pub struct PyMetadata<'py> {
     pub area: f64,
     pub elevation_layers: PyReadonlyArray1<'py, f64>,
     pub median_elevation: f64,
+    #[pyo3(default)]
+    pub timestep: Option<f64>,
+    #[pyo3(attribute("lat"), default)]
+    pub latitude: f64,
+    #[pyo3(default)]
+    pub glacier_fraction: f64,
+    #[pyo3(default)]
+    pub temperature_lapse_rate: Option<f64>,
+    #[pyo3(default)]
+    pub precipitation_gradient: Option<f64>,
+    #[pyo3(default = 10.0)]
+    pub snow_cover_threshold: f64,
+    #[pyo3(default)]
+    pub sublimation_fraction: f64,
+    #[pyo3(default)]
+    pub sublimation_pet_factor: f64,
+    #[pyo3(default)]
+    pub topographic_index: Option<PyReadonlyArray1<'py, f64>>,
+    #[pyo3(default)]
+    pub topographic_index_fractions: Option<PyReadonlyArray1<'py, f64>>,
 }
 
 impl<'py> PyMetadata<'py> {
@@ -160,6 +232,29 @@ impl<'py> PyMetadata<'py> {
             area: self.area,
             elevation_layers: self.elevation_layers.as_array(),
             median_elevation: self.median_elevation,
+            timestep: self.timestep,
+            latitude: self.latitude,
+            glacier_fraction: self.glacier_fraction,
+            temperature_lapse_rate: self.temperature_lapse_rate,
+            precipitation_gradient: self.precipitation_gradient,
+            snow_cover_threshold: self.snow_cover_threshold,
+            sublimation: Sublimation::new(
+                self.sublimation_fraction,
+                self.sublimation_pet_factor,
+            ),
+            topographic_index: as_optional_view(&self.topographic_index),
+            topographic_index_fractions: as_optional_view(
+                &self.topographic_index_fractions,
+            ),
         }
     }
 }
+
+fn as_optional_view<'a>(
+    array: &'a Option<PyReadonlyArray1<'_, f64>>,
+) -> ArrayView1<'a, f64> {
+    array
+        .as_ref()
+        .map(|a| a.as_array())
+        .unwrap_or_else(|| ArrayView1::from(&[]))
+}
@@ -0,0 +1,58 @@
+use ndarray::{Array1, ArrayView1};
+use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Error, PetData};
+use crate::pet::oudin::compute_daylight_hours;
+use crate::pet::utils::check_length;
+
+pub fn simulate(data: PetData, latitude: f64) -> Result<Array1<f64>, Error> {
+    compute(data.temperature, data.day_of_year, latitude, 1.0)
+}
+
+/// Blaney-Criddle (1950) PET (mm/day) from the daily mean temperature (°C)
+/// and the daily percentage of the annual daytime hours, as formulated by
+/// FAO-24. `coefficient` is the crop coefficient, 1 by default.
+pub fn compute(
+    temperature: ArrayView1<f64>,
+    day_of_year: ArrayView1<f64>,
+    latitude: f64,
+    coefficient: f64,
+) -> Result<Array1<f64>, Error> {
+    check_length("day_of_year", day_of_year, temperature)?;
+
+    let annual_daylight_hours: f64 = (1..=365)
+        .map(|doy| compute_daylight_hours(doy as f64, latitude))
+        .sum();
+
+    Ok(Array1::from_shape_fn(temperature.len(), |t| {
+        let p = 100. * compute_daylight_hours(day_of_year[t], latitude)
+            / annual_daylight_hours;
+        (coefficient * p * (0.46 * temperature[t] + 8.13)).max(0.)
+    }))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+#[pyo3(signature = (temperature, day_of_year, latitude, coefficient=1.0))]
+fn py_simulate<'py>(
+    py: Python<'py>,
+    temperature: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, f64>,
+    latitude: f64,
+    coefficient: f64,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let pet = compute(
+        temperature.as_array(),
+        day_of_year.as_array(),
+        latitude,
+        coefficient,
+    )?;
+    Ok(pet.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "blaney_criddle")?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
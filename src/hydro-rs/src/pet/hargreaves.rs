@@ -0,0 +1,84 @@
+use ndarray::{Array1, ArrayView1};
+use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Error, PetData};
+use crate::pet::oudin::compute_extraterrestrial_radiation;
+use crate::pet::utils::check_length;
+
+pub fn simulate(data: PetData, latitude: f64) -> Result<Array1<f64>, Error> {
+    let temperature_min = data
+        .temperature_min
+        .ok_or(Error::MissingData("temperature_min"))?;
+    let temperature_max = data
+        .temperature_max
+        .ok_or(Error::MissingData("temperature_max"))?;
+    compute(
+        temperature_min,
+        temperature_max,
+        Some(data.temperature),
+        data.day_of_year,
+        latitude,
+    )
+}
+
+/// Hargreaves-Samani (1985) PET (mm/day) from the daily minimum, maximum
+/// and mean temperature (°C), the mean defaulting to the average of the
+/// extremes.
+pub fn compute(
+    temperature_min: ArrayView1<f64>,
+    temperature_max: ArrayView1<f64>,
+    temperature_mean: Option<ArrayView1<f64>>,
+    day_of_year: ArrayView1<f64>,
+    latitude: f64,
+) -> Result<Array1<f64>, Error> {
+    check_length("temperature_max", temperature_max, temperature_min)?;
+    check_length("day_of_year", day_of_year, temperature_min)?;
+    if let Some(temperature_mean) = temperature_mean {
+        check_length("temperature_mean", temperature_mean, temperature_min)?;
+    }
+
+    let lambda = 2.45; // latent heat of vaporization (MJ/kg)
+
+    Ok(Array1::from_shape_fn(temperature_min.len(), |t| {
+        let tmin = temperature_min[t];
+        let tmax = temperature_max[t];
+        let tmean = temperature_mean.map_or((tmin + tmax) / 2., |x| x[t]);
+        let re = compute_extraterrestrial_radiation(day_of_year[t], latitude);
+        (0.0023 * re / lambda * (tmean + 17.8) * (tmax - tmin).max(0.).sqrt())
+            .max(0.)
+    }))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+#[pyo3(signature = (
+    temperature_min,
+    temperature_max,
+    day_of_year,
+    latitude,
+    temperature_mean=None,
+))]
+fn py_simulate<'py>(
+    py: Python<'py>,
+    temperature_min: PyReadonlyArray1<'py, f64>,
+    temperature_max: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, f64>,
+    latitude: f64,
+    temperature_mean: Option<PyReadonlyArray1<'py, f64>>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let pet = compute(
+        temperature_min.as_array(),
+        temperature_max.as_array(),
+        temperature_mean.as_ref().map(|x| x.as_array()),
+        day_of_year.as_array(),
+        latitude,
+    )?;
+    Ok(pet.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "hargreaves")?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
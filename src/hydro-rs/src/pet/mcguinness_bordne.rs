@@ -0,0 +1,51 @@
+use ndarray::{Array1, ArrayView1};
+use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Error, PetData};
+use crate::pet::oudin::compute_extraterrestrial_radiation;
+use crate::pet::utils::check_length;
+
+pub fn simulate(data: PetData, latitude: f64) -> Result<Array1<f64>, Error> {
+    compute(data.temperature, data.day_of_year, latitude)
+}
+
+/// McGuinness-Bordne (1972) PET (mm/day) from the daily mean temperature
+/// (°C) and the extraterrestrial radiation, in the form given by Oudin et
+/// al. (2005).
+pub fn compute(
+    temperature: ArrayView1<f64>,
+    day_of_year: ArrayView1<f64>,
+    latitude: f64,
+) -> Result<Array1<f64>, Error> {
+    check_length("day_of_year", day_of_year, temperature)?;
+
+    let rho = 1000.; // water density (kg/m^3)
+
+    Ok(Array1::from_shape_fn(temperature.len(), |t| {
+        let temp = temperature[t];
+        // latent heat of vaporization (MJ/kg)
+        let lambda = 2.501 - 0.002361 * temp;
+        let re = compute_extraterrestrial_radiation(day_of_year[t], latitude);
+        (re / (lambda * rho) * (temp + 5.) / 68. * 1000.).max(0.)
+    }))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+fn py_simulate<'py>(
+    py: Python<'py>,
+    temperature: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, f64>,
+    latitude: f64,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let pet =
+        compute(temperature.as_array(), day_of_year.as_array(), latitude)?;
+    Ok(pet.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "mcguinness_bordne")?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
@@ -1,10 +1,90 @@
+pub mod blaney_criddle;
+pub mod hamon;
+pub mod hargreaves;
+pub mod mcguinness_bordne;
 pub mod oudin;
+pub mod priestley_taylor;
+pub mod utils;
 
+use crate::model::{Error, PetData, PetFn};
 use crate::utils::register_submodule;
+use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
 use pyo3::prelude::*;
 
+pub fn get_model(model: &str) -> Result<PetFn, Error> {
+    match model {
+        "oudin" => Ok(oudin::simulate),
+        "hargreaves" => Ok(hargreaves::simulate),
+        "hamon" => Ok(hamon::simulate),
+        "priestley_taylor" => Ok(priestley_taylor::simulate),
+        "blaney_criddle" => Ok(blaney_criddle::simulate),
+        "mcguinness_bordne" => Ok(mcguinness_bordne::simulate),
+        _ => Err(Error::WrongModel(
+            model.to_string(),
+            "oudin, hargreaves, hamon, priestley_taylor, blaney_criddle, \
+             mcguinness_bordne"
+                .to_string(),
+        )),
+    }
+}
+
+/// Computes the PET with the model of the given name, the optional forcings
+/// being only required by some models.
+#[pyfunction]
+#[pyo3(signature = (
+    model,
+    temperature,
+    day_of_year,
+    latitude,
+    temperature_min=None,
+    temperature_max=None,
+    net_radiation=None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn simulate<'py>(
+    py: Python<'py>,
+    model: &str,
+    temperature: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, f64>,
+    latitude: f64,
+    temperature_min: Option<PyReadonlyArray1<'py, f64>>,
+    temperature_max: Option<PyReadonlyArray1<'py, f64>>,
+    net_radiation: Option<PyReadonlyArray1<'py, f64>>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulate = get_model(model)?;
+    let data = PetData {
+        temperature: temperature.as_array(),
+        day_of_year: day_of_year.as_array(),
+        temperature_min: temperature_min.as_ref().map(|x| x.as_array()),
+        temperature_max: temperature_max.as_ref().map(|x| x.as_array()),
+        net_radiation: net_radiation.as_ref().map(|x| x.as_array()),
+    };
+    Ok(simulate(data, latitude)?.to_pyarray(py))
+}
+
 pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
     let m = PyModule::new(py, "pet")?;
+    m.add_function(wrap_pyfunction!(simulate, &m)?)?;
+    register_submodule(
+        py,
+        &m,
+        &blaney_criddle::make_module(py)?,
+        "hydro_rs.pet",
+    )?;
+    register_submodule(py, &m, &hamon::make_module(py)?, "hydro_rs.pet")?;
+    register_submodule(py, &m, &hargreaves::make_module(py)?, "hydro_rs.pet")?;
+    register_submodule(
+        py,
+        &m,
+        &mcguinness_bordne::make_module(py)?,
+        "hydro_rs.pet",
+    )?;
     register_submodule(py, &m, &oudin::make_module(py)?, "hydro_rs.pet")?;
+    register_submodule(
+        py,
+        &m,
+        &priestley_taylor::make_module(py)?,
+        "hydro_rs.pet",
+    )?;
     Ok(m)
 }
@@ -1,43 +1,188 @@
-use numpy::{PyArray1, PyReadonlyArray1};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use numpy::{
+    PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2, ToPyArray,
+};
 use pyo3::prelude::*;
+use rayon::prelude::*;
 use std::f64::consts::PI;
 
+use crate::model::{Error, PetData};
+use crate::pet::utils::check_length;
+
+pub fn simulate(data: PetData, latitude: f64) -> Result<Array1<f64>, Error> {
+    compute(data.temperature, data.day_of_year, latitude, 24.)
+}
+
+/// Oudin et al. (2005) PET (mm/timestep) from the mean temperature (°C) and
+/// the extraterrestrial radiation.
+///
+/// For timesteps shorter than a day (in h), `day_of_year` is fractional
+/// (e.g. 100.25 for 6:00 on day 100) and the daily PET is distributed over
+/// the daylight hours following a sine curve.
+pub fn compute(
+    temperature: ArrayView1<f64>,
+    day_of_year: ArrayView1<f64>,
+    latitude: f64,
+    timestep: f64,
+) -> Result<Array1<f64>, Error> {
+    check_length("day_of_year", day_of_year, temperature)?;
+
+    let rho = 1000.; // water density (kg/m^3)
+    let n_timesteps: usize = temperature.len();
+
+    let mut potential_evapotranspiration: Vec<f64> = vec![];
+
+    for t in 0..n_timesteps {
+        let temp = temperature[t];
+        let lambda = 2.501 - 0.002361 * temp; // latent heat of vaporization (MJ/kg)
+        let (day, fraction) = if timestep < 24. {
+            (
+                day_of_year[t].floor(),
+                compute_daylight_fraction(day_of_year[t], latitude, timestep),
+            )
+        } else {
+            (day_of_year[t], 1.)
+        };
+        let re = compute_extraterrestrial_radiation(day, latitude);
+        potential_evapotranspiration.push(
+            fraction
+                * (re / (lambda * rho) * (temp + 5.) / 100. * 1000.).max(0.),
+        );
+    }
+
+    Ok(Array1::from_vec(potential_evapotranspiration))
+}
+
+/// Same as `compute` for many catchments in parallel, `temperature` being
+/// catchments x timesteps and `latitude` the latitude of each catchment.
+pub fn compute_batch(
+    temperature: ArrayView2<f64>,
+    day_of_year: ArrayView1<f64>,
+    latitude: ArrayView1<f64>,
+    timestep: f64,
+) -> Result<Array2<f64>, Error> {
+    if latitude.len() != temperature.nrows() {
+        return Err(Error::InputLengthMismatch(
+            "temperature",
+            "latitude",
+            temperature.nrows(),
+            latitude.len(),
+        ));
+    }
+
+    let results: Vec<Result<Array1<f64>, Error>> = (0..temperature.nrows())
+        .into_par_iter()
+        .map(|i| {
+            compute(temperature.row(i), day_of_year, latitude[i], timestep)
+        })
+        .collect();
+
+    let mut potential_evapotranspiration = Array2::zeros(temperature.dim());
+    for (i, result) in results.into_iter().enumerate() {
+        potential_evapotranspiration.row_mut(i).assign(&result?);
+    }
+    Ok(potential_evapotranspiration)
+}
+
+/// Fraction of the daily PET occurring during the timestep (h) starting at
+/// the fractional `day_of_year`, the PET following a sine curve between
+/// sunrise and sunset.
+fn compute_daylight_fraction(
+    day_of_year: f64,
+    latitude: f64,
+    timestep: f64,
+) -> f64 {
+    let daylight = compute_daylight_hours(day_of_year.floor(), latitude);
+    if daylight <= 0. {
+        return 0.;
+    }
+    let sunrise = 12. - daylight / 2.;
+    // cumulative fraction of the daily PET at hour h
+    let cumulative = |h: f64| {
+        let h = h.clamp(sunrise, sunrise + daylight);
+        (1. - (PI * (h - sunrise) / daylight).cos()) / 2.
+    };
+    let start = day_of_year.fract() * 24.;
+    cumulative(start + timestep) - cumulative(start)
+}
+
 #[pyfunction]
-fn simulate<'py>(
+#[pyo3(name = "simulate")]
+#[pyo3(signature = (temperature, day_of_year, latitude, timestep=24.0))]
+fn py_simulate<'py>(
     py: Python<'py>,
     temperature: PyReadonlyArray1<'py, f64>,
     day_of_year: PyReadonlyArray1<'py, f64>,
     latitude: f64,
-) -> Bound<'py, PyArray1<f64>> {
-    let temp = temperature.as_slice().unwrap();
-    let doy = day_of_year.as_slice().unwrap();
+    timestep: f64,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let pet = compute(
+        temperature.as_array(),
+        day_of_year.as_array(),
+        latitude,
+        timestep,
+    )?;
+    Ok(pet.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_batch")]
+#[pyo3(signature = (temperature, day_of_year, latitude, timestep=24.0))]
+fn py_simulate_batch<'py>(
+    py: Python<'py>,
+    temperature: PyReadonlyArray2<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, f64>,
+    latitude: PyReadonlyArray1<'py, f64>,
+    timestep: f64,
+) -> PyResult<Bound<'py, PyArray2<f64>>> {
+    let pet = compute_batch(
+        temperature.as_array(),
+        day_of_year.as_array(),
+        latitude.as_array(),
+        timestep,
+    )?;
+    Ok(pet.to_pyarray(py))
+}
 
+/// Daily extraterrestrial radiation (MJ m^-2 day^-1) for a latitude in
+/// degrees (FAO-56, eq. 21).
+pub fn compute_extraterrestrial_radiation(
+    day_of_year: f64,
+    latitude: f64,
+) -> f64 {
     let gsc = 0.082; // solar constant (MJ m^-2 min^-1)
-    let rho = 1000.; // water density (kg/m^3)
-    let n_timesteps: usize = temp.len();
     let lat_rad = PI * latitude / 180.; // latitude in rad
 
-    let mut potential_evapotranspiration: Vec<f64> = vec![];
+    let ds = compute_solar_declination(day_of_year);
+    let dr = 1. + 0.033 * (day_of_year * 2. * PI / 365.).cos(); // inverse relative distance Earth-Sun
+    let omega = compute_sunset_hour_angle(day_of_year, latitude);
+    24. * 60. / PI
+        * gsc
+        * dr
+        * (omega * lat_rad.sin() * ds.sin()
+            + lat_rad.cos() * ds.cos() * omega.sin())
+}
 
-    for t in 0..n_timesteps {
-        let lambda = 2.501 - 0.002361 * temp[t]; // latent heat of vaporization (MJ/kg)
-        let doy = doy[t];
-        let ds = 0.409 * (2. * PI / 365. * doy - 1.39).sin(); // solar declination (rad)
-        let dr = 1. + 0.033 * (doy * 2. * PI / 365.).cos(); // inverse relative distance Earth-Sun
-        let omega = (-lat_rad.tan() * ds.tan()).clamp(-1., 1.).acos(); // sunset hour angle (rad)
-        let re = 24. * 60. / PI
-            * gsc
-            * dr
-            * (omega * lat_rad.sin() * ds.sin() + lat_rad.cos() * ds.cos() * omega.sin()); // extraterrestrial radiation (MJ m^-2 day^-1)
-        potential_evapotranspiration
-            .push((re / (lambda * rho) * (temp[t] + 5.) / 100. * 1000.).max(0.));
-    }
+/// Maximum daylight hours for a latitude in degrees (FAO-56, eq. 34).
+pub fn compute_daylight_hours(day_of_year: f64, latitude: f64) -> f64 {
+    24. / PI * compute_sunset_hour_angle(day_of_year, latitude)
+}
+
+/// Solar declination (rad) (FAO-56, eq. 24).
+fn compute_solar_declination(day_of_year: f64) -> f64 {
+    0.409 * (2. * PI / 365. * day_of_year - 1.39).sin()
+}
 
-    PyArray1::from_vec(py, potential_evapotranspiration)
+/// Sunset hour angle (rad) (FAO-56, eq. 25).
+fn compute_sunset_hour_angle(day_of_year: f64, latitude: f64) -> f64 {
+    let lat_rad = PI * latitude / 180.;
+    let ds = compute_solar_declination(day_of_year);
+    (-lat_rad.tan() * ds.tan()).clamp(-1., 1.).acos()
 }
 
 pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
     let m = PyModule::new(py, "oudin")?;
-    m.add_function(wrap_pyfunction!(simulate, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_batch, &m)?)?;
     Ok(m)
 }
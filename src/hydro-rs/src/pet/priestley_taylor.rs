@@ -0,0 +1,113 @@
+use ndarray::{Array1, ArrayView1};
+use numpy::{PyArray1, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+
+use crate::model::{Error, PetData};
+use crate::pet::oudin::compute_extraterrestrial_radiation;
+use crate::pet::utils::check_length;
+
+pub fn simulate(data: PetData, latitude: f64) -> Result<Array1<f64>, Error> {
+    compute(
+        data.temperature,
+        data.day_of_year,
+        latitude,
+        data.net_radiation,
+        1.26,
+    )
+}
+
+/// Priestley-Taylor (1972) PET (mm/day) from the daily mean temperature
+/// (°C) and the net radiation (MJ m^-2 day^-1), with the soil heat flux
+/// neglected. When the net radiation isn't given, it is estimated from the
+/// extraterrestrial radiation (see `estimate_net_radiation`). `alpha` is
+/// 1.26 by default.
+pub fn compute(
+    temperature: ArrayView1<f64>,
+    day_of_year: ArrayView1<f64>,
+    latitude: f64,
+    net_radiation: Option<ArrayView1<f64>>,
+    alpha: f64,
+) -> Result<Array1<f64>, Error> {
+    check_length("day_of_year", day_of_year, temperature)?;
+    if let Some(net_radiation) = net_radiation {
+        check_length("net_radiation", net_radiation, temperature)?;
+    }
+
+    let lambda = 2.45; // latent heat of vaporization (MJ/kg)
+    let gamma = 0.067; // psychrometric constant at sea level (kPa/°C)
+
+    Ok(Array1::from_shape_fn(temperature.len(), |t| {
+        let temp = temperature[t];
+        let rn = net_radiation.map_or_else(
+            || estimate_net_radiation(temp, day_of_year[t], latitude),
+            |x| x[t],
+        );
+        // slope of the saturation vapour pressure curve (kPa/°C)
+        let delta = 4098. * compute_saturation_vapour_pressure(temp)
+            / (temp + 237.3).powi(2);
+        (alpha * delta / (delta + gamma) * rn / lambda).max(0.)
+    }))
+}
+
+/// Net radiation (MJ m^-2 day^-1) estimated from the temperature alone
+/// (FAO-56, eq. 37-40), assuming a solar radiation of half the
+/// extraterrestrial radiation, an albedo of 0.23 and an actual vapour
+/// pressure at saturation.
+pub fn estimate_net_radiation(
+    temperature: f64,
+    day_of_year: f64,
+    latitude: f64,
+) -> f64 {
+    // Stefan-Boltzmann constant (MJ K^-4 m^-2 day^-1)
+    let sigma = 4.903e-9;
+
+    let ra = compute_extraterrestrial_radiation(day_of_year, latitude);
+    let rs = 0.5 * ra;
+    let rso = 0.75 * ra;
+    let ea = compute_saturation_vapour_pressure(temperature);
+
+    let rns = (1. - 0.23) * rs;
+    let rnl = sigma
+        * (temperature + 273.16).powi(4)
+        * (0.34 - 0.14 * ea.sqrt())
+        * (1.35 * rs / rso - 0.35);
+    rns - rnl
+}
+
+/// Saturation vapour pressure (kPa) (FAO-56, eq. 11).
+fn compute_saturation_vapour_pressure(temperature: f64) -> f64 {
+    0.6108 * (17.27 * temperature / (temperature + 237.3)).exp()
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+#[pyo3(signature = (
+    temperature,
+    day_of_year,
+    latitude,
+    net_radiation=None,
+    alpha=1.26,
+))]
+fn py_simulate<'py>(
+    py: Python<'py>,
+    temperature: PyReadonlyArray1<'py, f64>,
+    day_of_year: PyReadonlyArray1<'py, f64>,
+    latitude: f64,
+    net_radiation: Option<PyReadonlyArray1<'py, f64>>,
+    alpha: f64,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let pet = compute(
+        temperature.as_array(),
+        day_of_year.as_array(),
+        latitude,
+        net_radiation.as_ref().map(|x| x.as_array()),
+        alpha,
+    )?;
+    Ok(pet.to_pyarray(py))
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "priestley_taylor")?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,20 @@
+use ndarray::ArrayView1;
+
+use crate::model::Error;
+
+/// Checks that `input` has the same length as the temperature.
+pub fn check_length(
+    name: &'static str,
+    input: ArrayView1<f64>,
+    temperature: ArrayView1<f64>,
+) -> Result<(), Error> {
+    if input.len() != temperature.len() {
+        return Err(Error::InputLengthMismatch(
+            "temperature",
+            name,
+            temperature.len(),
+            input.len(),
+        ));
+    }
+    Ok(())
+}
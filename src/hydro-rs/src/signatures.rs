@@ -0,0 +1,232 @@
+use ndarray::ArrayView1;
+use numpy::PyReadonlyArray1;
+use pyo3::prelude::*;
+
+use crate::metrics::{MetricsError, MIN_VALID_OBSERVATIONS};
+use crate::model::{Data, PyData};
+
+/// Runoff ratio of the observations and simulations, their total over the
+/// total precipitation, and the simulated minus the observed ratio. Only
+/// the timesteps with a valid observation count.
+pub fn calculate_runoff_ratio(
+    data: Data,
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(f64, f64, f64), MetricsError> {
+    let valid = valid_timesteps(observations, simulations)?;
+    if data.precipitation.len() != observations.len() {
+        return Err(MetricsError::DataLengthMismatch(
+            observations.len(),
+            data.precipitation.len(),
+        ));
+    }
+    let total = |series: ArrayView1<f64>| {
+        valid.iter().map(|&t| series[t]).sum::<f64>()
+    };
+    let precipitation = total(data.precipitation);
+    let observed = total(observations) / precipitation;
+    let simulated = total(simulations) / precipitation;
+    Ok((observed, simulated, simulated - observed))
+}
+
+/// Richards-Baker flashiness index (Baker et al., 2004) of the observations
+/// and simulations, the sum of the absolute day-to-day changes over the
+/// total flow, and the simulated minus the observed index, negative when
+/// the simulations are too smooth. Only the changes between consecutive
+/// valid observations count.
+pub fn calculate_flashiness(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(f64, f64, f64), MetricsError> {
+    let valid = valid_timesteps(observations, simulations)?;
+    let index = |series: ArrayView1<f64>| {
+        let changes: f64 = valid
+            .windows(2)
+            .filter(|pair| pair[1] == pair[0] + 1)
+            .map(|pair| (series[pair[1]] - series[pair[0]]).abs())
+            .sum();
+        let total: f64 = valid.iter().map(|&t| series[t]).sum();
+        changes / total
+    };
+    let observed = index(observations);
+    let simulated = index(simulations);
+    Ok((observed, simulated, simulated - observed))
+}
+
+/// Rising limb density (Sawicz et al., 2011) of the observations and
+/// simulations, the number of rising limbs over the number of rising
+/// timesteps, and the simulated minus the observed density.
+pub fn calculate_rising_limb_density(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(f64, f64, f64), MetricsError> {
+    let valid = valid_timesteps(observations, simulations)?;
+    let observed = limb_density(observations, &valid, true);
+    let simulated = limb_density(simulations, &valid, true);
+    Ok((observed, simulated, simulated - observed))
+}
+
+/// Falling limb density of the observations and simulations, like
+/// `calculate_rising_limb_density` for the recessions.
+pub fn calculate_falling_limb_density(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<(f64, f64, f64), MetricsError> {
+    let valid = valid_timesteps(observations, simulations)?;
+    let observed = limb_density(observations, &valid, false);
+    let simulated = limb_density(simulations, &valid, false);
+    Ok((observed, simulated, simulated - observed))
+}
+
+/// Number of limbs over their total duration, a limb being consecutive
+/// rising (or falling) changes between valid timesteps.
+fn limb_density(
+    series: ArrayView1<f64>,
+    valid: &[usize],
+    rising: bool,
+) -> f64 {
+    let mut n_limbs = 0;
+    let mut duration = 0;
+    let mut in_limb = false;
+    for pair in valid.windows(2) {
+        let change = series[pair[1]] - series[pair[0]];
+        let in_direction = pair[1] == pair[0] + 1
+            && if rising { change > 0. } else { change < 0. };
+        if in_direction {
+            if !in_limb {
+                n_limbs += 1;
+            }
+            duration += 1;
+        }
+        in_limb = in_direction;
+    }
+    n_limbs as f64 / duration as f64
+}
+
+/// Lag-`lag` autocorrelation of the observations and simulations, a proxy
+/// for the memory of the catchment storages, and the simulated minus the
+/// observed autocorrelation. Only the pairs of valid observations `lag`
+/// timesteps apart count.
+pub fn calculate_autocorrelation(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+    lag: usize,
+) -> Result<(f64, f64, f64), MetricsError> {
+    let valid = valid_timesteps(observations, simulations)?;
+    let autocorrelation = |series: ArrayView1<f64>| {
+        let mean =
+            valid.iter().map(|&t| series[t]).sum::<f64>() / valid.len() as f64;
+        let covariance: f64 = valid
+            .iter()
+            .filter(|&&t| {
+                t + lag < series.len() && !observations[t + lag].is_nan()
+            })
+            .map(|&t| (series[t] - mean) * (series[t + lag] - mean))
+            .sum();
+        let variance: f64 =
+            valid.iter().map(|&t| (series[t] - mean).powi(2)).sum();
+        covariance / variance
+    };
+    let observed = autocorrelation(observations);
+    let simulated = autocorrelation(simulations);
+    Ok((observed, simulated, simulated - observed))
+}
+
+/// Timesteps where the observations aren't missing, the signatures of both
+/// series being computed on them.
+fn valid_timesteps(
+    observations: ArrayView1<f64>,
+    simulations: ArrayView1<f64>,
+) -> Result<Vec<usize>, MetricsError> {
+    if observations.len() != simulations.len() {
+        return Err(MetricsError::LengthMismatch(
+            observations.len(),
+            simulations.len(),
+        ));
+    }
+    let valid: Vec<usize> = (0..observations.len())
+        .filter(|&t| !observations[t].is_nan())
+        .collect();
+    if valid.len() < MIN_VALID_OBSERVATIONS {
+        return Err(MetricsError::NotEnoughObservations(valid.len()));
+    }
+    Ok(valid)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_runoff_ratio")]
+pub fn py_calculate_runoff_ratio<'py>(
+    data: PyData<'py>,
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+) -> PyResult<(f64, f64, f64)> {
+    Ok(calculate_runoff_ratio(
+        data.as_data()?,
+        observations.as_array(),
+        simulations.as_array(),
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_flashiness")]
+pub fn py_calculate_flashiness<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+) -> PyResult<(f64, f64, f64)> {
+    Ok(calculate_flashiness(
+        observations.as_array(),
+        simulations.as_array(),
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_rising_limb_density")]
+pub fn py_calculate_rising_limb_density<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+) -> PyResult<(f64, f64, f64)> {
+    Ok(calculate_rising_limb_density(
+        observations.as_array(),
+        simulations.as_array(),
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_falling_limb_density")]
+pub fn py_calculate_falling_limb_density<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+) -> PyResult<(f64, f64, f64)> {
+    Ok(calculate_falling_limb_density(
+        observations.as_array(),
+        simulations.as_array(),
+    )?)
+}
+
+#[pyfunction]
+#[pyo3(name = "calculate_autocorrelation", signature = (
+    observations,
+    simulations,
+    lag=1,
+))]
+pub fn py_calculate_autocorrelation<'py>(
+    observations: PyReadonlyArray1<'py, f64>,
+    simulations: PyReadonlyArray1<'py, f64>,
+    lag: usize,
+) -> PyResult<(f64, f64, f64)> {
+    Ok(calculate_autocorrelation(
+        observations.as_array(),
+        simulations.as_array(),
+        lag,
+    )?)
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "signatures")?;
+    m.add_function(wrap_pyfunction!(py_calculate_runoff_ratio, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_flashiness, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_rising_limb_density, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_falling_limb_density, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_autocorrelation, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,192 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+use crate::snow::utils::{
+    compute_solid_fraction, ElevationLayers, SnowStates,
+};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to ctg, kf, g_acc, g_threshold
+    let default_values = array![0.25, 3.74, 100.0, 300.0];
+    let bounds = array![[0.0, 1.0], [0.0, 20.0], [1.0, 500.0], [10.0, 800.0]];
+    (default_values, bounds)
+}
+
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    Ok(simulate_states(params, data, metadata)?.effective_precipitation)
+}
+
+/// Runs CemaNeige with the linear hysteresis of the snow cover area
+/// (Riboust et al., 2019) and returns the effective precipitation (mm/day)
+/// and the fractional snow cover of the catchment.
+///
+/// The snow cover of each layer grows linearly with the accumulated snow
+/// (reaching full cover after g_acc mm) and, during melt, follows the
+/// depletion curve snowpack / g_threshold, which creates a hysteresis
+/// between accumulation and melt. The snow cover also scales the melt rate.
+pub fn simulate_with_snow_cover(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<(Array1<f64>, Array1<f64>), Error> {
+    let states = simulate_states(params, data, metadata)?;
+    Ok((states.effective_precipitation, states.snow_cover))
+}
+
+/// Same as `simulate_with_snow_cover`, but also returns the snowpack states.
+pub fn simulate_states(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<SnowStates, Error> {
+    let [ctg, kf, g_acc, g_threshold]: [f64; 4] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(4, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let temperature = data.temperature;
+    let pet = data.pet;
+    let day_of_year = data.day_of_year;
+    let layers = ElevationLayers::new(metadata)?;
+
+    let vmin = 0.1;
+    let tf = 0.0;
+    let n_layers = layers.n_layers();
+    let n_timesteps = precipitation.len();
+
+    let mut states = SnowStates::new(metadata, n_timesteps, n_layers, true);
+
+    let mut snowpack: Vec<f64> = vec![0.0; n_layers];
+    let mut thermal_state: Vec<f64> = vec![0.0; n_layers];
+    let mut layer_cover: Vec<f64> = vec![0.0; n_layers];
+
+    for t in 0..n_timesteps {
+        let mut total_liquid: f64 = 0.0;
+        let mut total_melt: f64 = 0.0;
+        let mut total_cover: f64 = 0.0;
+
+        for (i, (layer_temperature, layer_precip)) in layers
+            .extrapolate(temperature[t], precipitation[t], day_of_year[t])
+            .enumerate()
+        {
+            let p_solid =
+                compute_solid_fraction(layer_temperature) * layer_precip;
+            total_liquid += layer_precip - p_solid;
+            snowpack[i] += p_solid;
+            snowpack[i] -= metadata.sublimation.compute(snowpack[i], pet[t]);
+
+            thermal_state[i] = (thermal_state[i] * ctg
+                + layer_temperature * (1.0 - ctg))
+                .min(0.0);
+
+            let potential =
+                if thermal_state[i] >= tf && layer_temperature > 0.0 {
+                    snowpack[i].min((layer_temperature - tf) * kf)
+                } else {
+                    0.0
+                };
+            let snow_melt = potential * (layer_cover[i] * (1.0 - vmin) + vmin);
+            snowpack[i] -= snow_melt;
+            total_melt += snow_melt;
+
+            let change = p_solid - snow_melt;
+            if change > 0.0 {
+                layer_cover[i] = (layer_cover[i] + change / g_acc).min(1.0);
+            } else if change < 0.0 {
+                layer_cover[i] =
+                    (snowpack[i] / g_threshold).min(layer_cover[i]);
+            }
+            if snowpack[i] <= 0.0 {
+                layer_cover[i] = 0.0;
+            }
+            total_cover += layer_cover[i];
+        }
+
+        states.record(
+            t,
+            (total_liquid + total_melt) / n_layers as f64,
+            &snowpack,
+            Some(&thermal_state),
+        );
+        // replaces the cover derived from the snowpack by the hysteresis
+        states.snow_cover[t] = total_cover / n_layers as f64;
+    }
+
+    Ok(states)
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_with_snow_cover")]
+pub fn py_simulate_with_snow_cover<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<(Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>)> {
+    let (effective_precipitation, snow_cover) = simulate_with_snow_cover(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    Ok((
+        effective_precipitation.to_pyarray(py),
+        snow_cover.to_pyarray(py),
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_states")]
+pub fn py_simulate_states<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyDict>> {
+    let states = simulate_states(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    states.to_py_dict(py)
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "cemaneige_lh")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_with_snow_cover, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_states, &m)?)?;
+    Ok(m)
+}
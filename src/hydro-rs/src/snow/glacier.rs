@@ -0,0 +1,205 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+use crate::snow::utils::{
+    compute_solid_fraction, ElevationLayers, SnowStates,
+};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to ddf_snow, ddf_ice, k_ice, tt
+    let default_values = array![3.0, 6.0, 10.0, 0.0];
+    let bounds = array![[0.0, 10.0], [0.0, 15.0], [1.0, 50.0], [-3.0, 3.0]];
+    (default_values, bounds)
+}
+
+/// Runs a degree-day snow and glacier melt model and returns the effective
+/// precipitation (rainfall, snowmelt and ice reservoir outflow) in mm/day.
+///
+/// The glacierized fraction of `Metadata::glacier_fraction` is assigned to
+/// the highest elevation layers. Seasonal snow melts at the rate ddf_snow
+/// above tt in every layer. Once the snowpack of a layer is depleted, its
+/// glacierized area melts at the rate ddf_ice. Ice melt is routed through a
+/// linear reservoir with time constant k_ice (days).
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    Ok(simulate_states(params, data, metadata)?.effective_precipitation)
+}
+
+/// Same as `simulate`, but also returns the fractional snow cover of the
+/// catchment derived from the snowpack of each layer.
+pub fn simulate_with_snow_cover(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<(Array1<f64>, Array1<f64>), Error> {
+    let states = simulate_states(params, data, metadata)?;
+    Ok((states.effective_precipitation, states.snow_cover))
+}
+
+/// Same as `simulate`, but also returns the snowpack states.
+pub fn simulate_states(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<SnowStates, Error> {
+    let [ddf_snow, ddf_ice, k_ice, tt]: [f64; 4] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(4, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let temperature = data.temperature;
+    let pet = data.pet;
+    let day_of_year = data.day_of_year;
+    let layers = ElevationLayers::new(metadata)?;
+
+    let n_layers = layers.n_layers();
+    let n_timesteps = precipitation.len();
+    let ice_recession = (-1. / k_ice).exp();
+
+    let glacier_cover = compute_glacier_cover(
+        metadata.elevation_layers,
+        metadata.glacier_fraction,
+    );
+
+    let mut states = SnowStates::new(metadata, n_timesteps, n_layers, false);
+
+    let mut snowpack: Vec<f64> = vec![0.0; n_layers];
+    let mut ice_store: f64 = 0.0;
+
+    for t in 0..n_timesteps {
+        let mut total_liquid: f64 = 0.0;
+        let mut total_melt: f64 = 0.0;
+        let mut total_ice_melt: f64 = 0.0;
+
+        for (i, (layer_temperature, layer_precip)) in layers
+            .extrapolate(temperature[t], precipitation[t], day_of_year[t])
+            .enumerate()
+        {
+            let p_solid =
+                compute_solid_fraction(layer_temperature) * layer_precip;
+            total_liquid += layer_precip - p_solid;
+            snowpack[i] += p_solid;
+            snowpack[i] -= metadata.sublimation.compute(snowpack[i], pet[t]);
+
+            if layer_temperature > tt {
+                let melt =
+                    (ddf_snow * (layer_temperature - tt)).min(snowpack[i]);
+                snowpack[i] -= melt;
+                total_melt += melt;
+
+                if snowpack[i] <= 0.0 {
+                    total_ice_melt +=
+                        glacier_cover[i] * ddf_ice * (layer_temperature - tt);
+                }
+            }
+        }
+
+        ice_store += total_ice_melt / n_layers as f64;
+        let ice_outflow = (1. - ice_recession) * ice_store;
+        ice_store -= ice_outflow;
+
+        states.record(
+            t,
+            (total_liquid + total_melt) / n_layers as f64 + ice_outflow,
+            &snowpack,
+            None,
+        );
+    }
+
+    Ok(states)
+}
+
+/// Returns the glacierized fraction of each elevation layer, filling the
+/// highest layers first.
+fn compute_glacier_cover(
+    elevation_layers: ArrayView1<f64>,
+    glacier_fraction: f64,
+) -> Vec<f64> {
+    let n_layers = elevation_layers.len();
+    let mut indices: Vec<usize> = (0..n_layers).collect();
+    indices
+        .sort_by(|&a, &b| elevation_layers[b].total_cmp(&elevation_layers[a]));
+
+    let mut remaining = glacier_fraction.clamp(0., 1.) * n_layers as f64;
+    let mut cover = vec![0.0; n_layers];
+    for i in indices {
+        cover[i] = remaining.min(1.);
+        remaining -= cover[i];
+    }
+    cover
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_with_snow_cover")]
+pub fn py_simulate_with_snow_cover<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<(Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>)> {
+    let (effective_precipitation, snow_cover) = simulate_with_snow_cover(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    Ok((
+        effective_precipitation.to_pyarray(py),
+        snow_cover.to_pyarray(py),
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_states")]
+pub fn py_simulate_states<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyDict>> {
+    let states = simulate_states(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    states.to_py_dict(py)
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "glacier")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_with_snow_cover, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_states, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,179 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+use crate::snow::utils::{ElevationLayers, SnowStates};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to tt, cfmax, sfcf, cfr, cwh
+    let default_values = array![0.0, 3.5, 1.0, 0.05, 0.1];
+    let bounds =
+        array![[-3.0, 3.0], [0.5, 10.0], [0.5, 1.5], [0.0, 0.1], [0.0, 0.2]];
+    (default_values, bounds)
+}
+
+/// Runs the HBV snow routine (Bergström, 1976) and returns the effective
+/// precipitation (rainfall and snowpack outflow) in mm/day.
+///
+/// In each elevation layer, precipitation falls as snow below the threshold
+/// temperature tt (corrected by sfcf) and as rain above it. Snow melts at
+/// the degree-day rate cfmax above tt and the liquid water held in the
+/// snowpack refreezes at the rate cfr * cfmax below it. The snowpack
+/// retains liquid water up to a fraction cwh of its solid content and only
+/// the excess leaves the pack.
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    Ok(simulate_states(params, data, metadata)?.effective_precipitation)
+}
+
+/// Same as `simulate`, but also returns the fractional snow cover of the
+/// catchment derived from the snowpack of each layer.
+pub fn simulate_with_snow_cover(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<(Array1<f64>, Array1<f64>), Error> {
+    let states = simulate_states(params, data, metadata)?;
+    Ok((states.effective_precipitation, states.snow_cover))
+}
+
+/// Same as `simulate`, but also returns the snowpack states.
+pub fn simulate_states(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<SnowStates, Error> {
+    let [tt, cfmax, sfcf, cfr, cwh]: [f64; 5] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(5, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let temperature = data.temperature;
+    let pet = data.pet;
+    let day_of_year = data.day_of_year;
+    let layers = ElevationLayers::new(metadata)?;
+
+    let n_layers = layers.n_layers();
+    let n_timesteps = precipitation.len();
+
+    let mut states = SnowStates::new(metadata, n_timesteps, n_layers, false);
+
+    let mut snowpack: Vec<f64> = vec![0.0; n_layers];
+    let mut liquid_water: Vec<f64> = vec![0.0; n_layers];
+
+    for t in 0..n_timesteps {
+        let mut total_outflow: f64 = 0.0;
+
+        for (i, (layer_temperature, layer_precip)) in layers
+            .extrapolate(temperature[t], precipitation[t], day_of_year[t])
+            .enumerate()
+        {
+            let rain = if layer_temperature < tt {
+                snowpack[i] += sfcf * layer_precip;
+                0.0
+            } else {
+                layer_precip
+            };
+            snowpack[i] -= metadata.sublimation.compute(snowpack[i], pet[t]);
+
+            if layer_temperature > tt {
+                let melt = (cfmax * (layer_temperature - tt)).min(snowpack[i]);
+                snowpack[i] -= melt;
+                liquid_water[i] += melt;
+            } else {
+                let refreeze = (cfr * cfmax * (tt - layer_temperature))
+                    .min(liquid_water[i]);
+                snowpack[i] += refreeze;
+                liquid_water[i] -= refreeze;
+            }
+
+            liquid_water[i] += rain;
+            let outflow = (liquid_water[i] - cwh * snowpack[i]).max(0.0);
+            liquid_water[i] -= outflow;
+            total_outflow += outflow;
+        }
+
+        let swe: Vec<f64> = snowpack
+            .iter()
+            .zip(&liquid_water)
+            .map(|(s, l)| s + l)
+            .collect();
+        states.record(t, total_outflow / n_layers as f64, &swe, None);
+    }
+
+    Ok(states)
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_with_snow_cover")]
+pub fn py_simulate_with_snow_cover<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<(Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>)> {
+    let (effective_precipitation, snow_cover) = simulate_with_snow_cover(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    Ok((
+        effective_precipitation.to_pyarray(py),
+        snow_cover.to_pyarray(py),
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_states")]
+pub fn py_simulate_states<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyDict>> {
+    let states = simulate_states(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    states.to_py_dict(py)
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "hbv")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_with_snow_cover, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_states, &m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,182 @@
+#![allow(clippy::type_complexity)]
+
+use ndarray::{array, Array1, Array2, ArrayView1};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1, ToPyArray};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::model::{Data, Error, Metadata, PyData, PyMetadata};
+use crate::pet::oudin::compute_extraterrestrial_radiation;
+use crate::snow::utils::{
+    compute_solid_fraction, ElevationLayers, SnowStates,
+};
+
+pub fn init() -> (Array1<f64>, Array2<f64>) {
+    // corresponds to mf, a_rad, tt
+    let default_values = array![2.0, 0.05, 0.0];
+    let bounds = array![[0.0, 10.0], [0.0, 0.3], [-3.0, 3.0]];
+    (default_values, bounds)
+}
+
+/// Runs a radiation-enhanced temperature-index snow model (Hock, 1999) and
+/// returns the effective precipitation (rainfall and snowmelt) in mm/day.
+///
+/// Snow accumulates in each elevation layer with the CemaNeige solid
+/// fraction. Above the threshold temperature tt, melt is
+/// (mf + a_rad * rso) * (t - tt), where rso is the clear-sky solar
+/// radiation (MJ m^-2 day^-1) estimated from the extraterrestrial radiation
+/// at `Metadata::latitude` and the layer elevation (FAO-56, eq. 37).
+pub fn simulate(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<Array1<f64>, Error> {
+    Ok(simulate_states(params, data, metadata)?.effective_precipitation)
+}
+
+/// Same as `simulate`, but also returns the fractional snow cover of the
+/// catchment derived from the snowpack of each layer.
+pub fn simulate_with_snow_cover(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<(Array1<f64>, Array1<f64>), Error> {
+    let states = simulate_states(params, data, metadata)?;
+    Ok((states.effective_precipitation, states.snow_cover))
+}
+
+/// Same as `simulate`, but also returns the snowpack states.
+pub fn simulate_states(
+    params: ArrayView1<f64>,
+    data: Data,
+    metadata: &Metadata,
+) -> Result<SnowStates, Error> {
+    let [mf, a_rad, tt]: [f64; 3] = params
+        .as_slice()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::ParamsMismatch(3, params.len()))?;
+
+    let precipitation = data.precipitation;
+    let temperature = data.temperature;
+    let pet = data.pet;
+    let day_of_year = data.day_of_year;
+    let layers = ElevationLayers::new(metadata)?;
+
+    let n_layers = layers.n_layers();
+    let n_timesteps = precipitation.len();
+
+    let clear_sky_fractions: Vec<f64> = metadata
+        .elevation_layers
+        .iter()
+        .map(|&z| 0.75 + 2e-5 * z)
+        .collect();
+
+    let mut states = SnowStates::new(metadata, n_timesteps, n_layers, false);
+
+    let mut snowpack: Vec<f64> = vec![0.0; n_layers];
+
+    for t in 0..n_timesteps {
+        let radiation = compute_extraterrestrial_radiation(
+            day_of_year[t] as f64,
+            metadata.latitude,
+        );
+
+        let mut total_liquid: f64 = 0.0;
+        let mut total_melt: f64 = 0.0;
+
+        for (i, (layer_temperature, layer_precip)) in layers
+            .extrapolate(temperature[t], precipitation[t], day_of_year[t])
+            .enumerate()
+        {
+            let p_solid =
+                compute_solid_fraction(layer_temperature) * layer_precip;
+            total_liquid += layer_precip - p_solid;
+            snowpack[i] += p_solid;
+            snowpack[i] -= metadata.sublimation.compute(snowpack[i], pet[t]);
+
+            if layer_temperature > tt {
+                let clear_sky_radiation = clear_sky_fractions[i] * radiation;
+                let melt = ((mf + a_rad * clear_sky_radiation)
+                    * (layer_temperature - tt))
+                    .min(snowpack[i]);
+                snowpack[i] -= melt;
+                total_melt += melt;
+            }
+        }
+
+        states.record(
+            t,
+            (total_liquid + total_melt) / n_layers as f64,
+            &snowpack,
+            None,
+        );
+    }
+
+    Ok(states)
+}
+
+#[pyfunction]
+#[pyo3(name = "init")]
+pub fn py_init<'py>(
+    py: Python<'py>,
+) -> (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>) {
+    let (default_values, bounds) = init();
+    (default_values.to_pyarray(py), bounds.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate")]
+pub fn py_simulate<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let simulation =
+        simulate(params.as_array(), data.as_data()?, &metadata.as_metadata())?;
+    Ok(simulation.to_pyarray(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_with_snow_cover")]
+pub fn py_simulate_with_snow_cover<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<(Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>)> {
+    let (effective_precipitation, snow_cover) = simulate_with_snow_cover(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    Ok((
+        effective_precipitation.to_pyarray(py),
+        snow_cover.to_pyarray(py),
+    ))
+}
+
+#[pyfunction]
+#[pyo3(name = "simulate_states")]
+pub fn py_simulate_states<'py>(
+    py: Python<'py>,
+    params: PyReadonlyArray1<f64>,
+    data: PyData,
+    metadata: PyMetadata,
+) -> PyResult<Bound<'py, PyDict>> {
+    let states = simulate_states(
+        params.as_array(),
+        data.as_data()?,
+        &metadata.as_metadata(),
+    )?;
+    states.to_py_dict(py)
+}
+
+pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
+    let m = PyModule::new(py, "hock")?;
+    m.add_function(wrap_pyfunction!(py_init, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_with_snow_cover, &m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_states, &m)?)?;
+    Ok(m)
+}
@@ -1,18 +1,40 @@
 pub mod cemaneige;
-use ndarray::{Array1, Array2};
-
-use crate::model::{Error, SimulateFnPtr};
+pub mod cemaneige_lh;
+pub mod glacier;
+pub mod hbv;
+pub mod hock;
+pub mod utils;
+use crate::model::{Error, InitFn, SimulateFnPtr};
+use crate::snow::utils::SnowStatesFn;
 use crate::utils::register_submodule;
 use pyo3::prelude::*;
 
-pub fn get_model(
-    model: &str,
-) -> Result<(fn() -> (Array1<f64>, Array2<f64>), SimulateFnPtr), Error> {
+pub fn get_model(model: &str) -> Result<(InitFn, SimulateFnPtr), Error> {
     match model {
         "cemaneige" => Ok((cemaneige::init, cemaneige::simulate)),
+        "cemaneige_lh" => Ok((cemaneige_lh::init, cemaneige_lh::simulate)),
+        "hbv" => Ok((hbv::init, hbv::simulate)),
+        "hock" => Ok((hock::init, hock::simulate)),
+        "glacier" => Ok((glacier::init, glacier::simulate)),
+        _ => Err(Error::WrongModel(
+            model.to_string(),
+            "cemaneige, cemaneige_lh, hbv, hock, glacier".to_string(),
+        )),
+    }
+}
+
+/// Same as `get_model`, but returns the function simulating the snowpack
+/// states.
+pub fn get_states_model(model: &str) -> Result<SnowStatesFn, Error> {
+    match model {
+        "cemaneige" => Ok(cemaneige::simulate_states),
+        "cemaneige_lh" => Ok(cemaneige_lh::simulate_states),
+        "hbv" => Ok(hbv::simulate_states),
+        "hock" => Ok(hock::simulate_states),
+        "glacier" => Ok(glacier::simulate_states),
         _ => Err(Error::WrongModel(
             model.to_string(),
-            "cemaneige".to_string(),
+            "cemaneige, cemaneige_lh, hbv, hock, glacier".to_string(),
         )),
     }
 }
@@ -20,5 +42,14 @@ pub fn get_model(
 pub fn make_module(py: Python<'_>) -> PyResult<Bound<'_, PyModule>> {
     let m = PyModule::new(py, "snow")?;
     register_submodule(py, &m, &cemaneige::make_module(py)?, "hydro_rs.snow")?;
+    register_submodule(
+        py,
+        &m,
+        &cemaneige_lh::make_module(py)?,
+        "hydro_rs.snow",
+    )?;
+    register_submodule(py, &m, &hbv::make_module(py)?, "hydro_rs.snow")?;
+    register_submodule(py, &m, &hock::make_module(py)?, "hydro_rs.snow")?;
+    register_submodule(py, &m, &glacier::make_module(py)?, "hydro_rs.snow")?;
     Ok(m)
 }
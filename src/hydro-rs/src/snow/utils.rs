@@ -0,0 +1,279 @@
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use numpy::ToPyArray;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::model::{Data, Error, Metadata};
+
+// precipitation gradient of CemaNeige (1/m)
+pub const DEFAULT_PRECIPITATION_GRADIENT: f64 = 0.0004;
+// elevation above which precipitation stops increasing (m)
+const MAX_PRECIPITATION_ELEVATION: f64 = 4000.0;
+
+/// Extrapolation of the catchment temperature and precipitation to the
+/// elevation layers of `Metadata::elevation_layers`, which are assumed to
+/// cover equal areas.
+pub struct ElevationLayers {
+    elevation_offsets: Vec<f64>, // hm, relative to the median elevation
+    precipitation_weights: Vec<f64>,
+    temperature_lapse_rate: Option<f64>, // °C/100m
+}
+
+impl ElevationLayers {
+    /// Uses the lapse rates of `Metadata` when given, else the seasonal
+    /// temperature gradients and precipitation gradient of CemaNeige.
+    pub fn new(metadata: &Metadata) -> Result<Self, Error> {
+        Self::with_lapse_rates(
+            metadata,
+            metadata.temperature_lapse_rate,
+            metadata
+                .precipitation_gradient
+                .unwrap_or(DEFAULT_PRECIPITATION_GRADIENT),
+        )
+    }
+
+    /// Uses the given lapse rates, so models can calibrate them.
+    ///
+    /// `temperature_lapse_rate` is in °C/100m (negative when temperature
+    /// decreases with elevation), `None` meaning the seasonal gradients of
+    /// CemaNeige. `precipitation_gradient` (1/m) scales the precipitation
+    /// of each layer by exp(gradient * (z - median_elevation)), with
+    /// elevations capped at 4000 m, and the factors are normalized so the
+    /// catchment average is preserved.
+    pub fn with_lapse_rates(
+        metadata: &Metadata,
+        temperature_lapse_rate: Option<f64>,
+        precipitation_gradient: f64,
+    ) -> Result<Self, Error> {
+        let elevation_layers = metadata.elevation_layers;
+        let median_elevation = metadata.median_elevation;
+
+        if elevation_layers.is_empty() {
+            return Err(Error::MissingMetadata("elevation_layers"));
+        }
+
+        let elevation_offsets: Vec<f64> = elevation_layers
+            .iter()
+            .map(|&z| (z - median_elevation) / 100.0)
+            .collect();
+
+        let weights: Vec<f64> = elevation_layers
+            .iter()
+            .map(|&z| {
+                (precipitation_gradient
+                    * (z.min(MAX_PRECIPITATION_ELEVATION)
+                        - median_elevation.min(MAX_PRECIPITATION_ELEVATION)))
+                .exp()
+            })
+            .collect();
+        let mean = weights.iter().sum::<f64>() / weights.len() as f64;
+        let precipitation_weights = weights.iter().map(|w| w / mean).collect();
+
+        Ok(ElevationLayers {
+            elevation_offsets,
+            precipitation_weights,
+            temperature_lapse_rate,
+        })
+    }
+
+    pub fn n_layers(&self) -> usize {
+        self.elevation_offsets.len()
+    }
+
+    /// Returns the (temperature, precipitation) of each layer.
+    pub fn extrapolate(
+        &self,
+        temperature: f64,
+        precipitation: f64,
+        day_of_year: usize,
+    ) -> impl Iterator<Item = (f64, f64)> + '_ {
+        let lapse_rate = self
+            .temperature_lapse_rate
+            .unwrap_or(TEMPERATURE_GRADIENT[(day_of_year.max(1) - 1) % 365]);
+        self.elevation_offsets
+            .iter()
+            .zip(&self.precipitation_weights)
+            .map(move |(offset, weight)| {
+                (temperature + offset * lapse_rate, precipitation * weight)
+            })
+    }
+}
+
+/// Loss of the snowpack to sublimation and evaporation, as a fraction of the
+/// snowpack and of the pet, given to the snow models by
+/// `Metadata::sublimation` and disabled by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sublimation {
+    fraction: f64,   // 1/day, of the snowpack
+    pet_factor: f64, // -, of the pet
+}
+
+impl Sublimation {
+    pub fn new(fraction: f64, pet_factor: f64) -> Self {
+        Sublimation {
+            fraction,
+            pet_factor,
+        }
+    }
+
+    /// Returns the loss (mm) of a layer, which can't exceed its snowpack.
+    pub fn compute(&self, snowpack: f64, pet: f64) -> f64 {
+        (self.fraction * snowpack + self.pet_factor * pet)
+            .clamp(0.0, snowpack.max(0.0))
+    }
+}
+
+pub type SnowStatesFn = for<'a, 'b, 'c> fn(
+    ArrayView1<'a, f64>,
+    Data<'b>,
+    &Metadata<'c>,
+) -> Result<SnowStates, Error>;
+
+/// Time series produced by a snow model, in addition to the effective
+/// precipitation passed on to the climate model.
+pub struct SnowStates {
+    pub effective_precipitation: Array1<f64>, // mm/day
+    pub swe: Array2<f64>,                     // mm, timesteps x layers
+    pub thermal_state: Option<Array2<f64>>,   // °C, timesteps x layers
+    pub snow_cover: Array1<f64>,              // 0-1
+    snow_cover_threshold: f64,                // mm
+}
+
+impl SnowStates {
+    pub fn new(
+        metadata: &Metadata,
+        n_timesteps: usize,
+        n_layers: usize,
+        with_thermal_state: bool,
+    ) -> Self {
+        SnowStates {
+            effective_precipitation: Array1::zeros(n_timesteps),
+            swe: Array2::zeros((n_timesteps, n_layers)),
+            thermal_state: with_thermal_state
+                .then(|| Array2::zeros((n_timesteps, n_layers))),
+            snow_cover: Array1::zeros(n_timesteps),
+            snow_cover_threshold: metadata.snow_cover_threshold,
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        t: usize,
+        effective_precipitation: f64,
+        snowpack: &[f64],
+        thermal_state: Option<&[f64]>,
+    ) {
+        self.effective_precipitation[t] = effective_precipitation;
+        self.swe.row_mut(t).assign(&ArrayView1::from(snowpack));
+        self.snow_cover[t] =
+            compute_snow_cover(snowpack, self.snow_cover_threshold);
+        if let (Some(states), Some(layers)) =
+            (self.thermal_state.as_mut(), thermal_state)
+        {
+            states.row_mut(t).assign(&ArrayView1::from(layers));
+        }
+    }
+
+    /// Catchment average of the snow water equivalent (mm).
+    pub fn mean_swe(&self) -> Array1<f64> {
+        self.swe.mean_axis(Axis(1)).unwrap()
+    }
+
+    pub fn to_py_dict<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item(
+            "effective_precipitation",
+            self.effective_precipitation.to_pyarray(py),
+        )?;
+        dict.set_item("swe", self.swe.to_pyarray(py))?;
+        dict.set_item("mean_swe", self.mean_swe().to_pyarray(py))?;
+        if let Some(thermal_state) = &self.thermal_state {
+            dict.set_item("thermal_state", thermal_state.to_pyarray(py))?;
+            dict.set_item(
+                "mean_thermal_state",
+                thermal_state.mean_axis(Axis(1)).unwrap().to_pyarray(py),
+            )?;
+        }
+        dict.set_item("snow_cover", self.snow_cover.to_pyarray(py))?;
+        Ok(dict)
+    }
+}
+
+/// Fractional snow cover of the catchment, comparable to remote sensing
+/// products such as MODIS, from the snowpack of each layer. Each layer is
+/// fully covered above `threshold` mm of snow water equivalent and its cover
+/// decreases linearly to 0 below it.
+pub fn compute_snow_cover(snowpack: &[f64], threshold: f64) -> f64 {
+    let cover: f64 = snowpack
+        .iter()
+        .map(|&swe| {
+            if threshold > 0.0 {
+                (swe / threshold).clamp(0.0, 1.0)
+            } else if swe > 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .sum();
+    cover / snowpack.len() as f64
+}
+
+/// Solid fraction of precipitation used by CemaNeige (USACE, 1956).
+pub fn compute_solid_fraction(temperature: f64) -> f64 {
+    if temperature > 3.0 {
+        0.0
+    } else if temperature < -1.0 {
+        1.0
+    } else {
+        1.0 - (temperature + 1.0) / 4.0
+    }
+}
+
+#[allow(clippy::approx_constant)]
+const TEMPERATURE_GRADIENT: [f64; 365] = [
+    -0.376, -0.374, -0.371, -0.368, -0.366, -0.363, -0.361, -0.358, -0.355,
+    -0.353, -0.350, -0.348, -0.345, -0.343, -0.340, -0.337, -0.335, -0.332,
+    -0.329, -0.327, -0.324, -0.321, -0.319, -0.316, -0.313, -0.311, -0.308,
+    -0.305, -0.303, -0.300, -0.297, -0.295, -0.292, -0.289, -0.287, -0.284,
+    -0.281, -0.279, -0.276, -0.273, -0.271, -0.268, -0.265, -0.263, -0.260,
+    -0.262, -0.264, -0.266, -0.268, -0.270, -0.272, -0.274, -0.277, -0.279,
+    -0.281, -0.283, -0.285, -0.287, -0.289, -0.291, -0.293, -0.295, -0.297,
+    -0.299, -0.301, -0.303, -0.306, -0.308, -0.310, -0.312, -0.314, -0.316,
+    -0.318, -0.320, -0.323, -0.326, -0.330, -0.333, -0.336, -0.339, -0.343,
+    -0.346, -0.349, -0.352, -0.355, -0.359, -0.362, -0.365, -0.368, -0.372,
+    -0.375, -0.378, -0.381, -0.385, -0.388, -0.391, -0.394, -0.397, -0.401,
+    -0.404, -0.407, -0.410, -0.414, -0.417, -0.420, -0.420, -0.421, -0.421,
+    -0.421, -0.422, -0.422, -0.422, -0.423, -0.423, -0.423, -0.424, -0.424,
+    -0.424, -0.425, -0.425, -0.425, -0.426, -0.426, -0.426, -0.427, -0.427,
+    -0.427, -0.428, -0.428, -0.428, -0.429, -0.429, -0.429, -0.430, -0.430,
+    -0.428, -0.425, -0.423, -0.421, -0.419, -0.416, -0.414, -0.412, -0.410,
+    -0.407, -0.405, -0.403, -0.401, -0.398, -0.396, -0.394, -0.392, -0.389,
+    -0.387, -0.385, -0.383, -0.380, -0.378, -0.376, -0.374, -0.371, -0.369,
+    -0.367, -0.365, -0.362, -0.360, -0.362, -0.365, -0.367, -0.369, -0.372,
+    -0.374, -0.376, -0.379, -0.381, -0.383, -0.386, -0.388, -0.390, -0.393,
+    -0.395, -0.397, -0.400, -0.402, -0.404, -0.407, -0.409, -0.411, -0.414,
+    -0.416, -0.418, -0.421, -0.423, -0.425, -0.428, -0.430, -0.431, -0.431,
+    -0.432, -0.433, -0.433, -0.434, -0.435, -0.435, -0.436, -0.436, -0.437,
+    -0.438, -0.438, -0.439, -0.440, -0.440, -0.441, -0.442, -0.442, -0.443,
+    -0.444, -0.444, -0.445, -0.445, -0.446, -0.447, -0.447, -0.448, -0.449,
+    -0.449, -0.450, -0.448, -0.447, -0.445, -0.444, -0.442, -0.440, -0.439,
+    -0.437, -0.435, -0.434, -0.432, -0.431, -0.429, -0.427, -0.426, -0.424,
+    -0.423, -0.421, -0.419, -0.418, -0.416, -0.415, -0.413, -0.411, -0.410,
+    -0.408, -0.406, -0.405, -0.403, -0.402, -0.400, -0.403, -0.405, -0.408,
+    -0.411, -0.413, -0.416, -0.419, -0.421, -0.424, -0.427, -0.429, -0.432,
+    -0.435, -0.437, -0.440, -0.443, -0.445, -0.448, -0.451, -0.453, -0.456,
+    -0.459, -0.461, -0.464, -0.467, -0.469, -0.472, -0.475, -0.477, -0.480,
+    -0.482, -0.483, -0.485, -0.486, -0.488, -0.490, -0.491, -0.493, -0.495,
+    -0.496, -0.498, -0.499, -0.501, -0.503, -0.504, -0.506, -0.507, -0.509,
+    -0.511, -0.512, -0.514, -0.515, -0.517, -0.519, -0.520, -0.522, -0.524,
+    -0.525, -0.527, -0.528, -0.530, -0.526, -0.523, -0.519, -0.515, -0.512,
+    -0.508, -0.504, -0.501, -0.497, -0.493, -0.490, -0.486, -0.482, -0.479,
+    -0.475, -0.471, -0.468, -0.464, -0.460, -0.457, -0.453, -0.449, -0.446,
+    -0.442, -0.438, -0.435, -0.431, -0.427, -0.424, -0.420, -0.417, -0.415,
+    -0.412, -0.410, -0.407, -0.405, -0.402, -0.399, -0.397, -0.394, -0.392,
+    -0.389, -0.386, -0.384, -0.381, -0.379,
+];
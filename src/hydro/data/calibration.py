@@ -4,6 +4,7 @@ from typing import Any, Awaitable, Callable, Literal, assert_never
 import numpy as np
 import numpy.typing as npt
 import polars as pl
+from hydro_rs.calibration.dds import Dds
 from hydro_rs.calibration.sce import Sce
 
 from .climate import day_median
@@ -16,7 +17,7 @@ from .utils import Data
 
 Objective = Literal["rmse", "nse", "kge"]
 Transformation = Literal["log", "sqrt", "none"]
-Algorithm = Literal["sce"]
+Algorithm = Literal["sce", "dds"]
 
 ##########
 # public #
@@ -60,6 +61,21 @@ def get_algorithm_params(
                     "step": 1,
                 },
             }
+        case "dds":
+            return {
+                "max_evaluations": {
+                    "min": 1,
+                    "max": None,
+                    "default": 5000,
+                    "step": 1,
+                },
+                "perturbation": {
+                    "min": 0,
+                    "max": 1,
+                    "default": 0.2,
+                    "step": 0.01,
+                },
+            }
         case _:
             assert_never(algorithm)  # type: ignore
 
@@ -92,6 +108,7 @@ async def calibrate(
         )
         observations = data["discharge"].to_numpy()
 
+        calibration: Sce | Dds
         match algorithm:
             case "sce":
                 calibration = Sce(
@@ -107,31 +124,38 @@ async def calibrate(
                     ],
                     max_evaluations=params["max_evaluations"],
                 )
-                calibration.init(_data, metadata, observations)
-                for _ in range(max_iter):
-                    done, params, _simulation, objectives = calibration.step(
-                        _data, metadata, observations
-                    )
-                    simulation = data.select("date").with_columns(
-                        pl.Series("discharge", _simulation)
-                    )
-                    results = {
-                        "rmse": objectives[0],
-                        "nse": objectives[1],
-                        "kge": objectives[2],
-                    }
-                    if callback is not None:
-                        print(simulation[:10, "discharge"])
-                        print(observations[:10])
-                        await callback(done, simulation, results)
-                    # Yield control to allow I/O processing (e.g., receiving stop message)
-                    await asyncio.sleep(0.001)
-                    if stop_event is not None and stop_event.is_set():
-                        break
-                    if done:
-                        break
-
-                return np.array(params)
-
+            case "dds":
+                calibration = Dds(
+                    climate_model,
+                    snow_model,
+                    objective,
+                    seed=seed,
+                    max_evaluations=params["max_evaluations"],
+                    perturbation=params["perturbation"],
+                )
             case _:
                 assert_never(algorithm)  # type: ignore
+
+        calibration.init(_data, metadata, observations)
+        for _ in range(max_iter):
+            done, params, _simulation, objectives = calibration.step(
+                _data, metadata, observations
+            )
+            simulation = data.select("date").with_columns(
+                pl.Series("discharge", _simulation)
+            )
+            results = {
+                "rmse": objectives[0],
+                "nse": objectives[1],
+                "kge": objectives[2],
+            }
+            if callback is not None:
+                await callback(done, simulation, results)
+            # Yield control to allow I/O processing (e.g., receiving stop message)
+            await asyncio.sleep(0.001)
+            if stop_event is not None and stop_event.is_set():
+                break
+            if done:
+                break
+
+        return np.array(params)
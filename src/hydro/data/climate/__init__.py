@@ -1,9 +1,28 @@
-from . import day_median, gr4j
+from . import (
+    awbm,
+    bucket,
+    day_median,
+    gr2m,
+    gr4h,
+    gr4j,
+    gr5j,
+    pdm,
+    thornthwaite_mather,
+    topmodel,
+)
 from .climate import ClimateModel, get_model
 
 __all__ = [
     "ClimateModel",
+    "awbm",
+    "bucket",
     "day_median",
     "get_model",
+    "gr2m",
+    "gr4h",
     "gr4j",
+    "gr5j",
+    "pdm",
+    "thornthwaite_mather",
+    "topmodel",
 ]
@@ -0,0 +1,24 @@
+import numpy as np
+import numpy.typing as npt
+import polars as pl
+from hydro_rs.climate import awbm
+
+from ..hydro import Metadata
+from ..utils import Data
+
+
+def init() -> tuple[npt.NDArray[np.float64], npt.NDArray[np.float64]]:
+    # parameters: c1, c2, c3, bfi, k_base, k_surface
+    return awbm.init()
+
+
+def simulate(
+    params: npt.NDArray[np.float64], data: pl.DataFrame, metadata: Metadata
+) -> npt.NDArray[np.float64]:
+    _data = Data(
+        data["precipitation"].to_numpy(),
+        data["temperature"].to_numpy(),
+        data["pet"].to_numpy(),
+        data["date"].dt.ordinal_day().to_numpy().astype(np.uintp),
+    )
+    return awbm.simulate(params, _data, metadata)
@@ -1,13 +1,35 @@
 from typing import Literal, assert_never
 
 from ..utils import Model
-from . import day_median, gr4j
+from . import (
+    awbm,
+    bucket,
+    day_median,
+    gr2m,
+    gr4h,
+    gr4j,
+    gr5j,
+    pdm,
+    thornthwaite_mather,
+    topmodel,
+)
 
 #########
 # types #
 #########
 
-ClimateModel = Literal["day_median", "gr4j", "bucket"]
+ClimateModel = Literal[
+    "day_median",
+    "gr4j",
+    "gr5j",
+    "gr2m",
+    "gr4h",
+    "awbm",
+    "topmodel",
+    "thornthwaite_mather",
+    "pdm",
+    "bucket",
+]
 
 ##########
 # public #
@@ -20,7 +42,23 @@ def get_model(model: ClimateModel) -> Model:
             return Model(day_median.init, day_median.simulate)
         case "gr4j":
             return Model(gr4j.init, gr4j.simulate)
+        case "gr5j":
+            return Model(gr5j.init, gr5j.simulate)
+        case "gr2m":
+            return Model(gr2m.init, gr2m.simulate)
+        case "gr4h":
+            return Model(gr4h.init, gr4h.simulate)
+        case "awbm":
+            return Model(awbm.init, awbm.simulate)
+        case "topmodel":
+            return Model(topmodel.init, topmodel.simulate)
+        case "thornthwaite_mather":
+            return Model(
+                thornthwaite_mather.init, thornthwaite_mather.simulate
+            )
+        case "pdm":
+            return Model(pdm.init, pdm.simulate)
         case "bucket":
-            raise NotImplementedError()
+            return Model(bucket.init, bucket.simulate)
         case _:
             assert_never(model)  # type: ignore
@@ -11,7 +11,10 @@ from . import hydro, precipitation, weather
 # types #
 #########
 
-PetModel = Literal["oudin"]
+# models only requiring the mean temperature
+PetModel = Literal[
+    "oudin", "hamon", "priestley_taylor", "blaney_criddle", "mcguinness_bordne"
+]
 
 ##########
 # public #
@@ -80,21 +83,11 @@ async def _add_pet_data(
     else:
         path.parent.mkdir(exist_ok=True, parents=True)
         hydro_metadata = await hydro.read_metadata(id)
-        match (pet_model):
-            case "oudin":
-                temperature = data["temperature"].to_numpy()
-                day_of_year = (
-                    data["date"].dt.ordinal_day().cast(pl.Float64).to_numpy()
-                )
-                pet_data_ = pet.oudin.simulate(
-                    temperature, day_of_year, hydro_metadata.lat
-                )
-                pet_data = pl.DataFrame(
-                    {"date": data["date"], "pet": pet_data_}
-                )
-                pet_data.write_ipc(path)
-            case _:
-                raise NotImplementedError(
-                    f"The pet model {pet_model} isn't implemented."
-                )
+        temperature = data["temperature"].to_numpy()
+        day_of_year = data["date"].dt.ordinal_day().cast(pl.Float64).to_numpy()
+        pet_data_ = pet.simulate(
+            pet_model, temperature, day_of_year, hydro_metadata.lat
+        )
+        pet_data = pl.DataFrame({"date": data["date"], "pet": pet_data_})
+        pet_data.write_ipc(path)
     return data.join(pet_data, on="date").sort("date")
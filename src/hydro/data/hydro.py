@@ -29,6 +29,15 @@ class Metadata(NamedTuple):
     area: float
     elevation_layers: npt.NDArray[np.float64]
     median_elevation: float
+    timestep: float | None = None  # h
+    glacier_fraction: float = 0.0
+    temperature_lapse_rate: float | None = None  # °C/100m
+    precipitation_gradient: float | None = None  # 1/m
+    sublimation_fraction: float = 0.0  # 1/day
+    sublimation_pet_factor: float = 0.0
+    snow_cover_threshold: float = 10.0  # mm
+    topographic_index: npt.NDArray[np.float64] | None = None
+    topographic_index_fractions: npt.NDArray[np.float64] | None = None
 
 
 ##########
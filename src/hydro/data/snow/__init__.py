@@ -1,7 +1,11 @@
-from . import cemaneige
+from . import cemaneige, cemaneige_lh, glacier, hbv, hock
 from .snow import SnowModel
 
 __all__ = [
     "SnowModel",
     "cemaneige",
+    "cemaneige_lh",
+    "glacier",
+    "hbv",
+    "hock",
 ]
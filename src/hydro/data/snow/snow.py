@@ -1,13 +1,13 @@
 from typing import Literal, assert_never
 
 from ..utils import Model
-from . import cemaneige
+from . import cemaneige, cemaneige_lh, glacier, hbv, hock
 
 #########
 # types #
 #########
 
-SnowModel = Literal["cemaneige"]
+SnowModel = Literal["cemaneige", "cemaneige_lh", "hbv", "hock", "glacier"]
 
 ##########
 # public #
@@ -18,5 +18,13 @@ def get_model(model: SnowModel) -> Model:
     match (model):
         case "cemaneige":
             return Model(cemaneige.init, cemaneige.simulate)
+        case "cemaneige_lh":
+            return Model(cemaneige_lh.init, cemaneige_lh.simulate)
+        case "hbv":
+            return Model(hbv.init, hbv.simulate)
+        case "hock":
+            return Model(hock.init, hock.simulate)
+        case "glacier":
+            return Model(glacier.init, glacier.simulate)
         case _:
             assert_never(model)  # type: ignore